@@ -1,14 +1,39 @@
+use crate::runner::run_scenario;
+use crate::scenario::ScenarioConfig;
+use crate::sim::envelope::EnvelopeUpdate;
 use crate::telemetry::TelemetryRow;
-use std::io::{self, BufRead, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::net::{TcpListener, TcpStream};
 
-pub fn run_http_server(bind_addr: &str, telemetry: Vec<TelemetryRow>) -> io::Result<()> {
+/// Largest request body read; longer bodies are answered with 413.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// Most envelope updates the server keeps across POST requests.
+const MAX_ENVELOPE_UPDATES: usize = 10_000;
+
+/// Scenario being served and the telemetry of its latest run.
+struct ApiState {
+    scenario: ScenarioConfig,
+    telemetry: Vec<TelemetryRow>,
+}
+
+pub fn run_http_server(
+    bind_addr: &str,
+    scenario: ScenarioConfig,
+    telemetry: Vec<TelemetryRow>,
+) -> io::Result<()> {
     let listener = TcpListener::bind(bind_addr)?;
     println!("HTTP API listening on http://{bind_addr}");
-    serve(listener, telemetry)
+    serve(
+        listener,
+        ApiState {
+            scenario,
+            telemetry,
+        },
+    )
 }
 
-fn serve(listener: TcpListener, telemetry: Vec<TelemetryRow>) -> io::Result<()> {
+fn serve(listener: TcpListener, mut state: ApiState) -> io::Result<()> {
     for incoming in listener.incoming() {
         let stream = match incoming {
             Ok(stream) => stream,
@@ -18,7 +43,7 @@ fn serve(listener: TcpListener, telemetry: Vec<TelemetryRow>) -> io::Result<()>
             }
         };
 
-        if let Err(err) = handle_connection(stream, &telemetry) {
+        if let Err(err) = handle_connection(stream, &mut state) {
             eprintln!("warning: failed to handle request: {err}");
         }
     }
@@ -26,15 +51,16 @@ fn serve(listener: TcpListener, telemetry: Vec<TelemetryRow>) -> io::Result<()>
     Ok(())
 }
 
-fn handle_connection(mut stream: TcpStream, telemetry: &[TelemetryRow]) -> io::Result<()> {
+fn handle_connection(mut stream: TcpStream, state: &mut ApiState) -> io::Result<()> {
     let mut request_line = String::new();
-    {
+    let body = {
         let mut reader = BufReader::new(&mut stream);
         if reader.read_line(&mut request_line)? == 0 {
             return Ok(());
         }
 
-        // Consume and ignore headers.
+        // Consume headers, keeping only the body length.
+        let mut content_length = None;
         loop {
             let mut line = String::new();
             if reader.read_line(&mut line)? == 0 {
@@ -43,25 +69,83 @@ fn handle_connection(mut stream: TcpStream, telemetry: &[TelemetryRow]) -> io::R
             if line == "\r\n" {
                 break;
             }
+            if let Some((name, value)) = line.split_once(':')
+                && name.trim().eq_ignore_ascii_case("content-length")
+            {
+                content_length = Some(value.trim().to_string());
+            }
         }
-    }
+        let method = request_line.split_whitespace().next().unwrap_or("");
+        match body_length(method, content_length.as_deref()) {
+            Ok(length) => {
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body)?;
+                Ok(body)
+            }
+            Err(rejection) => Err(rejection),
+        }
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err((status, err)) => {
+            let body = serde_json::json!({ "error": err }).to_string();
+            return write_response(&mut stream, status, "application/json", &body);
+        }
+    };
 
     let request_line = request_line.trim_end_matches(['\r', '\n']);
     let mut parts = request_line.split_whitespace();
     let method = parts.next().unwrap_or("");
     let target = parts.next().unwrap_or("");
+    let (path, query) = split_target(target);
 
+    if method == "POST" && path == "/envelope" {
+        let updates = match parse_envelope_updates(&String::from_utf8_lossy(&body)) {
+            Ok(updates) => updates,
+            Err(err) => {
+                let body = serde_json::json!({ "error": err }).to_string();
+                return write_response(&mut stream, "400 Bad Request", "application/json", &body);
+            }
+        };
+        if state.scenario.envelope.updates().len() + updates.len() > MAX_ENVELOPE_UPDATES {
+            let body = serde_json::json!({
+                "error": format!("at most {MAX_ENVELOPE_UPDATES} envelope updates can be stored"),
+            })
+            .to_string();
+            return write_response(&mut stream, "400 Bad Request", "application/json", &body);
+        }
+        for update in updates {
+            state.scenario.envelope.push(update);
+        }
+        // The served run is already complete, so this is a what-if: the whole
+        // scenario is re-run from step 0 and the controller knows the updated
+        // envelope at every step, including those before the update was posted.
+        let result = run_scenario(&state.scenario, false);
+        state.telemetry = result.telemetry;
+        let body = serde_json::json!({
+            "updates": state.scenario.envelope.updates(),
+            "import_violations": result.kpis.envelope.as_ref().map_or(0, |e| e.import_violations),
+            "export_violations": result.kpis.envelope.as_ref().map_or(0, |e| e.export_violations),
+        })
+        .to_string();
+        return write_response(&mut stream, "200 OK", "application/json", &body);
+    }
     if method != "GET" {
         return write_response(
             &mut stream,
             "405 Method Not Allowed",
             "application/json",
-            "{\"error\":\"only GET is supported\"}",
+            "{\"error\":\"only GET (and POST on /envelope) is supported\"}",
         );
     }
 
-    let (path, query) = split_target(target);
+    let telemetry = &state.telemetry;
     match path {
+        "/envelope" => {
+            let body = serde_json::to_string(state.scenario.envelope.updates())
+                .map_err(|err| io::Error::other(format!("serialize envelope: {err}")))?;
+            write_response(&mut stream, "200 OK", "application/json", &body)
+        }
         "/state" => {
             if let Some(snapshot) = telemetry.last() {
                 let body = serde_json::to_string(snapshot)
@@ -110,6 +194,64 @@ fn handle_connection(mut stream: TcpStream, telemetry: &[TelemetryRow]) -> io::R
     }
 }
 
+/// Body length declared by `Content-Length`, or the status and error to
+/// answer with. POST requests must declare it; bodies over
+/// [`MAX_BODY_BYTES`] are refused before anything is read.
+fn body_length(
+    method: &str,
+    content_length: Option<&str>,
+) -> Result<usize, (&'static str, String)> {
+    let Some(value) = content_length else {
+        if method == "POST" {
+            return Err((
+                "400 Bad Request",
+                "missing `Content-Length` header".to_string(),
+            ));
+        }
+        return Ok(0);
+    };
+    let length = value.parse::<usize>().map_err(|_| {
+        (
+            "400 Bad Request",
+            format!("invalid `Content-Length` header `{value}`"),
+        )
+    })?;
+    if length > MAX_BODY_BYTES {
+        return Err((
+            "413 Payload Too Large",
+            format!("request body must be at most {MAX_BODY_BYTES} bytes"),
+        ));
+    }
+    Ok(length)
+}
+
+/// Parses one envelope update, or an array of them, from a JSON body.
+fn parse_envelope_updates(body: &str) -> Result<Vec<EnvelopeUpdate>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|err| format!("invalid JSON: {err}"))?;
+    let updates = if value.is_array() {
+        serde_json::from_value(value)
+    } else {
+        serde_json::from_value(value).map(|update| vec![update])
+    }
+    .map_err(|err| format!("invalid envelope update: {err}"))?;
+    for update in &updates {
+        let EnvelopeUpdate {
+            import_kw,
+            export_kw,
+            ..
+        } = update;
+        if !(import_kw.is_finite()
+            && *import_kw >= 0.0
+            && export_kw.is_finite()
+            && *export_kw >= 0.0)
+        {
+            return Err("`import_kw` and `export_kw` must be >= 0".to_string());
+        }
+    }
+    Ok(updates)
+}
+
 fn split_target(target: &str) -> (&str, &str) {
     if let Some((path, query)) = target.split_once('?') {
         (path, query)
@@ -135,13 +277,13 @@ fn parse_from_to(query: &str) -> io::Result<(Option<usize>, Option<usize>)> {
         }
     }
 
-    if let (Some(start), Some(end)) = (from, to) {
-        if start > end {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "query parameter `from` must be <= `to`",
-            ));
-        }
+    if let (Some(start), Some(end)) = (from, to)
+        && start > end
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "query parameter `from` must be <= `to`",
+        ));
     }
 
     Ok((from, to))
@@ -172,7 +314,7 @@ fn write_response(
 
 #[cfg(test)]
 mod tests {
-    use super::parse_from_to;
+    use super::{MAX_BODY_BYTES, body_length, parse_envelope_updates, parse_from_to};
 
     #[test]
     fn parses_query_range() {
@@ -191,4 +333,38 @@ mod tests {
         assert!(parse_from_to("from=abc").is_err());
         assert!(parse_from_to("from=5&to=1").is_err());
     }
+
+    #[test]
+    fn checks_declared_body_length() {
+        assert_eq!(body_length("GET", None), Ok(0));
+        assert_eq!(body_length("POST", Some("42")), Ok(42));
+        assert_eq!(body_length("POST", None).unwrap_err().0, "400 Bad Request");
+        assert_eq!(
+            body_length("POST", Some("lots")).unwrap_err().0,
+            "400 Bad Request"
+        );
+        let too_long = (MAX_BODY_BYTES + 1).to_string();
+        assert_eq!(
+            body_length("POST", Some(&too_long)).unwrap_err().0,
+            "413 Payload Too Large"
+        );
+    }
+
+    #[test]
+    fn parses_envelope_updates_one_or_many() {
+        let one = parse_envelope_updates(r#"{"start_step":12,"import_kw":3,"export_kw":0}"#)
+            .expect("single update should parse");
+        assert_eq!(one.len(), 1);
+        assert_eq!(one[0].start_step, 12);
+        let many = parse_envelope_updates(
+            r#"[{"start_step":0,"import_kw":5,"export_kw":4},{"start_step":6,"import_kw":2,"export_kw":1}]"#,
+        )
+        .expect("array should parse");
+        assert_eq!(many.len(), 2);
+
+        assert!(
+            parse_envelope_updates(r#"{"start_step":0,"import_kw":-1,"export_kw":0}"#).is_err()
+        );
+        assert!(parse_envelope_updates(r#"{"start_step":0}"#).is_err());
+    }
 }
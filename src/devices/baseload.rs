@@ -43,6 +43,9 @@ pub struct BaseLoad {
     /// Number of time steps per simulated day
     pub steps_per_day: usize,
 
+    /// Demand sampled for a timestep, so noise is drawn once per step
+    sampled: Option<(usize, f32)>,
+
     /// Random number generator for noise generation
     rng: StdRng,
 }
@@ -76,6 +79,7 @@ impl BaseLoad {
             phase_rad,
             noise_std,
             steps_per_day: steps_per_day.max(1),
+            sampled: None,
             rng: StdRng::seed_from_u64(seed),
         }
     }
@@ -89,7 +93,8 @@ impl Device for BaseLoad {
     /// - A sinusoidal daily pattern with specified amplitude and phase
     /// - Random Gaussian noise with specified standard deviation
     ///
-    /// The demand is guaranteed to be non-negative.
+    /// The demand is guaranteed to be non-negative. Repeated calls for the
+    /// same timestep return the same demand.
     ///
     /// # Arguments
     ///
@@ -99,13 +104,20 @@ impl Device for BaseLoad {
     ///
     /// The power demand in kilowatts at the specified time step
     fn power_kw(&mut self, context: &DeviceContext) -> f32 {
+        if let Some((t, kw)) = self.sampled
+            && t == context.timestep
+        {
+            return kw;
+        }
+
         let day_pos = (context.timestep % self.steps_per_day) as f32 / self.steps_per_day as f32; // [0,1)
         let angle = 2.0 * std::f32::consts::PI * day_pos + self.phase_rad;
         let sinus = angle.sin();
 
         let noise = gaussian_noise(&mut self.rng, self.noise_std);
-        let kw = self.base_kw + self.amp_kw * sinus + noise;
-        kw.max(0.0) // no negative demand
+        let kw = (self.base_kw + self.amp_kw * sinus + noise).max(0.0); // no negative demand
+        self.sampled = Some((context.timestep, kw));
+        kw
     }
 
     fn device_type(&self) -> &'static str {
@@ -208,4 +220,12 @@ mod tests {
 
         assert!(!all_same);
     }
+
+    #[test]
+    fn test_repeated_timestep_returns_same_sample() {
+        let mut load = BaseLoad::new(1.0, 0.0, 0.0, 0.5, 10, 42);
+        let first = load.power_kw(&ctx(3));
+        assert_eq!(load.power_kw(&ctx(3)), first);
+        assert_ne!(load.power_kw(&ctx(4)), first);
+    }
 }
@@ -1,11 +1,54 @@
+use crate::devices::degradation::BatteryDegradation;
+use crate::devices::dynamics::{Actuator, ActuatorLimits};
+use crate::devices::inverter::Inverter;
+use crate::devices::thermal::BatteryThermal;
 use crate::devices::types::{Device, DeviceContext};
 
+/// Contractual operating limits and standby losses of a [`Battery`].
+#[derive(Debug, Clone)]
+pub struct BatteryEnvelope {
+    /// Lowest SoC reachable when the reserve may be used (0.0 to 1.0)
+    pub soc_min: f32,
+
+    /// Highest SoC reachable by charging (0.0 to 1.0)
+    pub soc_max: f32,
+
+    /// Backup reserve SoC that normal dispatch may not discharge below
+    pub reserve_soc: f32,
+
+    /// Fraction of stored energy lost per day while standing
+    pub self_discharge_per_day: f32,
+
+    /// Auxiliary/parasitic load (BMS, cooling) drawn at the terminals in kilowatts
+    pub aux_load_kw: f32,
+}
+
+impl Default for BatteryEnvelope {
+    fn default() -> Self {
+        Self {
+            soc_min: 0.0,
+            soc_max: 1.0,
+            reserve_soc: 0.0,
+            self_discharge_per_day: 0.0,
+            aux_load_kw: 0.0,
+        }
+    }
+}
+
 /// A battery energy storage system that can charge and discharge electricity.
 ///
 /// `Battery` models a battery with configurable capacity, charge/discharge rates,
 /// and efficiencies. It maintains its state of charge (SOC) and enforces operational
 /// constraints when given power setpoints.
 ///
+/// An optional [`BatteryEnvelope`] restricts the usable SoC window, holds back a
+/// backup reserve that is only released when the context grants reserve access
+/// (demand response or outage), and adds self-discharge and auxiliary load.
+///
+/// An optional [`BatteryThermal`] model tracks cell temperature, derates
+/// charge/discharge power at temperature extremes and replaces the fixed
+/// `eta_c`/`eta_d` with an efficiency that falls with C-rate.
+///
 /// # Power Flow Convention
 /// - Positive power: Discharging (supplying power to the grid)
 /// - Negative power: Charging (consuming power from the grid)
@@ -58,6 +101,30 @@ pub struct Battery {
 
     /// Number of time steps per day
     pub steps_per_day: usize,
+
+    /// Capacity of the battery when new, in kilowatt-hours
+    pub nominal_capacity_kwh: f32,
+
+    /// Optional aging model that shrinks `capacity_kwh` over time
+    pub degradation: Option<BatteryDegradation>,
+
+    /// SoC limits, backup reserve and standby losses
+    pub envelope: BatteryEnvelope,
+
+    /// Optional cell temperature model with power derating
+    pub thermal: Option<BatteryThermal>,
+
+    /// Ramp, latency and minimum on/off limits applied to setpoints
+    dynamics: Actuator,
+
+    /// Optional inverter limiting kVA and providing reactive power
+    pub inverter: Option<Inverter>,
+
+    /// Reactive power injected in the last step (kVAR)
+    kvar: f32,
+
+    /// Cumulative absolute SoC change, used for equivalent full cycles
+    soc_throughput: f32,
 }
 
 impl Battery {
@@ -85,6 +152,149 @@ impl Battery {
             eta_c,
             eta_d,
             steps_per_day,
+            nominal_capacity_kwh: capacity_kwh,
+            degradation: None,
+            envelope: BatteryEnvelope::default(),
+            thermal: None,
+            dynamics: Actuator::new(ActuatorLimits::default(), steps_per_day),
+            inverter: None,
+            kvar: 0.0,
+            soc_throughput: 0.0,
+        }
+    }
+
+    /// Applies an operating envelope, clamping the current SoC into its window.
+    pub fn with_envelope(mut self, envelope: BatteryEnvelope) -> Self {
+        assert!(0.0 <= envelope.soc_min && envelope.soc_min < envelope.soc_max);
+        assert!(envelope.soc_max <= 1.0);
+        assert!((envelope.soc_min..=envelope.soc_max).contains(&envelope.reserve_soc));
+        assert!((0.0..1.0).contains(&envelope.self_discharge_per_day));
+        assert!(envelope.aux_load_kw >= 0.0);
+
+        self.soc = self.soc.clamp(envelope.soc_min, envelope.soc_max);
+        self.envelope = envelope;
+        self
+    }
+
+    /// Lowest SoC that dispatch may discharge to.
+    fn soc_floor(&self, reserve_access: bool) -> f32 {
+        if reserve_access {
+            self.envelope.soc_min
+        } else {
+            self.envelope.reserve_soc.max(self.envelope.soc_min)
+        }
+    }
+
+    fn dt_hours(&self) -> f32 {
+        24.0 / self.steps_per_day as f32
+    }
+
+    /// Applies ramp-rate, latency and minimum on/off limits to setpoints.
+    pub fn with_dynamics(mut self, limits: ActuatorLimits) -> Self {
+        self.dynamics = Actuator::new(limits, self.steps_per_day);
+        self
+    }
+
+    /// Setpoint power (kW) the actuator limits withheld in the last step.
+    pub fn command_shortfall_kw(&self) -> f32 {
+        self.dynamics.last_shortfall_kw()
+    }
+
+    /// Number of steps in which the setpoint was not honored due to actuator limits.
+    pub fn unhonored_commands(&self) -> usize {
+        self.dynamics.unhonored_steps()
+    }
+
+    /// Connects the battery through an inverter with a kVA limit and grid-support functions.
+    pub fn with_inverter(mut self, inverter: Inverter) -> Self {
+        self.inverter = Some(inverter);
+        self
+    }
+
+    /// Reactive power injected in the last step (kVAR, positive = injected).
+    pub fn reactive_kvar(&self) -> f32 {
+        self.kvar
+    }
+
+    /// Attaches a cell temperature model to the battery.
+    pub fn with_thermal(mut self, thermal: BatteryThermal) -> Self {
+        self.thermal = Some(thermal);
+        self
+    }
+
+    /// Cell temperature in °C, if a thermal model is attached.
+    pub fn temp_c(&self) -> Option<f32> {
+        self.thermal.as_ref().map(BatteryThermal::temp_c)
+    }
+
+    /// Fraction of rated power currently allowed by cell temperature.
+    pub fn derate_factor(&self) -> f32 {
+        self.thermal
+            .as_ref()
+            .map(BatteryThermal::derate_factor)
+            .unwrap_or(1.0)
+    }
+
+    /// Discharge power (kW) available for one step, limited by rating and usable energy.
+    pub fn available_discharge_kw(&self, reserve_access: bool) -> f32 {
+        let rated_kw = self.max_discharge_kw * self.derate_factor();
+        let (_, eta_d) = self.effective_efficiencies(rated_kw);
+        let usable_kwh = (self.soc - self.soc_floor(reserve_access)).max(0.0) * self.capacity_kwh;
+        rated_kw.min(usable_kwh * eta_d / self.dt_hours())
+    }
+
+    /// Charge power (kW) available for one step, limited by rating and headroom.
+    pub fn available_charge_kw(&self) -> f32 {
+        let rated_kw = self.max_charge_kw * self.derate_factor();
+        let (eta_c, _) = self.effective_efficiencies(rated_kw);
+        let headroom_kwh = (self.envelope.soc_max - self.soc).max(0.0) * self.capacity_kwh;
+        rated_kw.min(headroom_kwh / eta_c / self.dt_hours())
+    }
+
+    /// Attaches an aging model to the battery.
+    pub fn with_degradation(mut self, degradation: BatteryDegradation) -> Self {
+        self.degradation = Some(degradation);
+        self
+    }
+
+    /// Number of equivalent full cycles (two full SoC swings each) performed so far.
+    pub fn equivalent_full_cycles(&self) -> f32 {
+        self.soc_throughput / 2.0
+    }
+
+    /// Capacity lost to aging as a fraction of nominal capacity.
+    pub fn capacity_fade(&self) -> f32 {
+        1.0 - self.capacity_kwh / self.nominal_capacity_kwh
+    }
+
+    /// Charge/discharge efficiencies at `power_kw` after accounting for resistance growth.
+    ///
+    /// Without a thermal model, losses scale with internal resistance, so
+    /// `1 - eta` grows by the resistance factor reported by the aging model.
+    /// With a thermal model, efficiency depends on C-rate and cell temperature.
+    fn effective_efficiencies(&self, power_kw: f32) -> (f32, f32) {
+        let factor = self
+            .degradation
+            .as_ref()
+            .map(BatteryDegradation::resistance_factor)
+            .unwrap_or(1.0);
+        if let Some(thermal) = &self.thermal {
+            let c_rate = power_kw.abs() / self.nominal_capacity_kwh;
+            let eta = thermal.efficiency(c_rate, factor);
+            return (eta, eta);
+        }
+        let eta_c = (1.0 - (1.0 - self.eta_c) * factor).max(0.01);
+        let eta_d = (1.0 - (1.0 - self.eta_d) * factor).max(0.01);
+        (eta_c, eta_d)
+    }
+
+    /// Updates cycle counting and aging after the SoC has moved from `soc_before`.
+    fn record_step(&mut self, soc_before: f32, dt_hours: f32) {
+        self.soc_throughput += (self.soc - soc_before).abs();
+
+        if let Some(degradation) = self.degradation.as_mut() {
+            degradation.update(self.soc, dt_hours);
+            self.capacity_kwh = self.nominal_capacity_kwh * (1.0 - degradation.total_fade());
         }
     }
 }
@@ -110,46 +320,73 @@ impl Device for Battery {
     ///
     /// The actual power output in kW after applying constraints
     fn power_kw(&mut self, context: &DeviceContext) -> f32 {
-        let setpoint_kw = context.setpoint_kw.unwrap_or(0.0);
-        let dt_hours = 24.0 / self.steps_per_day as f32;
-
-        // First enforce kW limits
+        let setpoint_kw = self.dynamics.command_kw(context.setpoint_kw.unwrap_or(0.0));
+        let dt_hours = self.dt_hours();
+        let derate = self.derate_factor();
+
+        // Standby losses accrue regardless of dispatch.
+        let retained = (1.0 - self.envelope.self_discharge_per_day).powf(dt_hours / 24.0);
+        self.soc *= retained;
+        let soc_before = self.soc;
+        let soc_floor = self.soc_floor(context.reserve_access);
+        let soc_ceiling = self.envelope.soc_max;
+
+        // First enforce kW limits (derated by cell temperature)
         let cmd_kw = if setpoint_kw >= 0.0 {
             // Discharge (positive)
-            setpoint_kw.min(self.max_discharge_kw)
+            setpoint_kw.min(self.max_discharge_kw * derate)
         } else {
             // Charge (negative)
-            setpoint_kw.max(-self.max_charge_kw)
+            setpoint_kw.max(-self.max_charge_kw * derate)
         };
+        let cmd_kw = match &self.inverter {
+            Some(inverter) => inverter.limit_active_kw(cmd_kw, context.voltage_pu),
+            None => cmd_kw,
+        };
+        let (eta_c, eta_d) = self.effective_efficiencies(cmd_kw);
 
         // Enforce SOC limits
-        if cmd_kw > 0.0 {
+        let actual_kw = if cmd_kw > 0.0 {
             // Discharge
-            let max_kwh_this_step = self.soc * self.capacity_kwh * self.eta_d;
+            let max_kwh_this_step = (self.soc - soc_floor).max(0.0) * self.capacity_kwh * eta_d;
             let max_kw_soc = max_kwh_this_step / dt_hours;
             let actual_kw = cmd_kw.min(max_kw_soc.max(0.0));
 
             // Update SOC
-            self.soc -= (actual_kw * dt_hours) / (self.capacity_kwh * self.eta_d);
+            self.soc -= (actual_kw * dt_hours) / (self.capacity_kwh * eta_d);
             self.soc = self.soc.clamp(0.0, 1.0);
 
             actual_kw
         } else if cmd_kw < 0.0 {
             // Charge - limit by available capacity
             let cmd_abs = -cmd_kw;
-            let max_kwh_this_step = (1.0 - self.soc) * self.capacity_kwh / self.eta_c;
+            let max_kwh_this_step = (soc_ceiling - self.soc).max(0.0) * self.capacity_kwh / eta_c;
             let max_kw_soc = max_kwh_this_step / dt_hours;
             let actual_abs_kw = cmd_abs.min(max_kw_soc.max(0.0));
-            let actual_kw = -actual_abs_kw;
 
             // Update SOC
-            self.soc += (actual_abs_kw * dt_hours * self.eta_c) / self.capacity_kwh;
+            self.soc += (actual_abs_kw * dt_hours * eta_c) / self.capacity_kwh;
             self.soc = self.soc.clamp(0.0, 1.0);
 
-            actual_kw
+            -actual_abs_kw
         } else {
             0.0 // No action if setpoint is exactly zero
+        };
+
+        self.record_step(soc_before, dt_hours);
+        self.dynamics.record_output(actual_kw);
+        self.kvar = self.inverter.as_ref().map_or(0.0, |inverter| {
+            inverter.reactive_kvar(actual_kw, context.voltage_pu)
+        });
+
+        if let Some(thermal) = self.thermal.as_mut() {
+            let eta = if actual_kw > 0.0 { eta_d } else { eta_c };
+            let heat_kw = actual_kw.abs() * (1.0 - eta);
+            thermal.update(heat_kw, context.ambient_temp_c, dt_hours);
         }
+
+        // Auxiliary load is drawn at the terminals, reducing net output.
+        actual_kw - self.envelope.aux_load_kw
     }
 
     fn device_type(&self) -> &'static str {
@@ -160,6 +397,9 @@ impl Device for Battery {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::devices::degradation::DegradationParams;
+    use crate::devices::inverter::{ReactivePowerMode, VoltVarCurve, VoltWattCurve};
+    use crate::devices::thermal::ThermalParams;
 
     #[test]
     fn test_new_battery() {
@@ -290,4 +530,150 @@ mod tests {
         // We should get approximately 10kWh * 0.9 (discharge efficiency) = 9kWh
         assert!((energy_delivered - 9.0).abs() < 0.1);
     }
+
+    fn enveloped(soc: f32) -> Battery {
+        Battery::new(10.0, soc, 10.0, 10.0, 1.0, 1.0, 24).with_envelope(BatteryEnvelope {
+            soc_min: 0.1,
+            soc_max: 0.9,
+            reserve_soc: 0.3,
+            ..BatteryEnvelope::default()
+        })
+    }
+
+    #[test]
+    fn test_normal_dispatch_stops_at_reserve() {
+        let mut battery = enveloped(0.5);
+        let kw = battery.power_kw(&DeviceContext::with_setpoint(0, 10.0));
+        assert!((kw - 2.0).abs() < 1e-5);
+        assert!((battery.soc - 0.3).abs() < 1e-6);
+        assert_eq!(battery.available_discharge_kw(false), 0.0);
+    }
+
+    #[test]
+    fn test_reserve_access_discharges_to_soc_min() {
+        let mut battery = enveloped(0.3);
+        let context = DeviceContext::with_setpoint(0, 10.0).with_reserve_access();
+        let kw = battery.power_kw(&context);
+        assert!((kw - 2.0).abs() < 1e-5);
+        assert!((battery.soc - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_charge_stops_at_soc_max() {
+        let mut battery = enveloped(0.8);
+        let kw = battery.power_kw(&DeviceContext::with_setpoint(0, -10.0));
+        assert!((kw + 1.0).abs() < 1e-5);
+        assert!((battery.soc - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_envelope_clamps_initial_soc() {
+        assert_eq!(enveloped(1.0).soc, 0.9);
+    }
+
+    #[test]
+    fn test_self_discharge_and_aux_load() {
+        let mut battery =
+            Battery::new(10.0, 0.5, 5.0, 5.0, 1.0, 1.0, 24).with_envelope(BatteryEnvelope {
+                self_discharge_per_day: 0.1,
+                aux_load_kw: 0.2,
+                ..BatteryEnvelope::default()
+            });
+
+        let mut net_kwh = 0.0;
+        for t in 0..24 {
+            net_kwh += battery.power_kw(&DeviceContext::new(t));
+        }
+
+        assert!((battery.soc - 0.45).abs() < 1e-4);
+        assert!((net_kwh + 4.8).abs() < 1e-4);
+    }
+
+    fn with_thermal(initial_temp_c: f32) -> Battery {
+        Battery::new(10.0, 0.5, 5.0, 5.0, 0.95, 0.95, 24).with_thermal(BatteryThermal::new(
+            ThermalParams::for_capacity(10.0),
+            initial_temp_c,
+        ))
+    }
+
+    #[test]
+    fn test_hot_battery_is_derated() {
+        let mut battery = with_thermal(47.5);
+        let context = DeviceContext::with_setpoint(0, 5.0).with_ambient_temp_c(47.5);
+        let kw = battery.power_kw(&context);
+        assert!((kw - 2.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_thermal_efficiency_depends_on_c_rate() {
+        // Moving the same energy at a lower rate loses less of it.
+        let mut fast = with_thermal(25.0);
+        let mut slow = with_thermal(25.0);
+        fast.power_kw(&DeviceContext::with_setpoint(0, 4.0).with_ambient_temp_c(25.0));
+        for t in 0..4 {
+            slow.power_kw(&DeviceContext::with_setpoint(t, 1.0).with_ambient_temp_c(25.0));
+        }
+        assert!(slow.soc > fast.soc);
+    }
+
+    #[test]
+    fn test_dispatch_heats_cells() {
+        let mut battery = with_thermal(25.0);
+        battery.power_kw(&DeviceContext::with_setpoint(0, 5.0).with_ambient_temp_c(25.0));
+        assert!(battery.temp_c().expect("thermal model attached") > 25.0);
+    }
+
+    #[test]
+    fn test_ramp_limit_reports_unhonored_setpoint() {
+        let mut battery =
+            Battery::new(10.0, 0.5, 5.0, 5.0, 0.95, 0.95, 1440).with_dynamics(ActuatorLimits {
+                ramp_kw_per_min: Some(1.0),
+                ..ActuatorLimits::default()
+            });
+        assert!((battery.power_kw(&DeviceContext::with_setpoint(0, 5.0)) - 1.0).abs() < 1e-6);
+        assert!((battery.power_kw(&DeviceContext::with_setpoint(1, 5.0)) - 2.0).abs() < 1e-6);
+        assert!((battery.command_shortfall_kw() - 3.0).abs() < 1e-6);
+        assert_eq!(battery.unhonored_commands(), 2);
+    }
+
+    #[test]
+    fn test_volt_var_battery_absorbs_at_high_voltage_while_idle() {
+        let inverter = Inverter::new(5.0, ReactivePowerMode::VoltVar(VoltVarCurve::default()))
+            .with_volt_watt(VoltWattCurve::default());
+        let mut battery = Battery::new(10.0, 0.5, 5.0, 5.0, 0.95, 0.95, 24).with_inverter(inverter);
+
+        let kw = battery.power_kw(&DeviceContext::with_setpoint(0, 0.0).with_voltage_pu(1.08));
+        assert_eq!(kw, 0.0);
+        assert!((battery.reactive_kvar() + 0.44 * 5.0).abs() < 1e-5);
+
+        // Volt-Watt caps discharge to 60% of rating at 1.08 pu.
+        let kw = battery.power_kw(&DeviceContext::with_setpoint(1, 5.0).with_voltage_pu(1.08));
+        assert!((kw - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_equivalent_full_cycles() {
+        let mut battery = Battery::new(10.0, 0.0, 10.0, 10.0, 1.0, 1.0, 24);
+        battery.power_kw(&DeviceContext::with_setpoint(0, -10.0));
+        battery.power_kw(&DeviceContext::with_setpoint(1, 10.0));
+        assert!((battery.equivalent_full_cycles() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_degradation_reduces_capacity() {
+        let params = DegradationParams {
+            cycle_life_full_dod: 100.0,
+            ..DegradationParams::default()
+        };
+        let mut battery = Battery::new(10.0, 0.0, 10.0, 10.0, 1.0, 1.0, 24)
+            .with_degradation(BatteryDegradation::new(params));
+
+        for t in 0..20 {
+            let setpoint = if t % 2 == 0 { -10.0 } else { 10.0 };
+            battery.power_kw(&DeviceContext::with_setpoint(t, setpoint));
+        }
+
+        assert!(battery.capacity_kwh < battery.nominal_capacity_kwh);
+        assert!(battery.capacity_fade() > 0.0);
+    }
 }
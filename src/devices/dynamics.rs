@@ -0,0 +1,180 @@
+//! Actuator dynamics shared by dispatchable devices.
+
+use std::collections::VecDeque;
+
+/// Output magnitude below which a device is considered off.
+const ON_THRESHOLD_KW: f32 = 1e-4;
+
+/// Actuator limits for a dispatchable device.
+///
+/// The default imposes no limits, so a device follows every command instantly.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActuatorLimits {
+    /// Maximum change of output per minute in kW, or `None` for no limit.
+    pub ramp_kw_per_min: Option<f32>,
+
+    /// Number of steps between issuing a command and the device acting on it.
+    pub latency_steps: usize,
+
+    /// Minimum number of steps the device stays on once started.
+    pub min_on_steps: usize,
+
+    /// Minimum number of steps the device stays off once stopped.
+    pub min_off_steps: usize,
+}
+
+/// Applies [`ActuatorLimits`] to a stream of power commands.
+///
+/// Each step, [`Actuator::command_kw`] turns the requested power into the
+/// power the device will attempt after latency, minimum on/off times and ramp
+/// limits. The device then applies its physical limits and reports the power it
+/// actually produced through [`Actuator::record_output`], which becomes the
+/// starting point for the next ramp.
+#[derive(Debug, Clone)]
+pub struct Actuator {
+    limits: ActuatorLimits,
+    dt_minutes: f32,
+    in_flight: VecDeque<f32>,
+    active_cmd_kw: f32,
+    output_kw: f32,
+    is_on: bool,
+    steps_in_state: usize,
+    last_shortfall_kw: f32,
+    unhonored_steps: usize,
+}
+
+impl Actuator {
+    pub fn new(limits: ActuatorLimits, steps_per_day: usize) -> Self {
+        assert!(limits.ramp_kw_per_min.is_none_or(|ramp| ramp > 0.0));
+        assert!(steps_per_day > 0);
+
+        Self {
+            limits,
+            dt_minutes: 1440.0 / steps_per_day as f32,
+            in_flight: VecDeque::new(),
+            active_cmd_kw: 0.0,
+            output_kw: 0.0,
+            is_on: false,
+            // Start as if the device had been off long enough to start at once.
+            steps_in_state: usize::MAX,
+            last_shortfall_kw: 0.0,
+            unhonored_steps: 0,
+        }
+    }
+
+    /// Returns the power the device attempts this step for `requested_kw`.
+    pub fn command_kw(&mut self, requested_kw: f32) -> f32 {
+        self.in_flight.push_back(requested_kw);
+        if self.in_flight.len() > self.limits.latency_steps
+            && let Some(cmd_kw) = self.in_flight.pop_front()
+        {
+            self.active_cmd_kw = cmd_kw;
+        }
+
+        let mut target_kw = self.active_cmd_kw;
+        let wants_on = target_kw.abs() > ON_THRESHOLD_KW;
+        if self.is_on && !wants_on && self.steps_in_state < self.limits.min_on_steps {
+            target_kw = self.output_kw;
+        } else if !self.is_on && wants_on && self.steps_in_state < self.limits.min_off_steps {
+            target_kw = 0.0;
+        }
+
+        if let Some(ramp) = self.limits.ramp_kw_per_min {
+            let max_step_kw = ramp * self.dt_minutes;
+            target_kw =
+                self.output_kw + (target_kw - self.output_kw).clamp(-max_step_kw, max_step_kw);
+        }
+
+        self.last_shortfall_kw = (requested_kw - target_kw).abs();
+        if self.last_shortfall_kw > ON_THRESHOLD_KW {
+            self.unhonored_steps += 1;
+        }
+        target_kw
+    }
+
+    /// Records the power the device actually produced this step.
+    pub fn record_output(&mut self, actual_kw: f32) {
+        let on = actual_kw.abs() > ON_THRESHOLD_KW;
+        if on == self.is_on {
+            self.steps_in_state = self.steps_in_state.saturating_add(1);
+        } else {
+            self.is_on = on;
+            self.steps_in_state = 1;
+        }
+        self.output_kw = actual_kw;
+    }
+
+    /// Difference between the last requested power and what the actuator allowed.
+    pub fn last_shortfall_kw(&self) -> f32 {
+        self.last_shortfall_kw
+    }
+
+    /// Number of steps in which actuator limits kept a command from being followed.
+    pub fn unhonored_steps(&self) -> usize {
+        self.unhonored_steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(actuator: &mut Actuator, requested_kw: f32) -> f32 {
+        let kw = actuator.command_kw(requested_kw);
+        actuator.record_output(kw);
+        kw
+    }
+
+    #[test]
+    fn unlimited_actuator_follows_commands() {
+        let mut actuator = Actuator::new(ActuatorLimits::default(), 24);
+        assert_eq!(step(&mut actuator, 5.0), 5.0);
+        assert_eq!(step(&mut actuator, -3.0), -3.0);
+        assert_eq!(actuator.unhonored_steps(), 0);
+    }
+
+    #[test]
+    fn ramp_limit_scales_with_step_length() {
+        let limits = ActuatorLimits {
+            ramp_kw_per_min: Some(0.5),
+            ..ActuatorLimits::default()
+        };
+        // 1-minute steps: at most 0.5 kW change per step.
+        let mut actuator = Actuator::new(limits, 1440);
+        assert_eq!(step(&mut actuator, 2.0), 0.5);
+        assert_eq!(step(&mut actuator, 2.0), 1.0);
+        assert!((actuator.last_shortfall_kw() - 1.0).abs() < 1e-6);
+        assert_eq!(actuator.unhonored_steps(), 2);
+    }
+
+    #[test]
+    fn latency_delays_commands() {
+        let limits = ActuatorLimits {
+            latency_steps: 2,
+            ..ActuatorLimits::default()
+        };
+        let mut actuator = Actuator::new(limits, 24);
+        assert_eq!(step(&mut actuator, 4.0), 0.0);
+        assert_eq!(step(&mut actuator, 1.0), 0.0);
+        assert_eq!(step(&mut actuator, 0.0), 4.0);
+        assert_eq!(step(&mut actuator, 0.0), 1.0);
+    }
+
+    #[test]
+    fn min_on_and_off_times_hold_state() {
+        let limits = ActuatorLimits {
+            min_on_steps: 3,
+            min_off_steps: 2,
+            ..ActuatorLimits::default()
+        };
+        let mut actuator = Actuator::new(limits, 24);
+        assert_eq!(step(&mut actuator, 2.0), 2.0);
+        // Must stay on for three steps in total.
+        assert_eq!(step(&mut actuator, 0.0), 2.0);
+        assert_eq!(step(&mut actuator, 0.0), 2.0);
+        assert_eq!(step(&mut actuator, 0.0), 0.0);
+        // Must stay off for two steps in total.
+        assert_eq!(step(&mut actuator, 2.0), 0.0);
+        assert_eq!(step(&mut actuator, 2.0), 2.0);
+    }
+}
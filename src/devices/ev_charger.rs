@@ -1,25 +1,73 @@
+use crate::devices::dynamics::{Actuator, ActuatorLimits};
+use crate::devices::ev_hub::{EvChargingPolicy, SessionDemand};
 use crate::devices::types::{Device, DeviceContext};
 use rand::{RngExt, SeedableRng, rngs::StdRng};
 
+/// Energy outcome of a single charging session once the vehicle has left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvSessionOutcome {
+    /// Absolute timestep at which the vehicle arrived.
+    pub arrival_step: usize,
+    /// Absolute timestep at which the vehicle departed.
+    pub departure_step: usize,
+    /// Energy requested at arrival in kWh.
+    pub requested_kwh: f32,
+    /// Energy delivered before departure in kWh.
+    pub delivered_kwh: f32,
+}
+
+/// Unmet energy below this threshold is treated as floating-point residue.
+const UNMET_TOLERANCE_KWH: f32 = 1e-3;
+
+impl EvSessionOutcome {
+    /// Energy still missing at departure in kWh.
+    pub fn unmet_kwh(&self) -> f32 {
+        (self.requested_kwh - self.delivered_kwh).max(0.0)
+    }
+
+    /// Whether the vehicle left with (effectively) all of its requested energy.
+    pub fn is_satisfied(&self) -> bool {
+        self.unmet_kwh() <= UNMET_TOLERANCE_KWH
+    }
+}
+
 #[derive(Debug, Clone)]
 struct EvSession {
+    day_start: usize,
     arrival_step: usize,
     deadline_step: usize,
+    requested_kwh: f32,
     remaining_kwh: f32,
 }
 
+impl EvSession {
+    fn outcome(&self) -> EvSessionOutcome {
+        EvSessionOutcome {
+            arrival_step: self.day_start + self.arrival_step,
+            departure_step: self.day_start + self.deadline_step,
+            requested_kwh: self.requested_kwh,
+            delivered_kwh: self.requested_kwh - self.remaining_kwh,
+        }
+    }
+}
+
 /// A flexible electric load model using EV-style charging sessions.
 ///
-/// Each simulated day, this model samples one charging session with:
+/// Each simulated day, this model samples one charging session per vehicle with:
 /// - random arrival time
 /// - random dwell duration (which sets deadline)
 /// - random required energy in kWh
 ///
 /// During an active session, charging power is computed as the minimum required
 /// to meet the remaining energy by the deadline, limited by `max_charge_kw`.
+/// When a setpoint caps the fleet below its total request, the allocation
+/// policy decides how much each session gets.
+///
+/// Each session is recorded as an [`EvSessionOutcome`] when the vehicle leaves,
+/// so curtailment shows up as unmet energy rather than vanishing.
 #[derive(Debug)]
 pub struct EvCharger {
-    /// Maximum charging power in kilowatts.
+    /// Maximum charging power of one vehicle in kilowatts.
     pub max_charge_kw: f32,
 
     /// Number of simulation steps per day.
@@ -37,8 +85,16 @@ pub struct EvCharger {
     /// Maximum connected duration in simulation steps.
     pub dwell_steps_max: usize,
 
+    /// Number of vehicles, each with its own charger and daily session.
+    pub vehicles: usize,
+
+    /// Allocation policy applied when a setpoint is below the fleet request.
+    pub policy: EvChargingPolicy,
+
     sampled_day: Option<usize>,
-    session: Option<EvSession>,
+    sessions: Vec<EvSession>,
+    outcomes: Vec<EvSessionOutcome>,
+    dynamics: Actuator,
     rng: StdRng,
 }
 
@@ -66,67 +122,124 @@ impl EvCharger {
             demand_kwh_max,
             dwell_steps_min,
             dwell_steps_max,
+            vehicles: 1,
+            policy: EvChargingPolicy::default(),
             sampled_day: None,
-            session: None,
+            sessions: Vec::new(),
+            outcomes: Vec::new(),
+            dynamics: Actuator::new(ActuatorLimits::default(), steps_per_day),
             rng: StdRng::seed_from_u64(seed),
         }
     }
 
+    /// Charges `vehicles` vehicles, sharing a capped setpoint under `policy`.
+    pub fn with_fleet(mut self, vehicles: usize, policy: EvChargingPolicy) -> Self {
+        assert!(vehicles > 0);
+
+        self.vehicles = vehicles;
+        self.policy = policy;
+        self
+    }
+
+    /// Applies ramp-rate, latency and minimum on/off limits to charging commands.
+    pub fn with_dynamics(mut self, limits: ActuatorLimits) -> Self {
+        self.dynamics = Actuator::new(limits, self.steps_per_day);
+        self
+    }
+
+    /// Commanded charging power (kW) the actuator limits withheld in the last step.
+    pub fn command_shortfall_kw(&self) -> f32 {
+        self.dynamics.last_shortfall_kw()
+    }
+
+    /// Number of steps in which the command was not honored due to actuator limits.
+    pub fn unhonored_commands(&self) -> usize {
+        self.dynamics.unhonored_steps()
+    }
+
     fn dt_hours(&self) -> f32 {
         24.0 / self.steps_per_day as f32
     }
 
-    fn sample_session_for_day(&mut self, day: usize) {
-        let dwell_max = self.dwell_steps_max.min(self.steps_per_day);
-        let dwell_min = self.dwell_steps_min.min(dwell_max);
-        let dwell = self.rng.random_range(dwell_min..=dwell_max);
-
-        let latest_arrival = self.steps_per_day - dwell;
-        let arrival = self.rng.random_range(0..=latest_arrival);
-        let deadline = arrival + dwell;
+    /// Outcomes of all sessions that have departed so far.
+    pub fn session_outcomes(&self) -> &[EvSessionOutcome] {
+        &self.outcomes
+    }
 
-        let max_deliverable_kwh = self.max_charge_kw * self.dt_hours() * dwell as f32;
-        let raw_demand = self
-            .rng
-            .random_range(self.demand_kwh_min..=self.demand_kwh_max);
-        let demand_kwh = raw_demand.min(max_deliverable_kwh).max(0.0);
+    /// Records the sessions that have departed by `end_step`, e.g. at the end of a run.
+    pub fn finish(&mut self, end_step: usize) {
+        self.retire_departed(end_step);
+    }
 
-        self.sampled_day = Some(day);
-        self.session = Some(EvSession {
-            arrival_step: arrival,
-            deadline_step: deadline,
-            remaining_kwh: demand_kwh,
+    /// Moves sessions into `outcomes` once their deadline is reached.
+    fn retire_departed(&mut self, timestep: usize) {
+        let outcomes = &mut self.outcomes;
+        self.sessions.retain(|s| {
+            let stays = s.day_start + s.deadline_step > timestep;
+            if !stays {
+                outcomes.push(s.outcome());
+            }
+            stays
         });
     }
 
-    /// Returns the unconstrained charging request at the current timestep.
-    pub fn requested_power_kw(&mut self, context: &DeviceContext) -> f32 {
-        let day = context.timestep / self.steps_per_day;
-        let day_t = context.timestep % self.steps_per_day;
-        let dt_hours = self.dt_hours();
-
-        if self.sampled_day != Some(day) {
-            self.sample_session_for_day(day);
+    fn sample_sessions_for_day(&mut self, day: usize) {
+        let outcomes = &mut self.outcomes;
+        outcomes.extend(self.sessions.drain(..).map(|previous| previous.outcome()));
+
+        for _ in 0..self.vehicles {
+            let dwell_max = self.dwell_steps_max.min(self.steps_per_day);
+            let dwell_min = self.dwell_steps_min.min(dwell_max);
+            let dwell = self.rng.random_range(dwell_min..=dwell_max);
+
+            let latest_arrival = self.steps_per_day - dwell;
+            let arrival = self.rng.random_range(0..=latest_arrival);
+            let deadline = arrival + dwell;
+
+            let max_deliverable_kwh = self.max_charge_kw * self.dt_hours() * dwell as f32;
+            let raw_demand = self
+                .rng
+                .random_range(self.demand_kwh_min..=self.demand_kwh_max);
+            let demand_kwh = raw_demand.min(max_deliverable_kwh).max(0.0);
+
+            self.sessions.push(EvSession {
+                day_start: day * self.steps_per_day,
+                arrival_step: arrival,
+                deadline_step: deadline,
+                requested_kwh: demand_kwh,
+                remaining_kwh: demand_kwh,
+            });
         }
+        self.sampled_day = Some(day);
+    }
 
-        let Some(session) = &self.session else {
-            return 0.0;
-        };
-
+    /// Charging request (kW) of one session at `timestep`.
+    fn session_request_kw(&self, session: &EvSession, timestep: usize) -> f32 {
+        let day_t = timestep - session.day_start;
         if day_t < session.arrival_step || day_t >= session.deadline_step {
             return 0.0;
         }
-
         if session.remaining_kwh <= 0.0 {
             return 0.0;
         }
 
         let remaining_steps = session.deadline_step - day_t;
-        if remaining_steps == 0 {
-            return 0.0;
+        (session.remaining_kwh / (remaining_steps as f32 * self.dt_hours()))
+            .clamp(0.0, self.max_charge_kw)
+    }
+
+    /// Returns the unconstrained charging request at the current timestep.
+    pub fn requested_power_kw(&mut self, context: &DeviceContext) -> f32 {
+        let day = context.timestep / self.steps_per_day;
+        if self.sampled_day != Some(day) {
+            self.sample_sessions_for_day(day);
         }
+        self.retire_departed(context.timestep);
 
-        (session.remaining_kwh / (remaining_steps as f32 * dt_hours)).max(0.0)
+        self.sessions
+            .iter()
+            .map(|s| self.session_request_kw(s, context.timestep))
+            .sum()
     }
 }
 
@@ -135,19 +248,35 @@ impl Device for EvCharger {
         let requested_kw = self.requested_power_kw(context);
         let dt_hours = self.dt_hours();
 
-        if requested_kw <= 0.0 {
-            return 0.0;
+        let fleet_max_kw = self.max_charge_kw * self.vehicles as f32;
+        let cap_kw = context.setpoint_kw.unwrap_or(fleet_max_kw).max(0.0);
+        let cmd_kw = self
+            .dynamics
+            .command_kw(requested_kw.min(cap_kw).min(fleet_max_kw));
+        // Vehicles never draw more than they request, whatever the actuator holds.
+        let charge_kw = cmd_kw.min(requested_kw).max(0.0);
+        self.dynamics.record_output(charge_kw);
+
+        let demands: Vec<SessionDemand> = self
+            .sessions
+            .iter()
+            .map(|session| SessionDemand {
+                request_kw: self.session_request_kw(session, context.timestep),
+                departure_step: session.day_start + session.deadline_step,
+                remaining_kwh: session.remaining_kwh,
+                max_kw: self.max_charge_kw,
+                priority: 0,
+            })
+            .collect();
+        let allocation = self
+            .policy
+            .allocate_kw(&demands, charge_kw, context.timestep, dt_hours);
+        for (session, kw) in self.sessions.iter_mut().zip(allocation) {
+            session.remaining_kwh = (session.remaining_kwh - kw * dt_hours).max(0.0);
         }
 
-        let cap_kw = context.setpoint_kw.unwrap_or(self.max_charge_kw).max(0.0);
-        let charge_kw = requested_kw.min(cap_kw).min(self.max_charge_kw).max(0.0);
-
-        let Some(session) = &mut self.session else {
-            return 0.0;
-        };
-
-        let delivered_kwh = charge_kw * dt_hours;
-        session.remaining_kwh = (session.remaining_kwh - delivered_kwh).max(0.0);
+        // Record the session as soon as its last charging step has passed.
+        self.retire_departed(context.timestep + 1);
 
         charge_kw
     }
@@ -200,5 +329,80 @@ mod tests {
         }
 
         assert!((total_kwh - 10.0).abs() < 1e-4);
+
+        let outcomes = ev.session_outcomes();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].is_satisfied());
+    }
+
+    #[test]
+    fn latency_delays_start_of_charging() {
+        let limits = ActuatorLimits {
+            latency_steps: 1,
+            ..ActuatorLimits::default()
+        };
+        let mut plain = EvCharger::new(7.2, 24, 10.0, 10.0, 6, 6, 99);
+        let mut delayed = EvCharger::new(7.2, 24, 10.0, 10.0, 6, 6, 99).with_dynamics(limits);
+
+        let first_on = |ev: &mut EvCharger| (0..24).find(|&t| ev.power_kw(&ctx(t)) > 0.0);
+        let plain_start = first_on(&mut plain).expect("session charges");
+        let delayed_start = first_on(&mut delayed).expect("session charges");
+        assert_eq!(delayed_start, plain_start + 1);
+        assert!(delayed.unhonored_commands() > 0);
+    }
+
+    #[test]
+    fn curtailed_session_records_unmet_energy_at_deadline() {
+        let mut ev = EvCharger::new(7.2, 24, 10.0, 10.0, 6, 6, 99);
+
+        let mut total_kwh = 0.0;
+        for t in 0..24 {
+            total_kwh += ev.power_kw(&DeviceContext::with_setpoint(t, 1.0));
+        }
+
+        let outcomes = ev.session_outcomes();
+        assert_eq!(outcomes.len(), 1);
+        let outcome = &outcomes[0];
+        assert_eq!(outcome.departure_step - outcome.arrival_step, 6);
+        assert!((outcome.delivered_kwh - total_kwh).abs() < 1e-4);
+        assert!((outcome.unmet_kwh() - 4.0).abs() < 1e-4);
+        assert!(!outcome.is_satisfied());
+    }
+
+    #[test]
+    fn fleet_shares_capped_setpoint_by_policy() {
+        // Two plugged-in vehicles asking 2 kW each; the one leaving at step 4
+        // is due first.
+        let fleet = |policy: EvChargingPolicy| {
+            let mut ev = EvCharger::new(7.2, 24, 0.0, 0.0, 4, 4, 1).with_fleet(2, policy);
+            ev.requested_power_kw(&ctx(0));
+            ev.sessions = vec![
+                EvSession {
+                    day_start: 0,
+                    arrival_step: 0,
+                    deadline_step: 8,
+                    requested_kwh: 16.0,
+                    remaining_kwh: 16.0,
+                },
+                EvSession {
+                    day_start: 0,
+                    arrival_step: 0,
+                    deadline_step: 4,
+                    requested_kwh: 8.0,
+                    remaining_kwh: 8.0,
+                },
+            ];
+            assert_eq!(ev.power_kw(&DeviceContext::with_setpoint(0, 2.0)), 2.0);
+            ev.sessions
+                .iter()
+                .map(|s| s.remaining_kwh)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(fleet(EvChargingPolicy::Proportional), vec![15.0, 7.0]);
+        assert_eq!(
+            fleet(EvChargingPolicy::EarliestDeadlineFirst),
+            vec![16.0, 6.0]
+        );
     }
 }
@@ -0,0 +1,667 @@
+use crate::devices::dynamics::{Actuator, ActuatorLimits};
+use crate::devices::ev_charger::EvSessionOutcome;
+use crate::devices::types::{Device, DeviceContext, gaussian_noise};
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+use std::collections::VecDeque;
+
+/// Rule used to share a constrained power budget among connected sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvChargingPolicy {
+    /// Scale every session's request by the same factor.
+    #[default]
+    Proportional,
+    /// Serve sessions with the earliest departure first.
+    EarliestDeadlineFirst,
+    /// Serve sessions with the least slack (time left minus time needed at port rating) first.
+    LeastLaxityFirst,
+    /// Max-min fair split: equal shares, with unused share redistributed.
+    FairShare,
+    /// Serve higher priority classes first, earliest departure first within a class.
+    Priority,
+}
+
+impl EvChargingPolicy {
+    /// Parses a policy from its scenario name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "proportional" => Some(Self::Proportional),
+            "edf" => Some(Self::EarliestDeadlineFirst),
+            "llf" => Some(Self::LeastLaxityFirst),
+            "fair_share" => Some(Self::FairShare),
+            "priority" => Some(Self::Priority),
+            _ => None,
+        }
+    }
+
+    /// Returns the scenario name of the policy.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Proportional => "proportional",
+            Self::EarliestDeadlineFirst => "edf",
+            Self::LeastLaxityFirst => "llf",
+            Self::FairShare => "fair_share",
+            Self::Priority => "priority",
+        }
+    }
+
+    /// Splits `cap_kw` among connected `sessions` at `timestep`.
+    ///
+    /// No session receives more than its own request.
+    pub fn allocate_kw(
+        self,
+        sessions: &[SessionDemand],
+        cap_kw: f32,
+        timestep: usize,
+        dt_hours: f32,
+    ) -> Vec<f32> {
+        let requests: Vec<f32> = sessions.iter().map(|s| s.request_kw).collect();
+        let requested_kw: f32 = requests.iter().sum();
+        if requested_kw <= cap_kw {
+            return requests;
+        }
+
+        let mut order: Vec<usize> = (0..sessions.len()).collect();
+        match self {
+            Self::Proportional => {
+                let share = cap_kw / requested_kw;
+                return requests.iter().map(|r| r * share).collect();
+            }
+            Self::FairShare => return fair_share_kw(&requests, cap_kw),
+            Self::EarliestDeadlineFirst => {
+                order.sort_by_key(|&i| sessions[i].departure_step);
+            }
+            Self::LeastLaxityFirst => {
+                let laxity_hr = |i: usize| {
+                    let session = &sessions[i];
+                    let time_left_hr =
+                        session.departure_step.saturating_sub(timestep) as f32 * dt_hours;
+                    time_left_hr - session.remaining_kwh / session.max_kw
+                };
+                order.sort_by(|&a, &b| laxity_hr(a).total_cmp(&laxity_hr(b)));
+            }
+            Self::Priority => {
+                order.sort_by_key(|&i| {
+                    let session = &sessions[i];
+                    (std::cmp::Reverse(session.priority), session.departure_step)
+                });
+            }
+        }
+
+        let mut budget_kw = cap_kw;
+        let mut allocation = vec![0.0; requests.len()];
+        for i in order {
+            let kw = requests[i].min(budget_kw);
+            allocation[i] = kw;
+            budget_kw -= kw;
+        }
+        allocation
+    }
+}
+
+/// What the allocation policies rank a connected charging session by.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionDemand {
+    /// Power the session asks for in this step (kW).
+    pub request_kw: f32,
+    pub departure_step: usize,
+    pub remaining_kwh: f32,
+    /// Highest power the session's charger can deliver (kW).
+    pub max_kw: f32,
+    pub priority: u8,
+}
+
+/// Configuration for a multi-port [`EvChargingHub`].
+#[derive(Debug, Clone)]
+pub struct EvHubConfig {
+    /// Number of charging ports at the site.
+    pub ports: usize,
+
+    /// Maximum charging power of a single port in kilowatts.
+    pub port_max_kw: f32,
+
+    /// Site-level power cap shared among all ports in kilowatts; `None`
+    /// leaves only the port ratings.
+    pub site_cap_kw: Option<f32>,
+
+    /// Number of simulation steps per day.
+    pub steps_per_day: usize,
+
+    /// Number of vehicle arrivals sampled per day.
+    pub arrivals_per_day: usize,
+
+    /// Mean arrival time as a step within the day.
+    pub arrival_mean_step: f32,
+
+    /// Standard deviation of the arrival time in steps.
+    pub arrival_std_steps: f32,
+
+    /// Minimum per-session charging demand in kWh.
+    pub demand_kwh_min: f32,
+
+    /// Maximum per-session charging demand in kWh.
+    pub demand_kwh_max: f32,
+
+    /// Minimum dwell (arrival to departure) in simulation steps.
+    pub dwell_steps_min: usize,
+
+    /// Maximum dwell (arrival to departure) in simulation steps.
+    pub dwell_steps_max: usize,
+
+    /// Allocation policy applied when the available power is below the request.
+    pub policy: EvChargingPolicy,
+
+    /// Probability (0..=1) that a session belongs to the high priority class.
+    pub priority_share: f32,
+}
+
+#[derive(Debug, Clone)]
+struct HubSession {
+    arrival_step: usize,
+    departure_step: usize,
+    requested_kwh: f32,
+    remaining_kwh: f32,
+    priority: u8,
+}
+
+impl HubSession {
+    fn outcome(&self) -> EvSessionOutcome {
+        EvSessionOutcome {
+            arrival_step: self.arrival_step,
+            departure_step: self.departure_step,
+            requested_kwh: self.requested_kwh,
+            delivered_kwh: self.requested_kwh - self.remaining_kwh,
+        }
+    }
+}
+
+/// A multi-port EV charging site such as a workplace car park or fleet depot.
+///
+/// Each simulated day, this model samples `arrivals_per_day` sessions with:
+/// - an arrival time drawn from a normal distribution around `arrival_mean_step`
+/// - a random dwell duration (which sets the departure deadline)
+/// - a random required energy in kWh
+///
+/// Arriving vehicles take a free port or wait in a FIFO queue. A port is
+/// released when its vehicle departs or finishes charging; vehicles still
+/// queued at their departure step leave without charging.
+///
+/// Each connected session requests the minimum power needed to meet its
+/// remaining energy by departure, limited by `port_max_kw`. The aggregate is
+/// limited by `site_cap_kw`, and when a setpoint cap is lower than the request
+/// the configured [`EvChargingPolicy`] decides the per-session power.
+///
+/// Every session that leaves the site is recorded as an [`EvSessionOutcome`].
+#[derive(Debug)]
+pub struct EvChargingHub {
+    config: EvHubConfig,
+    sampled_day: Option<usize>,
+    advanced_to: Option<usize>,
+    pending: VecDeque<HubSession>,
+    queue: VecDeque<HubSession>,
+    connected: Vec<HubSession>,
+    outcomes: Vec<EvSessionOutcome>,
+    dynamics: Actuator,
+    /// Effective site cap in kilowatts.
+    site_cap_kw: f32,
+    rng: StdRng,
+}
+
+impl EvChargingHub {
+    pub fn new(config: EvHubConfig, seed: u64) -> Self {
+        assert!(config.ports > 0);
+        assert!(config.port_max_kw > 0.0);
+        assert!(config.site_cap_kw.is_none_or(|cap_kw| cap_kw > 0.0));
+        assert!(config.steps_per_day > 0);
+        assert!(config.arrival_std_steps >= 0.0);
+        assert!(config.demand_kwh_min >= 0.0);
+        assert!(config.demand_kwh_max >= config.demand_kwh_min);
+        assert!(config.dwell_steps_min > 0);
+        assert!(config.dwell_steps_max >= config.dwell_steps_min);
+        assert!((0.0..=1.0).contains(&config.priority_share));
+
+        Self {
+            sampled_day: None,
+            advanced_to: None,
+            pending: VecDeque::new(),
+            queue: VecDeque::new(),
+            connected: Vec::new(),
+            outcomes: Vec::new(),
+            dynamics: Actuator::new(ActuatorLimits::default(), config.steps_per_day),
+            site_cap_kw: config
+                .site_cap_kw
+                .unwrap_or(config.ports as f32 * config.port_max_kw),
+            config,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Applies ramp-rate, latency and minimum on/off limits to the site power command.
+    pub fn with_dynamics(mut self, limits: ActuatorLimits) -> Self {
+        self.dynamics = Actuator::new(limits, self.config.steps_per_day);
+        self
+    }
+
+    /// Site power (kW) the actuator limits withheld in the last step.
+    pub fn command_shortfall_kw(&self) -> f32 {
+        self.dynamics.last_shortfall_kw()
+    }
+
+    /// Number of steps in which the site command was not honored due to actuator limits.
+    pub fn unhonored_commands(&self) -> usize {
+        self.dynamics.unhonored_steps()
+    }
+
+    /// Number of vehicles currently connected to a port.
+    #[cfg(test)]
+    pub fn connected_count(&self) -> usize {
+        self.connected.len()
+    }
+
+    /// Number of vehicles waiting for a free port.
+    pub fn queued_count(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Outcomes of all sessions that have left the site so far.
+    pub fn session_outcomes(&self) -> &[EvSessionOutcome] {
+        &self.outcomes
+    }
+
+    /// Records every session that has departed by `end_step`, e.g. at the end of a run.
+    pub fn finish(&mut self, end_step: usize) {
+        self.retire_departed(end_step);
+    }
+
+    fn dt_hours(&self) -> f32 {
+        24.0 / self.config.steps_per_day as f32
+    }
+
+    fn sample_sessions_for_day(&mut self, day: usize) {
+        let steps_per_day = self.config.steps_per_day;
+        let day_start = day * steps_per_day;
+
+        let mut sessions = Vec::with_capacity(self.config.arrivals_per_day);
+        for _ in 0..self.config.arrivals_per_day {
+            let offset = gaussian_noise(&mut self.rng, self.config.arrival_std_steps);
+            let arrival_day_t = (self.config.arrival_mean_step + offset)
+                .round()
+                .clamp(0.0, (steps_per_day - 1) as f32) as usize;
+            let dwell = self
+                .rng
+                .random_range(self.config.dwell_steps_min..=self.config.dwell_steps_max);
+
+            // Demand a port cannot deliver within the dwell is kept, so it
+            // shows up as unmet energy at departure.
+            let demand_kwh = self
+                .rng
+                .random_range(self.config.demand_kwh_min..=self.config.demand_kwh_max);
+
+            let priority = u8::from(self.rng.random::<f32>() < self.config.priority_share);
+
+            let arrival_step = day_start + arrival_day_t;
+            sessions.push(HubSession {
+                arrival_step,
+                departure_step: arrival_step + dwell,
+                requested_kwh: demand_kwh,
+                remaining_kwh: demand_kwh,
+                priority,
+            });
+        }
+        sessions.sort_by_key(|s| s.arrival_step);
+
+        self.sampled_day = Some(day);
+        self.pending.extend(sessions);
+    }
+
+    /// Moves the hub state forward to `timestep`: samples new days, admits
+    /// arrivals, drops departures and fills free ports from the queue.
+    fn advance_to(&mut self, timestep: usize) {
+        if self.advanced_to == Some(timestep) {
+            return;
+        }
+
+        let day = timestep / self.config.steps_per_day;
+        if self.sampled_day != Some(day) {
+            self.sample_sessions_for_day(day);
+        }
+
+        while self
+            .pending
+            .front()
+            .is_some_and(|s| s.arrival_step <= timestep)
+        {
+            if let Some(session) = self.pending.pop_front() {
+                self.queue.push_back(session);
+            }
+        }
+
+        self.retire_departed(timestep);
+
+        while self.connected.len() < self.config.ports {
+            let Some(session) = self.queue.pop_front() else {
+                break;
+            };
+            self.connected.push(session);
+        }
+
+        self.advanced_to = Some(timestep);
+    }
+
+    /// Moves sessions that have departed (or finished charging) into `outcomes`.
+    fn retire_departed(&mut self, timestep: usize) {
+        let outcomes = &mut self.outcomes;
+        self.connected.retain(|s| {
+            let stays = s.departure_step > timestep && s.remaining_kwh > 0.0;
+            if !stays {
+                outcomes.push(s.outcome());
+            }
+            stays
+        });
+        self.queue.retain(|s| {
+            let stays = s.departure_step > timestep;
+            if !stays {
+                outcomes.push(s.outcome());
+            }
+            stays
+        });
+    }
+
+    fn session_request_kw(&self, session: &HubSession, timestep: usize) -> f32 {
+        let remaining_steps = session.departure_step.saturating_sub(timestep);
+        if remaining_steps == 0 || session.remaining_kwh <= 0.0 {
+            return 0.0;
+        }
+        let kw = session.remaining_kwh / (remaining_steps as f32 * self.dt_hours());
+        kw.clamp(0.0, self.config.port_max_kw)
+    }
+
+    /// Returns the unconstrained charging request at the current timestep,
+    /// limited by port ratings and the site cap.
+    pub fn requested_power_kw(&mut self, context: &DeviceContext) -> f32 {
+        self.advance_to(context.timestep);
+        let total: f32 = self
+            .connected
+            .iter()
+            .map(|s| self.session_request_kw(s, context.timestep))
+            .sum();
+        total.min(self.site_cap_kw)
+    }
+
+    /// Splits `cap_kw` among connected sessions according to the policy.
+    fn allocate_kw(&self, timestep: usize, requests: &[f32], cap_kw: f32) -> Vec<f32> {
+        let sessions: Vec<SessionDemand> = self
+            .connected
+            .iter()
+            .zip(requests)
+            .map(|(session, &request_kw)| SessionDemand {
+                request_kw,
+                departure_step: session.departure_step,
+                remaining_kwh: session.remaining_kwh,
+                max_kw: self.config.port_max_kw,
+                priority: session.priority,
+            })
+            .collect();
+        self.config
+            .policy
+            .allocate_kw(&sessions, cap_kw, timestep, self.dt_hours())
+    }
+}
+
+/// Max-min fair (water-filling) split of `cap_kw` among `requests`.
+fn fair_share_kw(requests: &[f32], cap_kw: f32) -> Vec<f32> {
+    let mut order: Vec<usize> = (0..requests.len()).collect();
+    order.sort_by(|&a, &b| requests[a].total_cmp(&requests[b]));
+
+    let mut budget_kw = cap_kw;
+    let mut allocation = vec![0.0; requests.len()];
+    for (served, &i) in order.iter().enumerate() {
+        let equal_share = budget_kw / (requests.len() - served) as f32;
+        let kw = requests[i].min(equal_share);
+        allocation[i] = kw;
+        budget_kw -= kw;
+    }
+    allocation
+}
+
+impl Device for EvChargingHub {
+    fn power_kw(&mut self, context: &DeviceContext) -> f32 {
+        self.advance_to(context.timestep);
+        let dt_hours = self.dt_hours();
+
+        let requests: Vec<f32> = self
+            .connected
+            .iter()
+            .map(|s| self.session_request_kw(s, context.timestep))
+            .collect();
+        let cap_kw = context
+            .setpoint_kw
+            .unwrap_or(self.site_cap_kw)
+            .min(self.site_cap_kw)
+            .max(0.0);
+        let requested_kw: f32 = requests.iter().sum();
+        let cmd_kw = self.dynamics.command_kw(requested_kw.min(cap_kw)).max(0.0);
+        let allocation = self.allocate_kw(context.timestep, &requests, cmd_kw);
+
+        let mut total_kw = 0.0;
+        for (session, charge_kw) in self.connected.iter_mut().zip(allocation) {
+            session.remaining_kwh = (session.remaining_kwh - charge_kw * dt_hours).max(0.0);
+            total_kw += charge_kw;
+        }
+
+        self.dynamics.record_output(total_kw);
+
+        // Record departures as soon as their last charging step has passed.
+        self.retire_departed(context.timestep + 1);
+
+        total_kw
+    }
+
+    fn device_type(&self) -> &'static str {
+        "EvChargingHub"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EvHubConfig {
+        EvHubConfig {
+            ports: 2,
+            port_max_kw: 11.0,
+            site_cap_kw: Some(22.0),
+            steps_per_day: 24,
+            arrivals_per_day: 4,
+            arrival_mean_step: 8.0,
+            arrival_std_steps: 0.0,
+            demand_kwh_min: 10.0,
+            demand_kwh_max: 10.0,
+            dwell_steps_min: 4,
+            dwell_steps_max: 4,
+            policy: EvChargingPolicy::Proportional,
+            priority_share: 0.0,
+        }
+    }
+
+    /// Hub with two connected sessions: one leaving at step 2 needing 4 kWh
+    /// (request 2 kW) and one leaving at step 4 needing 4 kWh (request 1 kW).
+    fn hub_with_sessions(policy: EvChargingPolicy) -> EvChargingHub {
+        let cfg = EvHubConfig {
+            arrivals_per_day: 0,
+            policy,
+            ..config()
+        };
+        let mut hub = EvChargingHub::new(cfg, 0);
+        hub.requested_power_kw(&ctx(0));
+        hub.connected = vec![
+            HubSession {
+                arrival_step: 0,
+                departure_step: 4,
+                requested_kwh: 4.0,
+                remaining_kwh: 4.0,
+                priority: 1,
+            },
+            HubSession {
+                arrival_step: 0,
+                departure_step: 2,
+                requested_kwh: 4.0,
+                remaining_kwh: 4.0,
+                priority: 0,
+            },
+        ];
+        hub
+    }
+
+    fn ctx(t: usize) -> DeviceContext {
+        DeviceContext::new(t)
+    }
+
+    #[test]
+    fn deterministic_for_same_seed() {
+        let cfg = EvHubConfig {
+            arrival_std_steps: 2.0,
+            demand_kwh_max: 30.0,
+            dwell_steps_max: 9,
+            ..config()
+        };
+        let mut hub1 = EvChargingHub::new(cfg.clone(), 42);
+        let mut hub2 = EvChargingHub::new(cfg, 42);
+
+        for t in 0..48 {
+            assert_eq!(hub1.power_kw(&ctx(t)), hub2.power_kw(&ctx(t)));
+        }
+    }
+
+    #[test]
+    fn arrivals_beyond_port_count_are_queued() {
+        let mut hub = EvChargingHub::new(config(), 1);
+        hub.requested_power_kw(&ctx(8));
+        assert_eq!(hub.connected_count(), 2);
+        assert_eq!(hub.queued_count(), 2);
+    }
+
+    #[test]
+    fn queued_vehicles_leave_unserved_at_departure() {
+        // With a one-step dwell, the connected pair charges 10 kWh at 10 kW
+        // while the queued pair waits for a port until it has to leave.
+        let cfg = EvHubConfig {
+            dwell_steps_min: 1,
+            dwell_steps_max: 1,
+            ..config()
+        };
+        let mut hub = EvChargingHub::new(cfg, 1);
+
+        let first = hub.power_kw(&ctx(8));
+        assert!((first - 20.0).abs() < 1e-4);
+        assert_eq!(hub.queued_count(), 0);
+
+        let outcomes = hub.session_outcomes();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes.iter().filter(|o| o.is_satisfied()).count(), 2);
+        assert_eq!(hub.power_kw(&ctx(9)), 0.0);
+    }
+
+    #[test]
+    fn site_cap_limits_aggregate_power() {
+        let cfg = EvHubConfig {
+            ports: 4,
+            site_cap_kw: Some(5.0),
+            ..config()
+        };
+        let mut hub = EvChargingHub::new(cfg, 3);
+        assert!((hub.requested_power_kw(&ctx(8)) - 5.0).abs() < 1e-6);
+        assert!((hub.power_kw(&ctx(8)) - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn setpoint_cap_is_shared_among_sessions() {
+        let mut hub = EvChargingHub::new(config(), 5);
+        let kw = hub.power_kw(&DeviceContext::with_setpoint(8, 2.0));
+        assert!((kw - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn edf_serves_earliest_departure_first() {
+        let hub = hub_with_sessions(EvChargingPolicy::EarliestDeadlineFirst);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 2.5), vec![0.5, 2.0]);
+    }
+
+    #[test]
+    fn llf_serves_least_slack_first() {
+        // Slack: 4h - 4/11h for the first session, 2h - 4/11h for the second.
+        let hub = hub_with_sessions(EvChargingPolicy::LeastLaxityFirst);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 1.5), vec![0.0, 1.5]);
+    }
+
+    #[test]
+    fn priority_serves_high_class_first() {
+        let hub = hub_with_sessions(EvChargingPolicy::Priority);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 1.5), vec![1.0, 0.5]);
+    }
+
+    #[test]
+    fn fair_share_redistributes_unused_share() {
+        let hub = hub_with_sessions(EvChargingPolicy::FairShare);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 2.5), vec![1.0, 1.5]);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 1.0), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn proportional_scales_all_requests() {
+        let hub = hub_with_sessions(EvChargingPolicy::Proportional);
+        assert_eq!(hub.allocate_kw(0, &[1.0, 2.0], 1.5), vec![0.5, 1.0]);
+    }
+
+    #[test]
+    fn curtailed_sessions_report_unmet_energy_at_departure() {
+        let mut hub = EvChargingHub::new(config(), 11);
+        for t in 0..24 {
+            hub.power_kw(&DeviceContext::with_setpoint(t, 2.5));
+        }
+        hub.finish(24);
+
+        let outcomes = hub.session_outcomes();
+        assert_eq!(outcomes.len(), 4);
+        let delivered: f32 = outcomes.iter().map(|o| o.delivered_kwh).sum();
+        let unmet: f32 = outcomes.iter().map(EvSessionOutcome::unmet_kwh).sum();
+        assert!((delivered - 10.0).abs() < 1e-3);
+        assert!((unmet - 30.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn demand_beyond_port_rating_is_reported_unmet() {
+        // 10 kWh over a 4-hour dwell on a 2 kW port can deliver only 8 kWh.
+        let cfg = EvHubConfig {
+            arrivals_per_day: 1,
+            port_max_kw: 2.0,
+            ..config()
+        };
+        let mut hub = EvChargingHub::new(cfg, 5);
+        for t in 0..24 {
+            hub.power_kw(&ctx(t));
+        }
+        hub.finish(24);
+
+        let outcome = &hub.session_outcomes()[0];
+        assert_eq!(outcome.requested_kwh, 10.0);
+        assert!((outcome.delivered_kwh - 8.0).abs() < 1e-3);
+        assert!((outcome.unmet_kwh() - 2.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn feasible_sessions_finish_by_departure() {
+        let cfg = EvHubConfig {
+            ports: 4,
+            site_cap_kw: Some(44.0),
+            ..config()
+        };
+        let mut hub = EvChargingHub::new(cfg, 9);
+
+        let mut total_kwh = 0.0;
+        for t in 0..24 {
+            total_kwh += hub.power_kw(&ctx(t));
+        }
+
+        assert!((total_kwh - 40.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::devices::types::{Device, DeviceContext};
+
+/// Parameters of a [`Genset`].
+///
+/// Fuel use follows a Willans line: `idle_fuel_l_per_h_per_kw * rated_kw`
+/// while running or warming up, plus `fuel_l_per_kwh` per kWh produced. For
+/// gas gensets, read litres as the fuel unit the factors are given in.
+#[derive(Debug, Clone)]
+pub struct GensetConfig {
+    /// Rated electrical output in kilowatts.
+    pub rated_kw: f32,
+    /// Minimum stable load as a fraction of rating.
+    pub min_load_fraction: f32,
+    /// Steps between a start command and the first output.
+    pub startup_steps: usize,
+    /// No-load fuel use in litres per hour per kW of rating.
+    pub idle_fuel_l_per_h_per_kw: f32,
+    /// Incremental fuel use in litres per kWh produced.
+    pub fuel_l_per_kwh: f32,
+    /// Fuel tank capacity in litres.
+    pub tank_l: f32,
+    /// CO2 emitted per litre of fuel burned, in kg.
+    pub co2_kg_per_l: f32,
+    /// NOx emitted per kWh produced, in grams.
+    pub nox_g_per_kwh: f32,
+    /// Number of simulation steps per day.
+    pub steps_per_day: usize,
+}
+
+impl GensetConfig {
+    /// Typical diesel genset of `rated_kw` with a tank for 8 hours at full load.
+    pub fn diesel(rated_kw: f32, steps_per_day: usize) -> Self {
+        let idle_fuel_l_per_h_per_kw = 0.08;
+        let fuel_l_per_kwh = 0.246;
+        Self {
+            rated_kw,
+            min_load_fraction: 0.3,
+            startup_steps: 1,
+            idle_fuel_l_per_h_per_kw,
+            fuel_l_per_kwh,
+            tank_l: 8.0 * rated_kw * (idle_fuel_l_per_h_per_kw + fuel_l_per_kwh),
+            co2_kg_per_l: 2.68,
+            nox_g_per_kwh: 10.0,
+            steps_per_day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GensetState {
+    Off,
+    Starting { steps_left: usize },
+    Running,
+}
+
+/// A dispatchable diesel or gas backup generator.
+///
+/// The setpoint is the requested output (positive = generation). A positive
+/// setpoint starts the unit, which produces nothing until its start-up time
+/// has passed; while running it holds at least its minimum load. A zero or
+/// missing setpoint stops it. Output is limited by the fuel left in the tank,
+/// and fuel, CO2 and NOx are accumulated as it runs.
+#[derive(Debug, Clone)]
+pub struct Genset {
+    config: GensetConfig,
+    state: GensetState,
+    fuel_l: f32,
+    fuel_used_l: f32,
+    energy_kwh: f32,
+    co2_kg: f32,
+    nox_kg: f32,
+    starts: usize,
+}
+
+impl Genset {
+    pub fn new(config: GensetConfig) -> Self {
+        assert!(config.rated_kw > 0.0);
+        assert!((0.0..=1.0).contains(&config.min_load_fraction));
+        assert!(config.idle_fuel_l_per_h_per_kw >= 0.0 && config.fuel_l_per_kwh >= 0.0);
+        assert!(config.tank_l >= 0.0);
+        assert!(config.co2_kg_per_l >= 0.0 && config.nox_g_per_kwh >= 0.0);
+        assert!(config.steps_per_day > 0);
+
+        Self {
+            fuel_l: config.tank_l,
+            config,
+            state: GensetState::Off,
+            fuel_used_l: 0.0,
+            energy_kwh: 0.0,
+            co2_kg: 0.0,
+            nox_kg: 0.0,
+            starts: 0,
+        }
+    }
+
+    pub fn rated_kw(&self) -> f32 {
+        self.config.rated_kw
+    }
+
+    /// Fuel left in the tank (litres).
+    pub fn fuel_l(&self) -> f32 {
+        self.fuel_l
+    }
+
+    /// Fuel burned so far (litres).
+    pub fn fuel_used_l(&self) -> f32 {
+        self.fuel_used_l
+    }
+
+    /// Energy produced so far (kWh).
+    pub fn energy_kwh(&self) -> f32 {
+        self.energy_kwh
+    }
+
+    /// CO2 emitted so far (kg).
+    pub fn co2_kg(&self) -> f32 {
+        self.co2_kg
+    }
+
+    /// NOx emitted so far (kg).
+    pub fn nox_kg(&self) -> f32 {
+        self.nox_kg
+    }
+
+    /// Number of start commands accepted so far.
+    pub fn starts(&self) -> usize {
+        self.starts
+    }
+
+    fn dt_hours(&self) -> f32 {
+        24.0 / self.config.steps_per_day as f32
+    }
+
+    /// Burns fuel for one step at `kw` output and returns the output the tank
+    /// could sustain.
+    fn burn(&mut self, kw: f32) -> f32 {
+        let dt_hours = self.dt_hours();
+        let idle_l = self.config.idle_fuel_l_per_h_per_kw * self.config.rated_kw * dt_hours;
+        let needed_l = idle_l + self.config.fuel_l_per_kwh * kw * dt_hours;
+        let kw = if needed_l <= self.fuel_l {
+            kw
+        } else if self.config.fuel_l_per_kwh > 0.0 {
+            ((self.fuel_l - idle_l) / (self.config.fuel_l_per_kwh * dt_hours)).max(0.0)
+        } else {
+            0.0
+        };
+        let burned_l = (idle_l + self.config.fuel_l_per_kwh * kw * dt_hours).min(self.fuel_l);
+
+        self.fuel_l -= burned_l;
+        self.fuel_used_l += burned_l;
+        self.co2_kg += burned_l * self.config.co2_kg_per_l;
+        self.energy_kwh += kw * dt_hours;
+        self.nox_kg += kw * dt_hours * self.config.nox_g_per_kwh / 1000.0;
+        kw
+    }
+}
+
+impl Device for Genset {
+    fn power_kw(&mut self, context: &DeviceContext) -> f32 {
+        let setpoint_kw = context.setpoint_kw.unwrap_or(0.0);
+        if setpoint_kw <= 0.0 || self.fuel_l <= 0.0 {
+            self.state = GensetState::Off;
+            return 0.0;
+        }
+
+        if self.state == GensetState::Off {
+            self.starts += 1;
+            self.state = if self.config.startup_steps > 0 {
+                GensetState::Starting {
+                    steps_left: self.config.startup_steps,
+                }
+            } else {
+                GensetState::Running
+            };
+        }
+
+        match self.state {
+            GensetState::Starting { steps_left } => {
+                // Warming up burns idle fuel without producing output.
+                self.burn(0.0);
+                self.state = if steps_left > 1 {
+                    GensetState::Starting {
+                        steps_left: steps_left - 1,
+                    }
+                } else {
+                    GensetState::Running
+                };
+                0.0
+            }
+            GensetState::Running => {
+                let min_kw = self.config.min_load_fraction * self.config.rated_kw;
+                let kw = self.burn(setpoint_kw.clamp(min_kw, self.config.rated_kw));
+                if kw < min_kw {
+                    // Not enough fuel left for stable operation.
+                    self.state = GensetState::Off;
+                }
+                kw
+            }
+            GensetState::Off => 0.0,
+        }
+    }
+
+    fn device_type(&self) -> &'static str {
+        "Genset"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> GensetConfig {
+        GensetConfig {
+            startup_steps: 2,
+            ..GensetConfig::diesel(100.0, 24)
+        }
+    }
+
+    fn run(genset: &mut Genset, t: usize, setpoint_kw: f32) -> f32 {
+        genset.power_kw(&DeviceContext::with_setpoint(t, setpoint_kw))
+    }
+
+    #[test]
+    fn starts_after_startup_time_and_holds_min_load() {
+        let mut genset = Genset::new(config());
+        assert_eq!(run(&mut genset, 0, 50.0), 0.0);
+        assert_eq!(run(&mut genset, 1, 50.0), 0.0);
+        assert_eq!(run(&mut genset, 2, 50.0), 50.0);
+        // Below minimum load the unit runs at 30% of rating.
+        assert!((run(&mut genset, 3, 10.0) - 30.0).abs() < 1e-4);
+        assert_eq!(run(&mut genset, 4, 0.0), 0.0);
+        assert_eq!(genset.starts(), 1);
+    }
+
+    #[test]
+    fn accounts_fuel_and_emissions() {
+        let mut genset = Genset::new(GensetConfig {
+            startup_steps: 0,
+            ..config()
+        });
+        assert_eq!(run(&mut genset, 0, 100.0), 100.0);
+
+        // 1 h at full load: 0.08 * 100 + 0.246 * 100 litres.
+        let fuel_l = 32.6;
+        assert!((genset.fuel_used_l() - fuel_l).abs() < 1e-3);
+        assert!((genset.co2_kg() - fuel_l * 2.68).abs() < 1e-2);
+        assert!((genset.nox_kg() - 1.0).abs() < 1e-5);
+        assert!((genset.energy_kwh() - 100.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn empty_tank_limits_output_and_stops_unit() {
+        let mut genset = Genset::new(GensetConfig {
+            startup_steps: 0,
+            tank_l: 20.0,
+            ..config()
+        });
+        // 20 L covers 8 L idle plus 12 L / 0.246 L/kWh ≈ 48.8 kWh.
+        let kw = run(&mut genset, 0, 100.0);
+        assert!((kw - 12.0 / 0.246).abs() < 1e-2);
+        assert!(genset.fuel_l() < 1e-4);
+        assert_eq!(run(&mut genset, 1, 100.0), 0.0);
+    }
+}
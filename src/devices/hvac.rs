@@ -0,0 +1,139 @@
+//! Air-conditioned building load with a lumped thermal model.
+
+use crate::devices::types::{Device, DeviceContext};
+
+/// Cooling of a site's houses, modelled as one lumped building.
+///
+/// The building exchanges heat with ambient through a single thermal
+/// resistance and stores it in its heat capacity. Each step is integrated
+/// exactly for constant ambient and cooling, so large timesteps remain
+/// stable. Without a setpoint the unit draws what holds the thermostat
+/// setpoint by the end of the step, up to its rating; a setpoint in the
+/// context overrides that, letting a controller pre-cool or shed it.
+#[derive(Debug, Clone)]
+pub struct BuildingHvac {
+    /// Electrical rating in kilowatts.
+    pub rated_kw: f32,
+
+    /// Heat removed per unit of electricity.
+    pub cop: f32,
+
+    /// Thermal resistance from indoors to ambient in °C per kW.
+    pub thermal_resistance_c_per_kw: f32,
+
+    /// Heat capacity of the building in kWh per °C.
+    pub heat_capacity_kwh_per_c: f32,
+
+    /// Thermostat setpoint in °C.
+    pub setpoint_c: f32,
+
+    /// Length of one simulation step in hours.
+    pub dt_hr: f32,
+
+    indoor_temp_c: f32,
+}
+
+impl BuildingHvac {
+    /// Cooling for `houses` houses rated `kw_per_house` each, starting at
+    /// the setpoint. Each house is a 4 °C/kW envelope with 2 kWh/°C of
+    /// thermal mass cooled at a COP of 3.
+    pub fn new(houses: f32, kw_per_house: f32, setpoint_c: f32, dt_hr: f32) -> Self {
+        assert!(houses > 0.0);
+        assert!(kw_per_house >= 0.0);
+        assert!(dt_hr > 0.0);
+
+        Self {
+            rated_kw: houses * kw_per_house,
+            cop: 3.0,
+            thermal_resistance_c_per_kw: 4.0 / houses,
+            heat_capacity_kwh_per_c: 2.0 * houses,
+            setpoint_c,
+            dt_hr,
+            indoor_temp_c: setpoint_c,
+        }
+    }
+
+    /// Current indoor temperature in °C.
+    pub fn indoor_temp_c(&self) -> f32 {
+        self.indoor_temp_c
+    }
+
+    /// Power (kW) that brings the building to `target_c` by the end of the
+    /// step at `ambient_c`, within the unit's rating.
+    pub fn power_for_target_kw(&self, ambient_c: f32, target_c: f32) -> f32 {
+        let decay = self.decay();
+        // Equilibrium temperature that lands on the target after one step.
+        let equilibrium_c = (target_c - self.indoor_temp_c * decay) / (1.0 - decay);
+        let cooling_kw = (ambient_c - equilibrium_c) / self.thermal_resistance_c_per_kw;
+        (cooling_kw / self.cop).clamp(0.0, self.rated_kw)
+    }
+
+    fn decay(&self) -> f32 {
+        (-self.dt_hr / (self.thermal_resistance_c_per_kw * self.heat_capacity_kwh_per_c)).exp()
+    }
+}
+
+impl Device for BuildingHvac {
+    /// Runs the unit for one step and advances the indoor temperature.
+    ///
+    /// Ambient defaults to the indoor temperature when the context has none.
+    fn power_kw(&mut self, context: &DeviceContext) -> f32 {
+        let ambient_c = context.ambient_temp_c.unwrap_or(self.indoor_temp_c);
+        let kw = context
+            .setpoint_kw
+            .unwrap_or_else(|| self.power_for_target_kw(ambient_c, self.setpoint_c))
+            .clamp(0.0, self.rated_kw);
+        let equilibrium_c = ambient_c - kw * self.cop * self.thermal_resistance_c_per_kw;
+        self.indoor_temp_c = equilibrium_c + (self.indoor_temp_c - equilibrium_c) * self.decay();
+        kw
+    }
+
+    fn device_type(&self) -> &'static str {
+        "BuildingHvac"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(t: usize, ambient_c: f32) -> DeviceContext {
+        DeviceContext::new(t).with_ambient_temp_c(ambient_c)
+    }
+
+    #[test]
+    fn holds_setpoint_against_a_hot_day() {
+        let mut hvac = BuildingHvac::new(1.0, 3.0, 24.0, 0.25);
+        let kw = hvac.power_kw(&ctx(0, 32.0));
+        // 8 °C across 4 °C/kW leaks in 2 kW of heat, removed at a COP of 3.
+        assert!((kw - 2.0 / 3.0).abs() < 1e-4);
+        assert!((hvac.indoor_temp_c() - 24.0).abs() < 1e-4);
+        assert_eq!(hvac.power_kw(&ctx(1, 20.0)), 0.0);
+    }
+
+    #[test]
+    fn precooling_stores_cold_that_coasts_through_a_shutoff() {
+        let mut hvac = BuildingHvac::new(1.0, 3.0, 24.0, 0.25);
+        let precool_kw = hvac.power_for_target_kw(32.0, 22.0);
+        assert!(precool_kw > 2.0 / 3.0);
+        let kw =
+            hvac.power_kw(&DeviceContext::with_setpoint(0, precool_kw).with_ambient_temp_c(32.0));
+        assert_eq!(kw, precool_kw);
+        assert!(hvac.indoor_temp_c() < 24.0);
+
+        // Switched off, the building warms toward ambient.
+        let before_c = hvac.indoor_temp_c();
+        assert_eq!(
+            hvac.power_kw(&DeviceContext::with_setpoint(1, 0.0).with_ambient_temp_c(32.0)),
+            0.0
+        );
+        assert!(hvac.indoor_temp_c() > before_c);
+        assert!(hvac.indoor_temp_c() < 32.0);
+    }
+
+    #[test]
+    fn cooling_is_limited_to_the_rating() {
+        let hvac = BuildingHvac::new(2.0, 0.5, 24.0, 0.25);
+        assert_eq!(hvac.power_for_target_kw(40.0, 18.0), 1.0);
+    }
+}
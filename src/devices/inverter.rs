@@ -0,0 +1,224 @@
+//! Grid-tie inverter capability and reactive power functions.
+
+/// Piecewise-linear Volt-VAR curve.
+///
+/// Points are `(voltage_pu, kvar_fraction)` pairs in increasing voltage order,
+/// where `kvar_fraction` is reactive power injected as a fraction of the
+/// inverter kVA rating (positive injects, negative absorbs). Outside the first
+/// and last point the curve is flat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoltVarCurve {
+    pub points: [(f32, f32); 4],
+}
+
+impl Default for VoltVarCurve {
+    /// IEEE 1547-2018 Category B default settings.
+    fn default() -> Self {
+        Self {
+            points: [(0.92, 0.44), (0.98, 0.0), (1.02, 0.0), (1.08, -0.44)],
+        }
+    }
+}
+
+impl VoltVarCurve {
+    /// Reactive power fraction of rating at `voltage_pu`.
+    pub fn kvar_fraction(&self, voltage_pu: f32) -> f32 {
+        let points = &self.points;
+        if voltage_pu <= points[0].0 {
+            return points[0].1;
+        }
+        for w in points.windows(2) {
+            let (v0, q0) = w[0];
+            let (v1, q1) = w[1];
+            if voltage_pu <= v1 {
+                if v1 <= v0 {
+                    return q1;
+                }
+                return q0 + (q1 - q0) * (voltage_pu - v0) / (v1 - v0);
+            }
+        }
+        points[points.len() - 1].1
+    }
+}
+
+/// Volt-Watt curve limiting active power export at high voltage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoltWattCurve {
+    /// Voltage at which export curtailment starts.
+    pub v_start_pu: f32,
+    /// Voltage at which export reaches `p_min_fraction`.
+    pub v_end_pu: f32,
+    /// Export limit at and above `v_end_pu`, as a fraction of rating.
+    pub p_min_fraction: f32,
+}
+
+impl Default for VoltWattCurve {
+    /// IEEE 1547-2018 Category B default settings.
+    fn default() -> Self {
+        Self {
+            v_start_pu: 1.06,
+            v_end_pu: 1.10,
+            p_min_fraction: 0.2,
+        }
+    }
+}
+
+impl VoltWattCurve {
+    /// Export limit at `voltage_pu` as a fraction of rating.
+    pub fn p_fraction(&self, voltage_pu: f32) -> f32 {
+        if voltage_pu <= self.v_start_pu {
+            1.0
+        } else if voltage_pu >= self.v_end_pu {
+            self.p_min_fraction
+        } else {
+            let span = (voltage_pu - self.v_start_pu) / (self.v_end_pu - self.v_start_pu);
+            1.0 - (1.0 - self.p_min_fraction) * span
+        }
+    }
+}
+
+/// Reactive power function of an inverter.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReactivePowerMode {
+    /// No reactive power.
+    #[default]
+    UnityPowerFactor,
+    /// Constant power factor; `absorbing` draws reactive power (lowers voltage).
+    FixedPowerFactor { power_factor: f32, absorbing: bool },
+    /// Reactive power from the measured voltage.
+    VoltVar(VoltVarCurve),
+}
+
+impl ReactivePowerMode {
+    /// Parses a mode name used in scenario files.
+    pub fn from_name(name: &str, power_factor: f32, absorbing: bool) -> Option<Self> {
+        match name {
+            "unity" => Some(Self::UnityPowerFactor),
+            "fixed_pf" => Some(Self::FixedPowerFactor {
+                power_factor,
+                absorbing,
+            }),
+            "volt_var" => Some(Self::VoltVar(VoltVarCurve::default())),
+            _ => None,
+        }
+    }
+}
+
+/// Inverter with an apparent power (kVA) limit and grid-support functions.
+///
+/// Reactive power uses generator convention: positive kVAR is injected into
+/// the grid. Active power has priority over reactive power, so kVAR is limited
+/// to the capability left at the current kW output.
+#[derive(Debug, Clone)]
+pub struct Inverter {
+    /// Apparent power rating in kVA.
+    pub rating_kva: f32,
+
+    /// Reactive power function.
+    pub mode: ReactivePowerMode,
+
+    /// Optional Volt-Watt export curtailment.
+    pub volt_watt: Option<VoltWattCurve>,
+}
+
+impl Inverter {
+    pub fn new(rating_kva: f32, mode: ReactivePowerMode) -> Self {
+        assert!(rating_kva >= 0.0);
+        if let ReactivePowerMode::FixedPowerFactor { power_factor, .. } = mode {
+            assert!(power_factor > 0.0 && power_factor <= 1.0);
+        }
+
+        Self {
+            rating_kva,
+            mode,
+            volt_watt: None,
+        }
+    }
+
+    pub fn with_volt_watt(mut self, curve: VoltWattCurve) -> Self {
+        assert!(curve.v_start_pu < curve.v_end_pu);
+        assert!((0.0..=1.0).contains(&curve.p_min_fraction));
+        self.volt_watt = Some(curve);
+        self
+    }
+
+    /// Limits active power `p_kw` (positive = export) to the kVA rating and,
+    /// when `voltage_pu` is known, to the Volt-Watt curve.
+    pub fn limit_active_kw(&self, p_kw: f32, voltage_pu: Option<f32>) -> f32 {
+        let mut p_kw = p_kw.clamp(-self.rating_kva, self.rating_kva);
+        if let (Some(curve), Some(v)) = (self.volt_watt, voltage_pu) {
+            p_kw = p_kw.min(curve.p_fraction(v) * self.rating_kva);
+        }
+        p_kw
+    }
+
+    /// Reactive power (kVAR, positive = injected) at active power `p_kw`.
+    pub fn reactive_kvar(&self, p_kw: f32, voltage_pu: Option<f32>) -> f32 {
+        let desired_kvar = match self.mode {
+            ReactivePowerMode::UnityPowerFactor => 0.0,
+            ReactivePowerMode::FixedPowerFactor {
+                power_factor,
+                absorbing,
+            } => {
+                let kvar = p_kw.abs() * power_factor.acos().tan();
+                if absorbing { -kvar } else { kvar }
+            }
+            ReactivePowerMode::VoltVar(curve) => voltage_pu
+                .map(|v| curve.kvar_fraction(v) * self.rating_kva)
+                .unwrap_or(0.0),
+        };
+        let headroom_kvar = (self.rating_kva.powi(2) - p_kw.powi(2)).max(0.0).sqrt();
+        desired_kvar.clamp(-headroom_kvar, headroom_kvar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volt_var_interpolates_between_points() {
+        let curve = VoltVarCurve::default();
+        assert_eq!(curve.kvar_fraction(0.90), 0.44);
+        assert_eq!(curve.kvar_fraction(1.0), 0.0);
+        assert!((curve.kvar_fraction(1.05) + 0.22).abs() < 1e-5);
+        assert_eq!(curve.kvar_fraction(1.10), -0.44);
+    }
+
+    #[test]
+    fn fixed_power_factor_absorbs_proportional_kvar() {
+        let inverter = Inverter::new(
+            10.0,
+            ReactivePowerMode::FixedPowerFactor {
+                power_factor: 0.8,
+                absorbing: true,
+            },
+        );
+        assert!((inverter.reactive_kvar(4.0, None) + 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn kva_rating_gives_active_power_priority() {
+        let inverter = Inverter::new(
+            5.0,
+            ReactivePowerMode::FixedPowerFactor {
+                power_factor: 0.8,
+                absorbing: false,
+            },
+        );
+        assert_eq!(inverter.limit_active_kw(6.0, None), 5.0);
+        // 4 kW leaves 3 kVAR of a 5 kVA rating; PF 0.8 would want 3 kVAR exactly.
+        assert!((inverter.reactive_kvar(4.0, None) - 3.0).abs() < 1e-5);
+        assert!((inverter.reactive_kvar(4.5, None) - 4.75_f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn volt_watt_curtails_export_at_high_voltage() {
+        let inverter = Inverter::new(10.0, ReactivePowerMode::UnityPowerFactor)
+            .with_volt_watt(VoltWattCurve::default());
+        assert_eq!(inverter.limit_active_kw(8.0, Some(1.0)), 8.0);
+        assert!((inverter.limit_active_kw(8.0, Some(1.08)) - 6.0).abs() < 1e-4);
+        // Charging is not curtailed.
+        assert_eq!(inverter.limit_active_kw(-8.0, Some(1.12)), -8.0);
+    }
+}
@@ -2,14 +2,30 @@
 
 pub mod baseload;
 pub mod battery;
+pub mod degradation;
+pub mod dynamics;
 pub mod ev_charger;
+pub mod ev_hub;
+pub mod genset;
+pub mod hvac;
+pub mod inverter;
 pub mod solar;
+pub mod thermal;
 pub mod types;
+pub mod wind;
 
 // Re-export the main types for convenience
 pub use baseload::BaseLoad;
-pub use battery::Battery;
-pub use ev_charger::EvCharger;
+pub use battery::{Battery, BatteryEnvelope};
+pub use degradation::{BatteryDegradation, DegradationParams};
+pub use dynamics::ActuatorLimits;
+pub use ev_charger::{EvCharger, EvSessionOutcome};
+pub use ev_hub::{EvChargingHub, EvChargingPolicy, EvHubConfig};
+pub use genset::{Genset, GensetConfig};
+pub use hvac::BuildingHvac;
+pub use inverter::{Inverter, ReactivePowerMode, VoltWattCurve};
 pub use solar::SolarPv;
+pub use thermal::{BatteryThermal, ThermalParams};
 pub use types::Device;
 pub use types::DeviceContext;
+pub use wind::{WindPowerCurve, WindSpeedSource, WindTurbine};
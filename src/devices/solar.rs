@@ -1,3 +1,4 @@
+use crate::devices::inverter::Inverter;
 use crate::devices::types::{Device, DeviceContext, gaussian_noise};
 use rand::{SeedableRng, rngs::StdRng};
 
@@ -44,6 +45,21 @@ pub struct SolarPv {
     /// Standard deviation of the Gaussian noise as a fraction of output
     pub noise_std: f32, // e.g. 0.05 for +/-5% (Gaussian-ish)
 
+    /// Optional inverter limiting kVA and providing reactive power
+    pub inverter: Option<Inverter>,
+
+    /// Reactive power injected in the last step (kVAR)
+    kvar: f32,
+
+    /// Output withheld by the curtailment setpoint in the last step (kW)
+    curtailed_kw: f32,
+
+    /// Total output withheld by curtailment setpoints (kWh)
+    curtailed_kwh: f32,
+
+    /// Uncurtailed DC output sampled for a timestep, so noise is drawn once per step
+    sampled: Option<(usize, f32)>,
+
     /// Random number generator for noise generation
     rng: StdRng,
 }
@@ -86,10 +102,67 @@ impl SolarPv {
             sunrise_idx,
             sunset_idx,
             noise_std: noise_std.max(0.0),
+            inverter: None,
+            kvar: 0.0,
+            curtailed_kw: 0.0,
+            curtailed_kwh: 0.0,
+            sampled: None,
             rng: StdRng::seed_from_u64(seed),
         }
     }
 
+    /// Connects the array through an inverter with a kVA limit and grid-support functions.
+    pub fn with_inverter(mut self, inverter: Inverter) -> Self {
+        self.inverter = Some(inverter);
+        self
+    }
+
+    /// Reactive power injected in the last step (kVAR, positive = injected).
+    pub fn reactive_kvar(&self) -> f32 {
+        self.kvar
+    }
+
+    /// Output (kW) the array could deliver this step without curtailment,
+    /// after inverter limits.
+    ///
+    /// Samples the step's weather noise, so a following [`Device::power_kw`]
+    /// call for the same timestep sees the same conditions.
+    pub fn available_kw(&mut self, context: &DeviceContext) -> f32 {
+        let kw = self.sample_kw(context.timestep);
+        match &self.inverter {
+            Some(inverter) => inverter.limit_active_kw(kw, context.voltage_pu).max(0.0),
+            None => kw,
+        }
+    }
+
+    /// Output withheld by the curtailment setpoint in the last step (kW).
+    pub fn curtailed_kw(&self) -> f32 {
+        self.curtailed_kw
+    }
+
+    /// Total output withheld by curtailment setpoints so far (kWh).
+    pub fn curtailed_kwh(&self) -> f32 {
+        self.curtailed_kwh
+    }
+
+    fn sample_kw(&mut self, timestep: usize) -> f32 {
+        if let Some((t, kw)) = self.sampled
+            && t == timestep
+        {
+            return kw;
+        }
+
+        let frac = self.daylight_frac(timestep);
+        let kw = if frac > 0.0 {
+            let noise_mult = 1.0 + gaussian_noise(&mut self.rng, self.noise_std);
+            (self.kw_peak * frac * noise_mult).max(0.0)
+        } else {
+            0.0
+        };
+        self.sampled = Some((timestep, kw));
+        kw
+    }
+
     /// Calculates the daylight fraction for a specific time step.
     ///
     /// Returns a value between 0.0 and 1.0 representing the relative
@@ -122,7 +195,8 @@ impl Device for SolarPv {
     /// - Base solar output following a half-cosine curve during daylight hours
     /// - Random Gaussian noise to simulate variations due to cloud cover
     ///
-    /// The generation is guaranteed to be non-negative.
+    /// A setpoint, if given, caps the output (curtailment). The generation is
+    /// guaranteed to be non-negative.
     ///
     /// # Arguments
     ///
@@ -132,16 +206,20 @@ impl Device for SolarPv {
     ///
     /// The power generation in kilowatts at the specified time step
     fn power_kw(&mut self, context: &DeviceContext) -> f32 {
-        let frac = self.daylight_frac(context.timestep);
-        if frac <= 0.0 {
-            return 0.0;
-        }
-
-        let noise_mult = 1.0 + gaussian_noise(&mut self.rng, self.noise_std);
-        let kw = self.kw_peak * frac * noise_mult;
+        let available_kw = self.available_kw(context);
+        let kw = match context.setpoint_kw {
+            Some(limit_kw) => available_kw.min(limit_kw.max(0.0)),
+            None => available_kw,
+        };
+        self.curtailed_kw = available_kw - kw;
+        self.curtailed_kwh += self.curtailed_kw * 24.0 / self.steps_per_day as f32;
+
+        self.kvar = self.inverter.as_ref().map_or(0.0, |inverter| {
+            inverter.reactive_kvar(kw, context.voltage_pu)
+        });
 
         // Return positive for generation (according to power flow convention)
-        kw.max(0.0)
+        kw
     }
 
     fn device_type(&self) -> &'static str {
@@ -152,6 +230,7 @@ impl Device for SolarPv {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::devices::inverter::ReactivePowerMode;
 
     // Helper function to create a context with just a timestep
     fn ctx(t: usize) -> DeviceContext {
@@ -278,4 +357,41 @@ mod tests {
             assert_eq!(pv.power_kw(&ctx(t)), pv.power_kw(&ctx(t + 24)));
         }
     }
+
+    #[test]
+    fn test_setpoint_curtails_output() {
+        let mut pv = SolarPv::new(5.0, 24, 6, 18, 0.0, 42);
+
+        assert_eq!(pv.available_kw(&ctx(12)), 5.0);
+        assert_eq!(pv.power_kw(&DeviceContext::with_setpoint(12, 3.0)), 3.0);
+        assert_eq!(pv.curtailed_kw(), 2.0);
+
+        // A setpoint above the available output does not curtail.
+        assert_eq!(
+            pv.power_kw(&DeviceContext::with_setpoint(13, 10.0)),
+            pv.available_kw(&ctx(13))
+        );
+        assert_eq!(pv.curtailed_kw(), 0.0);
+        assert_eq!(pv.curtailed_kwh(), 2.0);
+    }
+
+    #[test]
+    fn test_inverter_clips_output_and_absorbs_vars() {
+        let inverter = Inverter::new(
+            4.0,
+            ReactivePowerMode::FixedPowerFactor {
+                power_factor: 0.9,
+                absorbing: true,
+            },
+        );
+        let mut pv = SolarPv::new(5.0, 24, 6, 18, 0.0, 42).with_inverter(inverter);
+
+        // Noon output of 5 kW is clipped to the 4 kVA rating, leaving no kVAR headroom.
+        assert_eq!(pv.power_kw(&ctx(12)), 4.0);
+        assert_eq!(pv.reactive_kvar(), 0.0);
+
+        let kw = pv.power_kw(&ctx(8));
+        assert!(kw > 0.0 && kw < 4.0);
+        assert!(pv.reactive_kvar() < 0.0);
+    }
 }
@@ -0,0 +1,173 @@
+//! Lumped thermal model for battery cells.
+
+/// Parameters for [`BatteryThermal`].
+#[derive(Debug, Clone)]
+pub struct ThermalParams {
+    /// Heat capacity of the pack in kWh per °C.
+    pub heat_capacity_kwh_per_c: f32,
+
+    /// Thermal resistance from cells to ambient in °C per kW.
+    pub thermal_resistance_c_per_kw: f32,
+
+    /// Conversion loss independent of current (inverter, BMS), as a fraction.
+    pub base_loss: f32,
+
+    /// Resistive (I²R) loss fraction at a 1C charge/discharge rate at 25 °C.
+    ///
+    /// Resistive loss as a fraction of power scales linearly with C-rate.
+    pub loss_at_1c: f32,
+
+    /// Relative increase of cell resistance per °C below 25 °C.
+    pub cold_resistance_per_c: f32,
+
+    /// Below this temperature power is derated linearly.
+    pub derate_low_c: f32,
+
+    /// At or below this temperature no power is allowed.
+    pub cutoff_low_c: f32,
+
+    /// Above this temperature power is derated linearly.
+    pub derate_high_c: f32,
+
+    /// At or above this temperature no power is allowed.
+    pub cutoff_high_c: f32,
+}
+
+impl ThermalParams {
+    /// Default parameters for a pack of `capacity_kwh`, scaling heat capacity
+    /// and cooling with pack size.
+    pub fn for_capacity(capacity_kwh: f32) -> Self {
+        assert!(capacity_kwh > 0.0);
+
+        Self {
+            heat_capacity_kwh_per_c: 0.002 * capacity_kwh,
+            thermal_resistance_c_per_kw: 500.0 / capacity_kwh,
+            base_loss: 0.02,
+            loss_at_1c: 0.05,
+            cold_resistance_per_c: 0.02,
+            derate_low_c: 10.0,
+            cutoff_low_c: -10.0,
+            derate_high_c: 40.0,
+            cutoff_high_c: 55.0,
+        }
+    }
+}
+
+/// Cell temperature state of a battery pack.
+///
+/// Resistive losses heat the pack, which exchanges heat with ambient through a
+/// single thermal resistance. Each step is integrated exactly for constant
+/// losses and ambient, so large timesteps remain stable.
+#[derive(Debug, Clone)]
+pub struct BatteryThermal {
+    params: ThermalParams,
+    temp_c: f32,
+    last_ambient_c: f32,
+}
+
+impl BatteryThermal {
+    pub fn new(params: ThermalParams, initial_temp_c: f32) -> Self {
+        assert!(params.heat_capacity_kwh_per_c > 0.0);
+        assert!(params.thermal_resistance_c_per_kw > 0.0);
+        assert!((0.0..1.0).contains(&params.base_loss));
+        assert!(params.loss_at_1c >= 0.0);
+        assert!(params.cutoff_low_c < params.derate_low_c);
+        assert!(params.derate_low_c < params.derate_high_c);
+        assert!(params.derate_high_c < params.cutoff_high_c);
+
+        Self {
+            params,
+            temp_c: initial_temp_c,
+            last_ambient_c: initial_temp_c,
+        }
+    }
+
+    /// Current cell temperature in °C.
+    pub fn temp_c(&self) -> f32 {
+        self.temp_c
+    }
+
+    /// Fraction (0..=1) of rated power available at the current temperature.
+    pub fn derate_factor(&self) -> f32 {
+        let p = &self.params;
+        let t = self.temp_c;
+        if t <= p.cutoff_low_c || t >= p.cutoff_high_c {
+            0.0
+        } else if t < p.derate_low_c {
+            (t - p.cutoff_low_c) / (p.derate_low_c - p.cutoff_low_c)
+        } else if t > p.derate_high_c {
+            (p.cutoff_high_c - t) / (p.cutoff_high_c - p.derate_high_c)
+        } else {
+            1.0
+        }
+    }
+
+    /// One-way efficiency at `c_rate`, with cell resistance scaled by
+    /// `resistance_factor` (e.g. from aging) and by cold temperatures.
+    pub fn efficiency(&self, c_rate: f32, resistance_factor: f32) -> f32 {
+        let cold_factor = 1.0 + self.params.cold_resistance_per_c * (25.0 - self.temp_c).max(0.0);
+        let resistive = self.params.loss_at_1c * c_rate.abs() * resistance_factor * cold_factor;
+        (1.0 - self.params.base_loss - resistive).max(0.01)
+    }
+
+    /// Advances cell temperature by `dt_hours` with `heat_kw` of losses.
+    ///
+    /// Uses the last known ambient when `ambient_c` is `None`.
+    pub fn update(&mut self, heat_kw: f32, ambient_c: Option<f32>, dt_hours: f32) {
+        let ambient_c = ambient_c.unwrap_or(self.last_ambient_c);
+        self.last_ambient_c = ambient_c;
+
+        let r = self.params.thermal_resistance_c_per_kw;
+        let tau_hours = r * self.params.heat_capacity_kwh_per_c;
+        let steady_c = ambient_c + heat_kw.max(0.0) * r;
+        self.temp_c = steady_c + (self.temp_c - steady_c) * (-dt_hours / tau_hours).exp();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn losses_heat_pack_towards_steady_state() {
+        let params = ThermalParams::for_capacity(10.0);
+        let mut thermal = BatteryThermal::new(params, 25.0);
+        for _ in 0..48 {
+            thermal.update(0.2, Some(25.0), 1.0);
+        }
+        // 0.2 kW through 50 °C/kW settles 10 °C above ambient.
+        assert!((thermal.temp_c() - 35.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn idle_pack_relaxes_to_ambient() {
+        let mut thermal = BatteryThermal::new(ThermalParams::for_capacity(10.0), 45.0);
+        for _ in 0..48 {
+            thermal.update(0.0, Some(20.0), 1.0);
+        }
+        assert!((thermal.temp_c() - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn derates_linearly_at_temperature_extremes() {
+        let params = ThermalParams::for_capacity(10.0);
+        assert_eq!(
+            BatteryThermal::new(params.clone(), 25.0).derate_factor(),
+            1.0
+        );
+        assert!((BatteryThermal::new(params.clone(), 47.5).derate_factor() - 0.5).abs() < 1e-6);
+        assert!((BatteryThermal::new(params.clone(), 0.0).derate_factor() - 0.5).abs() < 1e-6);
+        assert_eq!(BatteryThermal::new(params, 60.0).derate_factor(), 0.0);
+    }
+
+    #[test]
+    fn efficiency_drops_with_c_rate_and_cold() {
+        let params = ThermalParams::for_capacity(10.0);
+        let warm = BatteryThermal::new(params.clone(), 25.0);
+        let cold = BatteryThermal::new(params, 0.0);
+
+        assert!(warm.efficiency(1.0, 1.0) < warm.efficiency(0.2, 1.0));
+        assert!(cold.efficiency(0.5, 1.0) < warm.efficiency(0.5, 1.0));
+        assert!((warm.efficiency(1.0, 1.0) - 0.93).abs() < 1e-6);
+    }
+}
@@ -7,9 +7,15 @@ use rand::{RngExt, rngs::StdRng};
 /// # Fields
 /// * `timestep` - Current simulation timestep
 /// * `setpoint_kw` - Optional power setpoint for controllable devices (kW)
+/// * `reserve_access` - Whether storage may use its backup reserve (DR or outage)
+/// * `ambient_temp_c` - Optional ambient temperature for temperature-dependent devices (°C)
+/// * `voltage_pu` - Optional measured voltage at the point of connection (per unit)
 pub struct DeviceContext {
     pub timestep: usize,
     pub setpoint_kw: Option<f32>,
+    pub reserve_access: bool,
+    pub ambient_temp_c: Option<f32>,
+    pub voltage_pu: Option<f32>,
 }
 
 impl DeviceContext {
@@ -18,6 +24,9 @@ impl DeviceContext {
         Self {
             timestep,
             setpoint_kw: None,
+            reserve_access: false,
+            ambient_temp_c: None,
+            voltage_pu: None,
         }
     }
 
@@ -26,8 +35,29 @@ impl DeviceContext {
         Self {
             timestep,
             setpoint_kw: Some(setpoint_kw),
+            reserve_access: false,
+            ambient_temp_c: None,
+            voltage_pu: None,
         }
     }
+
+    /// Grants storage devices access to their backup reserve for this step.
+    pub fn with_reserve_access(mut self) -> Self {
+        self.reserve_access = true;
+        self
+    }
+
+    /// Attaches the ambient temperature for this step.
+    pub fn with_ambient_temp_c(mut self, ambient_temp_c: f32) -> Self {
+        self.ambient_temp_c = Some(ambient_temp_c);
+        self
+    }
+
+    /// Attaches the measured voltage for this step.
+    pub fn with_voltage_pu(mut self, voltage_pu: f32) -> Self {
+        self.voltage_pu = Some(voltage_pu);
+        self
+    }
 }
 
 /// Trait defining a device that can produce or consume electricity.
@@ -0,0 +1,280 @@
+use crate::devices::types::{Device, DeviceContext, gaussian_noise};
+use crate::series::parse_last_column_csv;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Turbine power curve with cut-in, rated and cut-out wind speeds.
+///
+/// Output is zero below cut-in and at or above cut-out, rises with the cube of
+/// wind speed between cut-in and rated speed, and is flat at `rated_kw` from
+/// rated speed up to cut-out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindPowerCurve {
+    /// Rated electrical output in kilowatts.
+    pub rated_kw: f32,
+    /// Wind speed (m/s) at which the turbine starts producing.
+    pub cut_in_ms: f32,
+    /// Wind speed (m/s) at which the turbine reaches rated output.
+    pub rated_ms: f32,
+    /// Wind speed (m/s) at which the turbine shuts down for protection.
+    pub cut_out_ms: f32,
+}
+
+impl WindPowerCurve {
+    pub fn new(rated_kw: f32, cut_in_ms: f32, rated_ms: f32, cut_out_ms: f32) -> Self {
+        assert!(rated_kw >= 0.0);
+        assert!(0.0 <= cut_in_ms && cut_in_ms < rated_ms && rated_ms < cut_out_ms);
+
+        Self {
+            rated_kw,
+            cut_in_ms,
+            rated_ms,
+            cut_out_ms,
+        }
+    }
+
+    /// Electrical output (kW) at hub-height wind speed `speed_ms`.
+    pub fn power_kw(&self, speed_ms: f32) -> f32 {
+        if speed_ms < self.cut_in_ms || speed_ms >= self.cut_out_ms {
+            0.0
+        } else if speed_ms >= self.rated_ms {
+            self.rated_kw
+        } else {
+            let num = speed_ms.powi(3) - self.cut_in_ms.powi(3);
+            let den = self.rated_ms.powi(3) - self.cut_in_ms.powi(3);
+            self.rated_kw * num / den
+        }
+    }
+}
+
+/// Source of hub-height wind speeds.
+#[derive(Debug, Clone)]
+pub enum WindSpeedSource {
+    /// Weibull-distributed speeds with AR(1) autocorrelation between steps.
+    ///
+    /// A standard normal AR(1) process with coefficient `autocorrelation` is
+    /// mapped through the normal CDF and the inverse Weibull CDF, so speeds
+    /// have the requested marginal distribution and persist across steps.
+    Synthetic {
+        weibull_shape: f32,
+        weibull_scale_ms: f32,
+        autocorrelation: f32,
+    },
+    /// Measured speeds, one per timestep; repeats if shorter than the run.
+    Series(Vec<f32>),
+}
+
+/// A wind turbine generator driven by a wind speed source and power curve.
+///
+/// Like [`crate::devices::SolarPv`], output is positive for generation and a
+/// setpoint, if given, caps the output (curtailment).
+#[derive(Debug)]
+pub struct WindTurbine {
+    /// Power curve converting wind speed to output.
+    pub curve: WindPowerCurve,
+
+    source: WindSpeedSource,
+    ar_state: f32,
+    sampled: Option<(usize, f32)>,
+    curtailed_kw: f32,
+    curtailed_kwh: f32,
+    steps_per_day: usize,
+    rng: StdRng,
+}
+
+impl WindTurbine {
+    pub fn new(
+        curve: WindPowerCurve,
+        source: WindSpeedSource,
+        steps_per_day: usize,
+        seed: u64,
+    ) -> Self {
+        assert!(steps_per_day > 0);
+        match &source {
+            WindSpeedSource::Synthetic {
+                weibull_shape,
+                weibull_scale_ms,
+                autocorrelation,
+            } => {
+                assert!(*weibull_shape > 0.0 && *weibull_scale_ms > 0.0);
+                assert!((0.0..1.0).contains(autocorrelation));
+            }
+            WindSpeedSource::Series(speeds) => {
+                assert!(!speeds.is_empty());
+                assert!(speeds.iter().all(|v| *v >= 0.0));
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let ar_state = gaussian_noise(&mut rng, 1.0);
+        Self {
+            curve,
+            source,
+            ar_state,
+            sampled: None,
+            curtailed_kw: 0.0,
+            curtailed_kwh: 0.0,
+            steps_per_day,
+            rng,
+        }
+    }
+
+    /// Hub-height wind speed (m/s) at `timestep`.
+    ///
+    /// Synthetic speeds are drawn once per timestep, so repeated calls for the
+    /// same step agree.
+    pub fn wind_speed_ms(&mut self, timestep: usize) -> f32 {
+        if let Some((t, speed)) = self.sampled
+            && t == timestep
+        {
+            return speed;
+        }
+
+        let speed = match &self.source {
+            WindSpeedSource::Series(speeds) => speeds[timestep % speeds.len()],
+            WindSpeedSource::Synthetic {
+                weibull_shape,
+                weibull_scale_ms,
+                autocorrelation,
+            } => {
+                if self.sampled.is_some() {
+                    let innovation = (1.0 - autocorrelation * autocorrelation).sqrt();
+                    self.ar_state =
+                        autocorrelation * self.ar_state + gaussian_noise(&mut self.rng, innovation);
+                }
+                let u = normal_cdf(self.ar_state).clamp(1e-6, 1.0 - 1e-6);
+                weibull_scale_ms * (-(1.0 - u).ln()).powf(1.0 / weibull_shape)
+            }
+        };
+        self.sampled = Some((timestep, speed));
+        speed
+    }
+
+    /// Output (kW) the turbine could deliver this step without curtailment.
+    pub fn available_kw(&mut self, context: &DeviceContext) -> f32 {
+        let speed = self.wind_speed_ms(context.timestep);
+        self.curve.power_kw(speed)
+    }
+
+    /// Output withheld by the curtailment setpoint in the last step (kW).
+    pub fn curtailed_kw(&self) -> f32 {
+        self.curtailed_kw
+    }
+
+    /// Total output withheld by curtailment setpoints so far (kWh).
+    pub fn curtailed_kwh(&self) -> f32 {
+        self.curtailed_kwh
+    }
+}
+
+impl Device for WindTurbine {
+    fn power_kw(&mut self, context: &DeviceContext) -> f32 {
+        let available_kw = self.available_kw(context);
+        let kw = match context.setpoint_kw {
+            Some(limit_kw) => available_kw.min(limit_kw.max(0.0)),
+            None => available_kw,
+        };
+        self.curtailed_kw = available_kw - kw;
+        self.curtailed_kwh += self.curtailed_kw * 24.0 / self.steps_per_day as f32;
+
+        // Positive for generation, as for solar
+        kw
+    }
+
+    fn device_type(&self) -> &'static str {
+        "WindTurbine"
+    }
+}
+
+/// Parses wind speeds (m/s) from CSV text, one row per timestep.
+///
+/// The last column of each row is used, so both a bare list of speeds and
+/// `timestep,wind_speed_ms` rows work. A non-numeric first row is treated as
+/// a header; blank lines are skipped.
+pub fn parse_wind_speed_csv(raw: &str) -> Result<Vec<f32>, String> {
+    parse_last_column_csv(raw, "wind speed", ">= 0", |speed| {
+        speed >= 0.0 && speed.is_finite()
+    })
+}
+
+/// Standard normal CDF using the Abramowitz-Stegun 7.1.26 `erf` approximation.
+fn normal_cdf(z: f32) -> f32 {
+    let x = z.abs() / std::f32::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * x);
+    let poly = t
+        * (0.254_829_6
+            + t * (-0.284_496_74 + t * (1.421_413_7 + t * (-1.453_152_1 + t * 1.061_405_4))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> WindPowerCurve {
+        WindPowerCurve::new(10.0, 3.0, 12.0, 25.0)
+    }
+
+    #[test]
+    fn power_curve_regions() {
+        let curve = curve();
+        assert_eq!(curve.power_kw(2.0), 0.0);
+        assert_eq!(curve.power_kw(3.0), 0.0);
+        assert!(curve.power_kw(8.0) > 0.0 && curve.power_kw(8.0) < 10.0);
+        assert_eq!(curve.power_kw(12.0), 10.0);
+        assert_eq!(curve.power_kw(24.9), 10.0);
+        assert_eq!(curve.power_kw(25.0), 0.0);
+    }
+
+    #[test]
+    fn series_source_follows_input_and_repeats() {
+        let source = WindSpeedSource::Series(vec![2.0, 12.0, 30.0]);
+        let mut turbine = WindTurbine::new(curve(), source, 24, 1);
+        let kws: Vec<f32> = (0..6)
+            .map(|t| turbine.power_kw(&DeviceContext::new(t)))
+            .collect();
+        assert_eq!(kws, vec![0.0, 10.0, 0.0, 0.0, 10.0, 0.0]);
+    }
+
+    #[test]
+    fn synthetic_speeds_match_weibull_mean_and_persist() {
+        let source = WindSpeedSource::Synthetic {
+            weibull_shape: 2.0,
+            weibull_scale_ms: 7.0,
+            autocorrelation: 0.9,
+        };
+        let mut turbine = WindTurbine::new(curve(), source, 24, 7);
+        let speeds: Vec<f32> = (0..20_000).map(|t| turbine.wind_speed_ms(t)).collect();
+
+        // Weibull(k=2, λ=7) has mean λ·Γ(1.5) ≈ 6.20 m/s.
+        let mean = speeds.iter().sum::<f32>() / speeds.len() as f32;
+        assert!((mean - 6.20).abs() < 0.3, "mean {mean}");
+
+        let mean_step_change =
+            speeds.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f32>() / (speeds.len() - 1) as f32;
+        assert!(mean_step_change < 2.0, "step change {mean_step_change}");
+        assert_eq!(turbine.wind_speed_ms(19_999), speeds[19_999]);
+    }
+
+    #[test]
+    fn setpoint_curtails_output() {
+        let source = WindSpeedSource::Series(vec![15.0]);
+        let mut turbine = WindTurbine::new(curve(), source, 24, 1);
+        assert_eq!(turbine.power_kw(&DeviceContext::with_setpoint(0, 4.0)), 4.0);
+        assert_eq!(turbine.curtailed_kw(), 6.0);
+        assert_eq!(turbine.curtailed_kwh(), 6.0);
+    }
+
+    #[test]
+    fn parses_csv_with_header_and_timestep_column() {
+        let speeds = parse_wind_speed_csv("timestep,wind_speed_ms\n0,4.5\n1,6.0\n\n").unwrap();
+        assert_eq!(speeds, vec![4.5, 6.0]);
+        assert_eq!(parse_wind_speed_csv("3.0\n7.5").unwrap(), vec![3.0, 7.5]);
+        assert!(parse_wind_speed_csv("3.0\nfast").is_err());
+        assert!(parse_wind_speed_csv("-1.0").is_err());
+    }
+}
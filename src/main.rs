@@ -5,15 +5,17 @@ mod forecast;
 mod reporting;
 mod runner;
 mod scenario;
+mod series;
+mod settlement;
 mod sim;
 mod telemetry;
 
 use api::run_http_server;
 use cli::{parse_args, print_usage};
-use reporting::print_kpi_report;
-use runner::run_scenario;
+use reporting::{print_kpi_report, print_topology_report};
+use runner::{run_scenario, run_topology};
 use scenario::ScenarioConfig;
-use telemetry::write_telemetry_to_path;
+use telemetry::{write_telemetry_to_path, write_topology_to_path};
 
 fn main() {
     let opts = match parse_args() {
@@ -45,23 +47,48 @@ fn main() {
         ScenarioConfig::default()
     };
 
-    let result = run_scenario(&scenario, true);
-    if let Some(path) = opts.telemetry_out.as_deref() {
-        if let Err(err) = write_telemetry_to_path(path, &result.telemetry) {
+    if let Some(topology) = scenario.topology.as_ref() {
+        if opts.api_bind.is_some() {
+            eprintln!("Error: --api is not available for scenarios with `sites`");
+            std::process::exit(2);
+        }
+        let result = run_topology(&scenario, topology);
+        if let Some(path) = opts.telemetry_out.as_deref()
+            && let Err(err) = write_topology_to_path(
+                path,
+                topology,
+                &result,
+                scenario.steps_per_day,
+                scenario.telemetry_interval_steps,
+            )
+        {
             eprintln!(
                 "Error: failed to write telemetry CSV to {}: {err}",
                 path.display()
             );
             std::process::exit(1);
         }
+        print_topology_report(topology, &result);
+        return;
+    }
+
+    let result = run_scenario(&scenario, true);
+    if let Some(path) = opts.telemetry_out.as_deref()
+        && let Err(err) = write_telemetry_to_path(path, &result.telemetry)
+    {
+        eprintln!(
+            "Error: failed to write telemetry CSV to {}: {err}",
+            path.display()
+        );
+        std::process::exit(1);
     }
 
     print_kpi_report(&result.kpis);
 
-    if let Some(bind_addr) = opts.api_bind.as_deref() {
-        if let Err(err) = run_http_server(bind_addr, result.telemetry.clone()) {
-            eprintln!("Error: failed to start HTTP API on {bind_addr}: {err}");
-            std::process::exit(1);
-        }
+    if let Some(bind_addr) = opts.api_bind.as_deref()
+        && let Err(err) = run_http_server(bind_addr, scenario.clone(), result.telemetry.clone())
+    {
+        eprintln!("Error: failed to start HTTP API on {bind_addr}: {err}");
+        std::process::exit(1);
     }
 }
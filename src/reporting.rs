@@ -1,8 +1,287 @@
-use crate::runner::SimulationKpis;
+use crate::runner::{SimulationKpis, TopologyResult, VOLTAGE_UNBALANCE_LIMIT_PCT};
+use crate::scenario::Topology;
+use crate::sim::topology::NodeFlow;
+use crate::sim::transformer::REFERENCE_HOT_SPOT_C;
 
 pub fn print_kpi_report(kpis: &SimulationKpis) {
     println!("\n--- KPI Report ---");
     println!("RMSE tracking error: {:.3} kW", kpis.rmse_tracking_kw);
     println!("Curtailment achieved: {:.1}%", kpis.curtailment_pct);
+    for (i, event) in kpis.dr_events.iter().enumerate() {
+        println!(
+            "  DR event {i} ({}, steps {}-{}): delivered {:.2} of {:.2} kWh",
+            event.kind.name(),
+            event.start_step,
+            event.end_step,
+            event.delivered_kwh,
+            event.requested_kwh
+        );
+        if let Some(peak_kw) = event.post_event_peak_kw {
+            println!(
+                "    post-event peak: {:.2} kW ({:.0}% of {:.2} kW import limit)",
+                peak_kw,
+                100.0 * peak_kw / kpis.feeder_import_limit_kw,
+                kpis.feeder_import_limit_kw
+            );
+        }
+        if let Some(settlement) = &event.settlement {
+            println!(
+                "    settled: baseline {:.2} kWh, metered {:.2} kWh, performance {:.0}%, payment {:.2}, penalty {:.2}, net {:.2}",
+                settlement.baseline_kwh,
+                settlement.metered_kwh,
+                100.0 * settlement.performance,
+                settlement.payment,
+                settlement.penalty,
+                settlement.net_payment()
+            );
+        }
+    }
     println!("Feeder peak load: {:.2} kW", kpis.feeder_peak_load_kw);
+    println!(
+        "Battery equivalent full cycles: {:.2}",
+        kpis.battery_equivalent_full_cycles
+    );
+    println!(
+        "Battery capacity fade: {:.3}%",
+        kpis.battery_capacity_fade_pct
+    );
+    if let Some(peak_temp_c) = kpis.battery_peak_temp_c {
+        println!("Battery peak cell temperature: {peak_temp_c:.1} °C");
+        println!("Battery derated steps: {}", kpis.battery_derated_steps);
+    }
+    println!(
+        "Commands not honored (ramp/latency/min on-off): battery {} steps, EV {} steps",
+        kpis.battery_unhonored_commands, kpis.ev_unhonored_commands
+    );
+    println!(
+        "Feeder limit violations: {} steps",
+        kpis.feeder_limit_violations
+    );
+    if let Some(envelope) = &kpis.envelope {
+        println!(
+            "Operating envelope: tighter than connection limits {} steps, violations {} import / {} export steps (peak excess {:.2} kW, {:.2} kWh)",
+            envelope.constrained_steps,
+            envelope.import_violations,
+            envelope.export_violations,
+            envelope.peak_excess_kw,
+            envelope.excess_kwh
+        );
+    }
+    println!("Solar curtailed: {:.2} kWh", kpis.solar_curtailed_kwh);
+    if let Some(wind_energy_kwh) = kpis.wind_energy_kwh {
+        println!(
+            "Wind generated: {:.2} kWh (curtailed {:.2} kWh)",
+            wind_energy_kwh, kpis.wind_curtailed_kwh
+        );
+    }
+    if let Some(genset) = &kpis.genset {
+        println!(
+            "Genset generated: {:.2} kWh in {} starts ({:.2} kWh toward DR)",
+            genset.energy_kwh, genset.starts, genset.dr_energy_kwh
+        );
+        println!(
+            "Genset fuel used: {:.2} L (CO2 {:.2} kg, NOx {:.3} kg)",
+            genset.fuel_used_l, genset.co2_kg, genset.nox_kg
+        );
+    }
+    if !kpis.outages.is_empty() {
+        println!(
+            "Grid outages: {} (unserved energy {:.2} kWh)",
+            kpis.outages.len(),
+            kpis.unserved_energy_kwh
+        );
+        for (i, outage) in kpis.outages.iter().enumerate() {
+            println!(
+                "  outage {i}: steps {}-{}, served {} of {} steps, dropped baseload {:.2} kWh, EV charging {:.2} kWh",
+                outage.start_step,
+                outage.end_step,
+                outage.survived_steps,
+                outage.duration_steps(),
+                outage.baseload_shed_kwh,
+                outage.ev_shed_kwh
+            );
+        }
+    }
+    if let Some(regulation) = &kpis.regulation {
+        println!(
+            "Regulation: {:.2} kW for {:.1} h, score {:.2} (correlation {:.2}, delay {:.2}, precision {:.2})",
+            regulation.capacity_kw,
+            regulation.hours,
+            regulation.score.composite(),
+            regulation.score.correlation,
+            regulation.score.delay,
+            regulation.score.precision
+        );
+        println!(
+            "Regulation revenue: capacity {:.2}, performance {:.2}, total {:.2} (mileage {:.1})",
+            regulation.capacity_payment,
+            regulation.performance_payment,
+            regulation.total_payment(),
+            regulation.mileage
+        );
+    }
+    if let Some(frequency) = &kpis.frequency_response {
+        println!(
+            "Grid frequency: {:.3}-{:.3} Hz, outside deadband {} steps",
+            frequency.min_hz, frequency.max_hz, frequency.steps_outside_deadband
+        );
+        println!(
+            "Droop response: peak {:.2} kW, battery {:.2} kWh, EV charging shed {:.2} kWh",
+            frequency.peak_response_kw, frequency.battery_energy_kwh, frequency.ev_shed_kwh
+        );
+    }
+    println!(
+        "Site voltage range: {:.3}-{:.3} pu",
+        kpis.voltage_min_pu, kpis.voltage_max_pu
+    );
+    if let Some(unbalance) = &kpis.unbalance {
+        let [a, b, c] = unbalance.phase_peak_kw;
+        println!(
+            "Phase peak load: A {a:.2} kW, B {b:.2} kW, C {c:.2} kW (phase limit exceeded {} steps)",
+            unbalance.phase_overload_steps
+        );
+        println!(
+            "Phase voltage range: {:.3}-{:.3} pu, voltage unbalance max {:.2}% (above {:.0}% for {} steps), current unbalance at peak {:.1}%",
+            unbalance.phase_voltage_min_pu,
+            unbalance.phase_voltage_max_pu,
+            unbalance.voltage_unbalance_max_pct,
+            VOLTAGE_UNBALANCE_LIMIT_PCT,
+            unbalance.voltage_unbalance_violations,
+            unbalance.current_unbalance_at_peak_pct
+        );
+    }
+    if let Some(transformer) = &kpis.transformer {
+        println!(
+            "Transformer ({:.1} kVA): peak loading {:.0}% (overloaded {} steps), top oil {:.1} °C, hot spot {:.1} °C (above {:.0} °C for {} steps)",
+            transformer.rated_kva,
+            transformer.peak_loading_pct,
+            transformer.overload_steps,
+            transformer.peak_top_oil_c,
+            transformer.peak_hot_spot_c,
+            REFERENCE_HOT_SPOT_C,
+            transformer.hot_spot_over_reference_steps
+        );
+        println!(
+            "Transformer aging: equivalent aging factor {:.3}, loss of life {:.2} h ({:.4}% of normal life)",
+            transformer.equivalent_aging_factor,
+            transformer.loss_of_life_hours,
+            transformer.loss_of_life_pct()
+        );
+    }
+    println!(
+        "EV energy delivered: {:.2} of {:.2} kWh requested (unmet {:.2} kWh)",
+        kpis.ev_energy_delivered_kwh, kpis.ev_energy_requested_kwh, kpis.ev_unmet_kwh
+    );
+    println!(
+        "EV sessions unsatisfied at departure: {} of {}",
+        kpis.ev_sessions_unsatisfied, kpis.ev_sessions_departed
+    );
+    println!("EV home charging policy: {}", kpis.ev_policy.name());
+
+    if let Some(policy) = kpis.ev_hub_policy {
+        let unmet_kwh: f32 = kpis.ev_hub_sessions.iter().map(|s| s.unmet_kwh()).sum();
+        println!("EV hub policy: {}", policy.name());
+        println!(
+            "EV hub sessions departed: {} (unmet energy {:.2} kWh)",
+            kpis.ev_hub_sessions.len(),
+            unmet_kwh
+        );
+        for (i, session) in kpis.ev_hub_sessions.iter().enumerate() {
+            println!(
+                "  session {i}: arrival={} departure={} requested={:.2} kWh delivered={:.2} kWh unmet={:.2} kWh",
+                session.arrival_step,
+                session.departure_step,
+                session.requested_kwh,
+                session.delivered_kwh,
+                session.unmet_kwh()
+            );
+        }
+    }
+}
+
+pub fn print_topology_report(topology: &Topology, result: &TopologyResult) {
+    println!("\n--- Topology Report ---");
+    println!(
+        "Sites: {}, feeders: {}, coordination passes: {}",
+        result.site_flows.len(),
+        result.feeder_flows.len(),
+        result.coordination_passes
+    );
+    for node in result.nodes() {
+        print_node_line(node);
+    }
+    if let (Some(network), Some(kpis)) = (topology.network.as_ref(), result.network.as_ref()) {
+        println!(
+            "Network: losses {:.2} kWh, bus voltage {:.3}-{:.3} pu (limits {:.3}-{:.3} pu), voltage violations {} steps",
+            kpis.loss_kwh,
+            kpis.voltage_min_pu,
+            kpis.voltage_max_pu,
+            topology.voltage_min_pu,
+            topology.voltage_max_pu,
+            kpis.voltage_violation_steps
+        );
+        if kpis.unconverged_steps > 0 {
+            println!(
+                "Power flow did not converge in {} steps",
+                kpis.unconverged_steps
+            );
+        }
+        for (name, bus) in network.bus_names().iter().zip(&kpis.buses).skip(1) {
+            println!(
+                "  bus {name}: {:.3}-{:.3} pu, outside limits {} steps",
+                bus.voltage_min_pu, bus.voltage_max_pu, bus.violation_steps
+            );
+        }
+        for (line, kpis) in network.lines().iter().zip(&kpis.lines) {
+            let rating = line
+                .ampacity_a
+                .map_or("unrated".to_string(), |a| format!("ampacity {a:.0} A"));
+            println!(
+                "  line {}: peak {:.1} A ({rating}), losses {:.2} kWh, overloaded {} steps",
+                line.name, kpis.peak_current_a, kpis.loss_kwh, kpis.overload_steps
+            );
+        }
+    }
+    for (flow, site) in result.site_flows.iter().zip(&result.sites) {
+        let kpis = &site.kpis;
+        let aging = kpis
+            .transformer
+            .as_ref()
+            .map_or(String::new(), |transformer| {
+                format!(
+                    ", transformer loss of life {:.2} h",
+                    transformer.loss_of_life_hours
+                )
+            });
+        println!(
+            "  {}: RMSE {:.3} kW, curtailment {:.1}%, EV unmet {:.2} kWh, solar curtailed {:.2} kWh{aging}",
+            flow.name,
+            kpis.rmse_tracking_kw,
+            kpis.curtailment_pct,
+            kpis.ev_unmet_kwh,
+            kpis.solar_curtailed_kwh
+        );
+    }
+}
+
+/// Peak flows against limits and violation counts of one node.
+fn print_node_line(node: &NodeFlow) {
+    let limit = |kw: f32| {
+        if kw.is_finite() {
+            format!("{kw:.2} kW")
+        } else {
+            "unlimited".to_string()
+        }
+    };
+    println!(
+        "{} {}: peak import {:.2} kW (limit {}), peak export {:.2} kW (limit {}), violations {} import / {} export steps",
+        node.level.name(),
+        node.name,
+        node.peak_import_kw(),
+        limit(node.limits.import_kw),
+        node.peak_export_kw(),
+        limit(node.limits.export_kw),
+        node.import_violations(),
+        node.export_violations()
+    );
 }
@@ -1,17 +1,221 @@
-use crate::devices::{BaseLoad, Battery, Device, DeviceContext, EvCharger, SolarPv};
+use crate::devices::{
+    ActuatorLimits, BaseLoad, Battery, BatteryDegradation, BatteryEnvelope, BatteryThermal,
+    BuildingHvac, DegradationParams, Device, DeviceContext, EvCharger, EvChargingHub,
+    EvChargingPolicy, EvHubConfig, EvSessionOutcome, Genset, GensetConfig, Inverter, SolarPv,
+    ThermalParams, VoltWattCurve, WindPowerCurve, WindSpeedSource, WindTurbine,
+};
 use crate::forecast::NaiveForecast;
-use crate::scenario::ScenarioConfig;
+use crate::scenario::{ScenarioConfig, Topology};
+use crate::settlement::{
+    EventSettlement, MeterHistory, SameDayAdjustment, SettlementTerms, baseline_kw, settle_event,
+};
 use crate::sim::clock::Clock;
-use crate::sim::controller::NaiveRtController;
-use crate::sim::event::DemandResponseEvent;
-use crate::sim::feeder::Feeder;
+use crate::sim::controller::{DroopController, NaiveRtController};
+use crate::sim::dr_allocation::DrAllocator;
+use crate::sim::envelope::{OperatingEnvelope, hosting_capacity_limits};
+use crate::sim::event::{DemandResponseEvent, DemandResponseKind, DemandResponseProgram};
+use crate::sim::feeder::{Feeder, Phase};
+use crate::sim::frequency::GridFrequency;
+use crate::sim::outage::OutageSchedule;
+use crate::sim::power_flow::{PowerFlowSolution, RadialNetwork};
+use crate::sim::rebound::Rebound;
+use crate::sim::regulation::{
+    RegulationOutcome, RegulationParams, RegulationService, RegulationSignalSource,
+};
 use crate::sim::schedule::DayAheadSchedule;
+use crate::sim::topology::{NodeFlow, NodeLevel, NodeLimits, share_excess_kw};
+use crate::sim::transformer::{
+    NORMAL_LIFE_HOURS, REFERENCE_HOT_SPOT_C, TransformerParams, TransformerThermal,
+};
+use crate::sim::weather::AmbientTemperature;
 use crate::telemetry::TelemetryRow;
 
 pub struct SimulationKpis {
     pub rmse_tracking_kw: f32,
     pub curtailment_pct: f32,
     pub feeder_peak_load_kw: f32,
+    /// Feeder import limit the peaks are compared against.
+    pub feeder_import_limit_kw: f32,
+    /// Energy requested by EV sessions (home charger and hub) that departed during the run.
+    pub ev_energy_requested_kwh: f32,
+    /// Energy delivered to those sessions before departure.
+    pub ev_energy_delivered_kwh: f32,
+    /// Energy still missing when those sessions departed.
+    pub ev_unmet_kwh: f32,
+    /// Number of EV sessions that departed during the run.
+    pub ev_sessions_departed: usize,
+    /// Number of departed EV sessions that left without their requested energy.
+    pub ev_sessions_unsatisfied: usize,
+    /// Allocation policy shared by the houses' EV sessions.
+    pub ev_policy: EvChargingPolicy,
+    /// Allocation policy of the EV charging hub, if one is configured.
+    pub ev_hub_policy: Option<EvChargingPolicy>,
+    /// Per-session outcomes of EV hub sessions that departed during the run.
+    pub ev_hub_sessions: Vec<EvSessionOutcome>,
+    /// Equivalent full cycles performed by the battery.
+    pub battery_equivalent_full_cycles: f32,
+    /// Battery capacity lost to aging, in percent of nominal capacity.
+    pub battery_capacity_fade_pct: f32,
+    /// Highest battery cell temperature, if the thermal model is enabled.
+    pub battery_peak_temp_c: Option<f32>,
+    /// Steps in which cell temperature derated the battery power rating.
+    pub battery_derated_steps: usize,
+    /// Steps in which ramp, latency or min on/off limits kept the battery from its setpoint.
+    pub battery_unhonored_commands: usize,
+    /// Steps in which actuator limits kept an EV charger (home or hub) from its command.
+    pub ev_unhonored_commands: usize,
+    /// Steps in which feeder import or export exceeded its limit.
+    pub feeder_limit_violations: usize,
+    /// Solar energy withheld to respect the feeder export limit.
+    pub solar_curtailed_kwh: f32,
+    /// Wind energy generated, if a turbine is configured.
+    pub wind_energy_kwh: Option<f32>,
+    /// Wind energy withheld to respect the feeder export limit.
+    pub wind_curtailed_kwh: f32,
+    /// Lowest estimated site voltage (per unit).
+    pub voltage_min_pu: f32,
+    /// Highest estimated site voltage (per unit).
+    pub voltage_max_pu: f32,
+    /// Backup generator totals, if a genset is configured.
+    pub genset: Option<GensetKpis>,
+    /// Load dropped while islanded during grid outages.
+    pub unserved_energy_kwh: f32,
+    /// Per-outage resilience outcomes.
+    pub outages: Vec<OutageOutcome>,
+    /// Per-event demand response outcomes.
+    pub dr_events: Vec<DrEventOutcome>,
+    /// Regulation score and revenue, if the battery offers regulation.
+    pub regulation: Option<RegulationOutcome>,
+    /// Frequency excursions and droop response, if droop response is enabled.
+    pub frequency_response: Option<FrequencyResponseKpis>,
+    /// Per-phase loading and unbalance, if any device is on a single phase.
+    pub unbalance: Option<UnbalanceKpis>,
+    /// Loading, temperatures and aging of the site transformer, if rated.
+    pub transformer: Option<TransformerKpis>,
+    /// Compliance with the DSO's operating envelope, if one is set.
+    pub envelope: Option<EnvelopeKpis>,
+}
+
+/// How closely the site held to its dynamic operating envelope.
+pub struct EnvelopeKpis {
+    /// Steps in which the envelope was tighter than the connection limits.
+    pub constrained_steps: usize,
+    /// Steps above the envelope's import limit.
+    pub import_violations: usize,
+    /// Steps beyond the envelope's export limit.
+    pub export_violations: usize,
+    /// Largest flow beyond the envelope (kW).
+    pub peak_excess_kw: f32,
+    /// Energy beyond the envelope over the run (kWh).
+    pub excess_kwh: f32,
+}
+
+/// Requested and delivered energy of one demand response event.
+pub struct DrEventOutcome {
+    pub kind: DemandResponseKind,
+    pub start_step: usize,
+    pub end_step: usize,
+    /// Load change requested over the event (kWh).
+    pub requested_kwh: f32,
+    /// Load change delivered over the event (kWh).
+    pub delivered_kwh: f32,
+    /// Performance and payment against the customer baseline, if settled.
+    pub settlement: Option<EventSettlement>,
+    /// Highest feeder load in the rebound window after the event, if the run
+    /// continues past it.
+    pub post_event_peak_kw: Option<f32>,
+}
+
+/// How the site rode through one grid outage.
+pub struct OutageOutcome {
+    pub start_step: usize,
+    pub end_step: usize,
+    /// Steps from the start of the outage until load first had to be dropped.
+    pub survived_steps: usize,
+    /// Baseload dropped during the outage (kWh).
+    pub baseload_shed_kwh: f32,
+    /// EV charging dropped during the outage (kWh).
+    pub ev_shed_kwh: f32,
+}
+
+impl OutageOutcome {
+    pub fn duration_steps(&self) -> usize {
+        self.end_step - self.start_step
+    }
+
+    pub fn unserved_kwh(&self) -> f32 {
+        self.baseload_shed_kwh + self.ev_shed_kwh
+    }
+}
+
+/// Output, fuel and emissions totals of the backup generator.
+pub struct GensetKpis {
+    pub energy_kwh: f32,
+    /// Part of `energy_kwh` that covered demand response shortfall.
+    pub dr_energy_kwh: f32,
+    pub fuel_used_l: f32,
+    pub co2_kg: f32,
+    pub nox_kg: f32,
+    pub starts: usize,
+}
+
+/// Grid frequency seen during the run and the droop response to it.
+pub struct FrequencyResponseKpis {
+    pub min_hz: f32,
+    pub max_hz: f32,
+    /// Steps with frequency outside the deadband.
+    pub steps_outside_deadband: usize,
+    /// Largest combined battery and EV response (kW).
+    pub peak_response_kw: f32,
+    /// Battery energy moved in response, discharge and charge alike (kWh).
+    pub battery_energy_kwh: f32,
+    /// EV charging shed on under-frequency (kWh).
+    pub ev_shed_kwh: f32,
+}
+
+/// Per-phase feeder loading and unbalance factors over the run.
+pub struct UnbalanceKpis {
+    /// Highest import on phases A, B and C (kW).
+    pub phase_peak_kw: [f32; 3],
+    /// Steps in which a phase carried more than a third of the feeder's
+    /// import or export limit.
+    pub phase_overload_steps: usize,
+    /// Lowest and highest estimated phase voltage (per unit).
+    pub phase_voltage_min_pu: f32,
+    pub phase_voltage_max_pu: f32,
+    pub voltage_unbalance_max_pct: f32,
+    /// Steps with voltage unbalance above [`VOLTAGE_UNBALANCE_LIMIT_PCT`].
+    pub voltage_unbalance_violations: usize,
+    /// Current unbalance in the step with the highest phase loading.
+    pub current_unbalance_at_peak_pct: f32,
+}
+
+/// Voltage unbalance accepted by common planning limits (EN 50160, IEC
+/// 61000-2-2), in percent.
+pub const VOLTAGE_UNBALANCE_LIMIT_PCT: f32 = 2.0;
+
+/// Loading, temperatures and insulation aging of the site transformer.
+pub struct TransformerKpis {
+    pub rated_kva: f32,
+    pub peak_loading_pct: f32,
+    /// Steps loaded above nameplate.
+    pub overload_steps: usize,
+    pub peak_top_oil_c: f32,
+    pub peak_hot_spot_c: f32,
+    /// Steps with the hot spot above [`REFERENCE_HOT_SPOT_C`], where
+    /// insulation ages faster than normal.
+    pub hot_spot_over_reference_steps: usize,
+    /// Average aging rate over the run relative to the normal rate.
+    pub equivalent_aging_factor: f32,
+    /// Insulation life consumed, in hours at the normal aging rate.
+    pub loss_of_life_hours: f32,
+}
+
+impl TransformerKpis {
+    /// Loss of life as a share of normal insulation life.
+    pub fn loss_of_life_pct(&self) -> f32 {
+        100.0 * self.loss_of_life_hours / NORMAL_LIFE_HOURS
+    }
 }
 
 pub struct SimulationResult {
@@ -22,8 +226,9 @@ pub struct SimulationResult {
 pub fn run_scenario(config: &ScenarioConfig, print_readable_log: bool) -> SimulationResult {
     let houses = config.houses as f32;
     let steps_per_day = config.steps_per_day;
+    let total_steps = steps_per_day * config.days;
     let dt_hr = 24.0 / steps_per_day as f32;
-    let mut clock = Clock::new(steps_per_day); // Simulate 1 day
+    let mut clock = Clock::new(total_steps);
 
     let mut load = BaseLoad::new(
         0.8 * houses,  /* base_kw */
@@ -43,25 +248,53 @@ pub fn run_scenario(config: &ScenarioConfig, print_readable_log: bool) -> Simula
         steps_per_day,
         config.seed,
     );
+    // Slow inputs change only every `slow_update_steps` and are held in between.
+    let slow_step = |t: usize| t - t % config.slow_update_steps;
     let mut baseline = Vec::with_capacity(steps_per_day);
     for t in 0..steps_per_day {
-        baseline.push(baseline_load.power_kw(&DeviceContext::new(t)));
+        baseline.push(baseline_load.power_kw(&DeviceContext::new(slow_step(t))));
     }
     let forecaster = NaiveForecast;
-    let load_forecast = forecaster.forecast(&baseline, steps_per_day);
+    let load_forecast = forecaster.forecast(&baseline, total_steps);
     let target_schedule = DayAheadSchedule::flat_target(&load_forecast);
 
     let mut pv = SolarPv::new(
         config.solar_kw_peak_per_house * houses, /* kw_peak */
         steps_per_day,                           /* steps_per_day */
-        6,                                       /* sunrise_idx (6 AM) */
-        18,                                      /* sunset_idx (6 PM) */
+        6 * steps_per_day / 24,                  /* sunrise_idx (6 AM) */
+        18 * steps_per_day / 24,                 /* sunset_idx (6 PM) */
         0.05,                                    /* noise_std */
         config.seed.wrapping_add(1),             /* seed */
-    );
+    )
+    .with_inverter(inverter(
+        config,
+        config.solar_kw_peak_per_house * houses * config.solar_inverter_kva_ratio,
+    ));
 
     let solar_device = pv.device_type();
 
+    let mut wind = (config.wind_rated_kw > 0.0).then(|| {
+        let source = match &config.wind_speed_series {
+            Some(speeds) => WindSpeedSource::Series(speeds.clone()),
+            None => WindSpeedSource::Synthetic {
+                weibull_shape: config.wind_weibull_shape,
+                weibull_scale_ms: config.wind_weibull_scale_ms,
+                autocorrelation: config.wind_autocorrelation,
+            },
+        };
+        WindTurbine::new(
+            WindPowerCurve::new(
+                config.wind_rated_kw,
+                config.wind_cut_in_ms,
+                config.wind_rated_ms,
+                config.wind_cut_out_ms,
+            ),
+            source,
+            steps_per_day,
+            config.seed.wrapping_add(4),
+        )
+    });
+
     let mut battery = Battery::new(
         10.0 * houses, /* capacity_kwh */
         0.5,           /* initial_soc */
@@ -71,92 +304,682 @@ pub fn run_scenario(config: &ScenarioConfig, print_readable_log: bool) -> Simula
         0.95,          /* eta_d */
         steps_per_day, /* steps_per_day */
     );
+    battery = battery.with_envelope(BatteryEnvelope {
+        soc_min: config.battery_soc_min,
+        soc_max: config.battery_soc_max,
+        reserve_soc: config.battery_reserve_soc,
+        self_discharge_per_day: config.battery_self_discharge_per_day,
+        aux_load_kw: config.battery_aux_kw_per_house * houses,
+    });
+    if config.battery_degradation {
+        battery = battery.with_degradation(BatteryDegradation::new(DegradationParams {
+            cycle_life_full_dod: config.battery_cycle_life,
+            dod_exponent: config.battery_dod_exponent,
+            end_of_life_fade: config.battery_eol_fade,
+            calendar_fade_per_year: config.battery_calendar_fade_per_year,
+            resistance_growth_per_fade: config.battery_resistance_growth,
+            ..DegradationParams::default()
+        }));
+    }
+
+    let ambient = AmbientTemperature::new(
+        config.ambient_temp_mean_c,
+        config.ambient_temp_amplitude_c,
+        steps_per_day,
+    )
+    .with_daily_variation(
+        config.ambient_temp_day_std_c,
+        config.days,
+        config.seed.wrapping_add(7),
+    );
+    if config.battery_thermal {
+        battery = battery.with_thermal(BatteryThermal::new(
+            ThermalParams::for_capacity(10.0 * houses),
+            ambient.temp_c(0),
+        ));
+    }
+    let mut hvac = (config.hvac_kw_per_house > 0.0).then(|| {
+        BuildingHvac::new(
+            houses,
+            config.hvac_kw_per_house,
+            config.hvac_setpoint_c,
+            dt_hr,
+        )
+    });
+    let mut transformer = (config.transformer_kva > 0.0).then(|| {
+        TransformerThermal::new(
+            TransformerParams {
+                top_oil_rise_c: config.transformer_top_oil_rise_c,
+                hot_spot_rise_c: config.transformer_hot_spot_rise_c,
+                loss_ratio: config.transformer_loss_ratio,
+                oil_time_constant_hr: config.transformer_oil_time_constant_hr,
+                winding_time_constant_hr: config.transformer_winding_time_constant_hr,
+                ..TransformerParams::onan(config.transformer_kva)
+            },
+            ambient.temp_c(0),
+        )
+    });
+
+    battery = battery.with_dynamics(actuator_limits(
+        config.battery_ramp_kw_per_min_per_house * houses,
+        config.battery_latency_steps,
+        config.battery_min_on_steps,
+        config.battery_min_off_steps,
+    ));
+
+    let battery_inverter_kva = battery.max_discharge_kw * config.battery_inverter_kva_ratio;
+    battery = battery.with_inverter(inverter(config, battery_inverter_kva));
 
     let battery_device = battery.device_type();
+
+    let mut regulation = (config.regulation_kw_per_house > 0.0).then(|| {
+        let source = match &config.regulation_signal_series {
+            Some(values) => RegulationSignalSource::Series(values.clone()),
+            None => RegulationSignalSource::Synthetic(config.regulation_signal),
+        };
+        RegulationService::new(
+            RegulationParams {
+                capacity_kw: config.regulation_kw_per_house * houses,
+                response_delay_s: config.regulation_response_delay_s,
+                ramp_kw_per_s: config.battery_ramp_kw_per_min_per_house * houses / 60.0,
+                capacity_price: config.regulation_capacity_price,
+                performance_price: config.regulation_performance_price,
+            },
+            source,
+            config.seed.wrapping_add(6),
+        )
+    });
+    let seconds_per_step = 86_400 / steps_per_day;
+
+    let mut grid_frequency =
+        GridFrequency::new(config.frequency_nominal_hz, config.frequency_events.clone());
+    if let Some(series) = &config.frequency_series {
+        grid_frequency = grid_frequency.with_series(series.clone());
+    }
+    let droop = config.frequency_droop_response.then_some(DroopController {
+        nominal_hz: config.frequency_nominal_hz,
+        deadband_hz: config.frequency_deadband_hz,
+        droop: config.frequency_droop,
+    });
+
+    let mut genset = (config.genset_rated_kw > 0.0).then(|| {
+        let diesel = GensetConfig::diesel(config.genset_rated_kw, steps_per_day);
+        Genset::new(GensetConfig {
+            min_load_fraction: config.genset_min_load_fraction,
+            startup_steps: config.genset_startup_steps,
+            idle_fuel_l_per_h_per_kw: config.genset_idle_fuel_l_per_h_per_kw,
+            fuel_l_per_kwh: config.genset_fuel_l_per_kwh,
+            tank_l: config.genset_tank_l.unwrap_or(
+                8.0 * config.genset_rated_kw
+                    * (config.genset_idle_fuel_l_per_h_per_kw + config.genset_fuel_l_per_kwh),
+            ),
+            co2_kg_per_l: config.genset_co2_kg_per_l,
+            nox_g_per_kwh: config.genset_nox_g_per_kwh,
+            ..diesel
+        })
+    });
+
+    // One vehicle per house, each with its own session and charger.
     let mut ev = EvCharger::new(
-        7.2 * houses,                /* max_charge_kw */
-        steps_per_day,               /* steps_per_day */
-        4.0 * houses,                /* demand_kwh_min */
-        14.0 * houses,               /* demand_kwh_max */
-        3,                           /* dwell_steps_min */
-        10,                          /* dwell_steps_max */
-        config.seed.wrapping_add(2), /* seed */
-    );
+        7.2,                              /* max_charge_kw */
+        steps_per_day,                    /* steps_per_day */
+        4.0,                              /* demand_kwh_min */
+        14.0,                             /* demand_kwh_max */
+        (3 * steps_per_day / 24).max(1),  /* dwell_steps_min (3 h) */
+        (10 * steps_per_day / 24).max(1), /* dwell_steps_max (10 h) */
+        config.seed.wrapping_add(2),      /* seed */
+    )
+    .with_fleet(config.houses as usize, config.ev_policy)
+    .with_dynamics(actuator_limits(
+        config.ev_ramp_kw_per_min_per_house * houses,
+        config.ev_latency_steps,
+        config.ev_min_on_steps,
+        config.ev_min_off_steps,
+    ));
     let ev_device = ev.device_type();
 
+    let mut ev_hub = (config.ev_hub_ports > 0).then(|| {
+        EvChargingHub::new(
+            EvHubConfig {
+                ports: config.ev_hub_ports,
+                port_max_kw: config.ev_hub_port_kw,
+                site_cap_kw: config.ev_hub_site_cap_kw,
+                steps_per_day,
+                arrivals_per_day: config.ev_hub_arrivals_per_day,
+                arrival_mean_step: config.ev_hub_arrival_mean_step,
+                arrival_std_steps: config.ev_hub_arrival_std_steps,
+                demand_kwh_min: config.ev_hub_demand_kwh_min,
+                demand_kwh_max: config.ev_hub_demand_kwh_max,
+                dwell_steps_min: config.ev_hub_dwell_steps_min,
+                dwell_steps_max: config.ev_hub_dwell_steps_max,
+                policy: config.ev_hub_policy,
+                priority_share: config.ev_hub_priority_share,
+            },
+            config.seed.wrapping_add(3),
+        )
+        .with_dynamics(actuator_limits(
+            config.ev_hub_ramp_kw_per_min,
+            config.ev_latency_steps,
+            config.ev_min_on_steps,
+            config.ev_min_off_steps,
+        ))
+    });
+
     let mut feeder = Feeder::with_limits(
         "MainFeeder",
         config.feeder_kw,       /* max_import_kw */
         config.feeder_kw * 0.8, /* max_export_kw */
+    )
+    .with_impedance(config.feeder_r_pu, config.feeder_x_pu);
+    let baseload_kvar_per_kw = config.baseload_power_factor.acos().tan();
+
+    let dr_events = if config.dr_events.is_empty() {
+        vec![
+            DemandResponseEvent::new(
+                config.dr_start_step,
+                config.dr_end_step,
+                config.dr_reduction_kw_per_house * houses,
+            )
+            .with_notice(config.dr_notice_steps),
+        ]
+    } else {
+        config
+            .dr_events
+            .iter()
+            .flat_map(|spec| {
+                let days = match spec.day {
+                    Some(day) => day..day + 1,
+                    None => 0..config.days,
+                };
+                days.map(|day| {
+                    spec.event
+                        .clone()
+                        .scaled(houses)
+                        .shifted(day * steps_per_day)
+                })
+            })
+            .collect()
+    };
+    let dr_program = DemandResponseProgram::new(dr_events);
+    let mut dr_outcomes: Vec<DrEventOutcome> = dr_program
+        .events()
+        .iter()
+        .map(|event| DrEventOutcome {
+            kind: event.kind,
+            start_step: event.start_step,
+            end_step: event.end_step,
+            requested_kwh: 0.0,
+            delivered_kwh: 0.0,
+            settlement: None,
+            post_event_peak_kw: None,
+        })
+        .collect();
+
+    let mut rebound = Rebound::new(
+        config.dr_rebound_fraction,
+        config.dr_rebound_steps,
+        config.dr_rebound_shape,
+        steps_per_day,
     );
 
-    let dr_event = DemandResponseEvent::new(
-        config.dr_start_step,
-        config.dr_end_step,
-        config.dr_reduction_kw_per_house * houses,
+    // Registration order of the devices the DR allocator splits requests across.
+    let mut dr_allocator = DrAllocator::new(vec![
+        config.dr_ev,
+        config.dr_ev_hub,
+        config.dr_baseload,
+        config.dr_battery,
+        config.dr_hvac,
+    ]);
+
+    let outage_schedule = OutageSchedule::new(config.outage_windows.clone()).with_stochastic(
+        config.outage_rate_per_day,
+        config.outage_mean_duration_steps,
+        steps_per_day,
+        total_steps,
+        config.seed.wrapping_add(5),
     );
 
     let controller = NaiveRtController;
 
-    let mut telemetry = Vec::with_capacity(steps_per_day);
+    let mut telemetry = Vec::with_capacity(total_steps);
     let mut tracking_error_sq_sum = 0.0_f32;
     let mut tracking_error_count = 0_usize;
     let mut requested_curtailment_sum_kw = 0.0_f32;
     let mut achieved_curtailment_sum_kw = 0.0_f32;
     let mut feeder_peak_load_kw = 0.0_f32;
+    let mut ev_outcomes_seen = 0_usize;
+    let mut ev_hub_outcomes_seen = 0_usize;
+    let mut battery_peak_temp_c: Option<f32> = None;
+    let mut battery_derated_steps = 0_usize;
+    let mut feeder_limit_violations = 0_usize;
+    let mut wind_energy_kwh = 0.0_f32;
+    let mut genset_dr_energy_kwh = 0.0_f32;
+    // Devices see the voltage measured at the end of the previous step.
+    let mut voltage_pu = 1.0_f32;
+    let mut voltage_min_pu = f32::INFINITY;
+    let mut voltage_max_pu = f32::NEG_INFINITY;
+    let mut phase_voltage_min_pu = f32::INFINITY;
+    let mut phase_voltage_max_pu = f32::NEG_INFINITY;
+    // Meter limits before the DSO's envelope: `feeder_kw`, or the tighter
+    // per-step limits the VPP holds a topology site to.
+    let connection_limits = |t: usize| {
+        NodeLimits::new(
+            config
+                .import_limit_kw_by_step
+                .get(t)
+                .copied()
+                .unwrap_or(config.feeder_kw),
+            config
+                .export_limit_kw_by_step
+                .get(t)
+                .copied()
+                .unwrap_or(config.feeder_kw * 0.8),
+        )
+    };
+    let envelope = operating_envelope(config, total_steps);
 
     clock.run(|t| {
-        let context = DeviceContext::new(t);
+        let slow_t = slow_step(t);
+        let ambient_temp_c = ambient.temp_c(slow_t);
+        let context = DeviceContext::new(t)
+            .with_ambient_temp_c(ambient_temp_c)
+            .with_voltage_pu(voltage_pu);
+        let slow_context = DeviceContext::new(slow_t).with_voltage_pu(voltage_pu);
 
-        let base_demand_kw_raw = load.power_kw(&context);
+        // Load shed by earlier events returns once no event is active.
+        let rebound_kw = rebound.next_kw(dr_program.active(t).is_some());
+        let base_demand_kw_raw = load.power_kw(&slow_context) + rebound_kw;
         let forecast_kw = load_forecast[context.timestep];
-        let target_kw = target_schedule[context.timestep];
-        let solar_kw = pv.power_kw(&context);
-        let ev_requested_kw = ev.requested_power_kw(&context);
+        let scheduled_kw = target_schedule[context.timestep];
+        let solar_available_kw = pv.available_kw(&slow_context);
+        let (wind_available_kw, wind_speed_ms) = match wind.as_mut() {
+            Some(turbine) => (
+                turbine.available_kw(&slow_context),
+                turbine.wind_speed_ms(slow_t),
+            ),
+            None => (0.0, 0.0),
+        };
+        let ev_home_requested_kw = ev.requested_power_kw(&context);
+        let ev_hub_requested_kw = ev_hub
+            .as_mut()
+            .map(|hub| hub.requested_power_kw(&context))
+            .unwrap_or(0.0);
+        let ev_requested_kw = ev_home_requested_kw + ev_hub_requested_kw;
 
-        let dr_requested_kw = dr_event.requested_reduction_at_kw(t);
-        let (base_demand_kw, ev_after_dr_kw, dr_achieved_kw) = controller.apply_demand_response_kw(
-            base_demand_kw_raw,
-            ev_requested_kw,
-            dr_requested_kw,
-        );
+        let dr_event = dr_program.active(t);
+        let islanded = outage_schedule.active_index(t).is_some();
+        // From the notice of a shed or target-level event until it starts,
+        // the houses are cooled below the setpoint so they can coast through
+        // it; during the event cooling may be shed until indoor temperature
+        // reaches the setback limit.
+        let precool = config.dr_pre_event_prep
+            && dr_event.is_none()
+            && !islanded
+            && dr_program
+                .upcoming(t)
+                .is_some_and(|(_, event)| event.kind != DemandResponseKind::Take);
+        let (hvac_requested_kw, hvac_sheddable_kw) = match hvac.as_ref() {
+            Some(unit) => {
+                let target_c = if precool {
+                    unit.setpoint_c - config.hvac_precool_c
+                } else {
+                    unit.setpoint_c
+                };
+                let requested_kw = unit.power_for_target_kw(ambient_temp_c, target_c);
+                let setback_kw = unit.power_for_target_kw(
+                    ambient_temp_c,
+                    unit.setpoint_c + config.hvac_max_setback_c,
+                );
+                (requested_kw, (requested_kw - setback_kw).max(0.0))
+            }
+            None => (0.0, 0.0),
+        };
+        let dr_kind = dr_event.map(|(_, event)| event.kind);
+        let dr_signal_kw = dr_event.map_or(0.0, |(_, event)| event.requested_at_kw(t));
+        // Shed events ask for a load reduction, take events raise the target,
+        // and target-level events cap feeder load, shedding if the battery
+        // cannot hold the level.
+        let (target_kw, dr_requested_kw, shed_request_kw) = match dr_kind {
+            Some(DemandResponseKind::Shed) => (scheduled_kw, dr_signal_kw, dr_signal_kw),
+            Some(DemandResponseKind::Take) => (scheduled_kw + dr_signal_kw, dr_signal_kw, 0.0),
+            Some(DemandResponseKind::TargetLevel) => (
+                scheduled_kw.min(dr_signal_kw),
+                (scheduled_kw - dr_signal_kw).max(0.0),
+                controller.target_level_shed_kw(
+                    base_demand_kw_raw + hvac_requested_kw + ev_requested_kw
+                        - solar_available_kw
+                        - wind_available_kw,
+                    battery.available_discharge_kw(true),
+                    dr_signal_kw,
+                ),
+            ),
+            None => (scheduled_kw, 0.0, 0.0),
+        };
+        let dr_active = dr_kind != Some(DemandResponseKind::Take)
+            && (dr_requested_kw > 0.0 || shed_request_kw > 0.0);
+        feeder.set_islanded(islanded);
+        let limits = envelope.as_ref().map_or(connection_limits(t), |limits| {
+            connection_limits(t).tightest(limits[t])
+        });
+        feeder.set_limits(limits.import_kw, limits.export_kw);
+        let frequency_hz = grid_frequency.hz(t);
+        // An islanded site is cut off from the grid frequency it would support.
+        let droop = droop.filter(|_| !islanded);
+        // The backup reserve may only be used to deliver demand response or
+        // to ride through a grid outage.
+        let reserve_access = dr_active || islanded;
+        // Battery discharge only counts toward shed events; target-level
+        // events already use it to hold the level.
+        let battery_dr_available_kw = if dr_kind == Some(DemandResponseKind::Shed) {
+            battery.available_discharge_kw(true)
+        } else {
+            0.0
+        };
+        let [
+            ev_home_shed_kw,
+            ev_hub_shed_kw,
+            baseload_shed_kw,
+            battery_dr_request_kw,
+            hvac_shed_kw,
+        ] = match dr_event {
+            Some((idx, _)) if shed_request_kw > 0.0 => dr_allocator.allocate(
+                idx,
+                [
+                    ev_home_requested_kw,
+                    ev_hub_requested_kw,
+                    base_demand_kw_raw,
+                    battery_dr_available_kw,
+                    hvac_sheddable_kw,
+                ],
+                shed_request_kw,
+            ),
+            _ => [0.0; 5],
+        };
+        let base_demand_kw = base_demand_kw_raw - baseload_shed_kw;
+        let hvac_kw = hvac.as_mut().map_or(0.0, |unit| {
+            unit.power_kw(
+                &DeviceContext::with_setpoint(context.timestep, hvac_requested_kw - hvac_shed_kw)
+                    .with_ambient_temp_c(ambient_temp_c),
+            )
+        });
+        let ev_home_after_dr_kw = ev_home_requested_kw - ev_home_shed_kw;
+        let ev_hub_after_dr_kw = ev_hub_requested_kw - ev_hub_shed_kw;
+        let ev_after_dr_kw = ev_home_after_dr_kw + ev_hub_after_dr_kw;
+        let mut shed_achieved_kw = ev_home_shed_kw
+            + ev_hub_shed_kw
+            + baseload_shed_kw
+            + battery_dr_request_kw
+            + hvac_shed_kw;
+        // Shed EV energy stays with its sessions and returns on its own.
+        rebound.defer(baseload_shed_kw);
 
-        let net_fixed_kw = base_demand_kw - solar_kw;
+        // The genset covers whatever DR reduction load shedding could not,
+        // and what the battery cannot supply while islanded.
+        let (genset_kw, genset_dr_kw) = match genset.as_mut() {
+            Some(unit) => {
+                let mut setpoint_kw = controller.backup_generation_kw(
+                    shed_request_kw,
+                    shed_achieved_kw,
+                    unit.rated_kw(),
+                );
+                if islanded {
+                    setpoint_kw = setpoint_kw.max(controller.island_generation_kw(
+                        base_demand_kw + hvac_kw + ev_after_dr_kw
+                            - solar_available_kw
+                            - wind_available_kw,
+                        battery.available_discharge_kw(true),
+                        unit.rated_kw(),
+                    ));
+                }
+                let kw =
+                    unit.power_kw(&DeviceContext::with_setpoint(context.timestep, setpoint_kw));
+                let dr_kw = kw.min(shed_request_kw - shed_achieved_kw);
+                shed_achieved_kw += dr_kw;
+                genset_dr_energy_kwh += dr_kw * dt_hr;
+                (kw, dr_kw)
+            }
+            None => (0.0, 0.0),
+        };
+
+        let net_fixed_kw =
+            base_demand_kw + hvac_kw - solar_available_kw - wind_available_kw - genset_kw;
         let ev_capped_kw = controller.capped_flexible_load_kw(
             net_fixed_kw,
             ev_after_dr_kw,
             feeder.max_import_kw(),
-            battery.max_discharge_kw,
+            battery.available_discharge_kw(reserve_access),
+        );
+        // Share the capped EV allowance between the home charger and the hub
+        // in proportion to what DR left of their requests.
+        let ev_share = if ev_after_dr_kw > 0.0 {
+            (ev_capped_kw / ev_after_dr_kw).min(1.0)
+        } else {
+            0.0
+        };
+        // Under-frequency sheds EV charging in proportion to the deviation.
+        let ev_droop_kw = |kw: f32| droop.map_or(0.0, |c| c.load_shed_kw(frequency_hz, kw));
+        let ev_home_droop_kw = ev_droop_kw(ev_home_after_dr_kw * ev_share);
+        let ev_hub_droop_kw = ev_droop_kw(ev_hub_after_dr_kw * ev_share);
+        let ev_context = DeviceContext::with_setpoint(
+            context.timestep,
+            ev_home_after_dr_kw * ev_share - ev_home_droop_kw,
         );
-        let ev_context = DeviceContext::with_setpoint(context.timestep, ev_capped_kw);
-        let ev_kw = ev.power_kw(&ev_context);
+        let ev_home_kw = ev.power_kw(&ev_context);
+        let mut ev_cmd_shortfall_kw = ev.command_shortfall_kw();
+        let (ev_hub_kw, ev_hub_queued) = match ev_hub.as_mut() {
+            Some(hub) => {
+                let hub_context = DeviceContext::with_setpoint(
+                    context.timestep,
+                    ev_hub_after_dr_kw * ev_share - ev_hub_droop_kw,
+                );
+                let hub_kw = hub.power_kw(&hub_context);
+                ev_cmd_shortfall_kw += hub.command_shortfall_kw();
+                (hub_kw, hub.queued_count())
+            }
+            None => (0.0, 0),
+        };
+        let ev_kw = ev_home_kw + ev_hub_kw;
+        let mut ev_unmet_kwh = unmet_kwh_since(ev.session_outcomes(), &mut ev_outcomes_seen);
+        if let Some(hub) = ev_hub.as_ref() {
+            ev_unmet_kwh += unmet_kwh_since(hub.session_outcomes(), &mut ev_hub_outcomes_seen);
+        }
 
         let net_without_battery = net_fixed_kw + ev_kw;
-        let battery_setpoint_kw = controller.constrained_battery_setpoint_kw(
-            net_without_battery,
-            target_kw,
-            feeder.max_import_kw(),
-            feeder.max_export_kw(),
-            battery.max_charge_kw,
-            battery.max_discharge_kw,
-        );
-        let battery_context = DeviceContext::with_setpoint(context.timestep, battery_setpoint_kw);
+        // Once an event is announced, move the battery toward what it needs:
+        // full before shed and target-level events, down to the reserve
+        // before take events.
+        let dr_prep_kw = match dr_program.upcoming(t) {
+            Some((_, event)) if config.dr_pre_event_prep && dr_event.is_none() && !islanded => {
+                let steps_left = event.start_step - t;
+                match event.kind {
+                    DemandResponseKind::Take => controller
+                        .pre_event_battery_kw(
+                            battery.soc,
+                            battery.envelope.reserve_soc.max(battery.envelope.soc_min),
+                            battery.capacity_kwh,
+                            steps_left,
+                            dt_hr,
+                        )
+                        .max(0.0),
+                    _ => controller
+                        .pre_event_battery_kw(
+                            battery.soc,
+                            battery.envelope.soc_max,
+                            battery.capacity_kwh,
+                            steps_left,
+                            dt_hr,
+                        )
+                        .min(0.0),
+                }
+            }
+            _ => 0.0,
+        };
+        let battery_target_kw =
+            controller.pre_event_target_kw(target_kw, net_without_battery, dr_prep_kw);
+        // Regulation capacity is held back from the base point in both
+        // directions; no regulation is offered while islanded.
+        let regulation_reserve_kw = match regulation.as_ref() {
+            Some(service) if !islanded => service.capacity_kw(),
+            _ => 0.0,
+        };
+        let battery_setpoint_for_kw = |target_kw: f32| {
+            controller.constrained_battery_setpoint_kw(
+                net_without_battery,
+                target_kw,
+                feeder.max_import_kw(),
+                feeder.max_export_kw(),
+                (battery.available_charge_kw() - regulation_reserve_kw).max(0.0),
+                (battery.available_discharge_kw(reserve_access) - regulation_reserve_kw).max(0.0),
+            )
+        };
+        // Battery DR is extra discharge below the target it would otherwise track.
+        let battery_tracking_kw = battery_setpoint_for_kw(battery_target_kw);
+        let battery_setpoint_kw =
+            battery_setpoint_for_kw(battery_target_kw - battery_dr_request_kw);
+        // Within the step the battery follows the regulation signal around
+        // its base point; the step sees the mean response.
+        let regulation_kw = match regulation.as_mut() {
+            Some(service) if !islanded => service.follow(
+                seconds_per_step,
+                battery.available_discharge_kw(reserve_access) - battery_setpoint_kw,
+                battery.available_charge_kw() + battery_setpoint_kw,
+            ),
+            _ => 0.0,
+        };
+        // Droop response rides on top of the base point and regulation.
+        let battery_droop_kw = droop.map_or(0.0, |c| {
+            c.battery_kw(
+                frequency_hz,
+                battery.max_discharge_kw,
+                battery.available_discharge_kw(reserve_access)
+                    - battery_setpoint_kw
+                    - regulation_kw,
+                battery.available_charge_kw() + battery_setpoint_kw + regulation_kw,
+            )
+        });
+        if battery.derate_factor() < 1.0 {
+            battery_derated_steps += 1;
+        }
+        let mut battery_context = DeviceContext::with_setpoint(
+            context.timestep,
+            battery_setpoint_kw + regulation_kw + battery_droop_kw,
+        )
+        .with_ambient_temp_c(ambient_temp_c)
+        .with_voltage_pu(voltage_pu);
+        if reserve_access {
+            battery_context = battery_context.with_reserve_access();
+        }
 
         let battery_kw = battery.power_kw(&battery_context);
+        let battery_dr_kw = (battery_kw - regulation_kw - battery_droop_kw - battery_tracking_kw)
+            .clamp(0.0, battery_dr_request_kw);
+        shed_achieved_kw += battery_dr_kw - battery_dr_request_kw;
+        let battery_temp_c = battery.temp_c();
+        if let Some(temp_c) = battery_temp_c {
+            battery_peak_temp_c = Some(battery_peak_temp_c.map_or(temp_c, |peak| peak.max(temp_c)));
+        }
+
+        // Islanded, drop baseload the battery and generation cannot supply.
+        let (base_demand_kw, unserved_baseload_kw, unserved_ev_kw) = if islanded {
+            let shed_kw = controller.island_baseload_shed_kw(
+                base_demand_kw + hvac_kw + ev_kw,
+                battery_kw + genset_kw + solar_available_kw + wind_available_kw,
+                base_demand_kw,
+            );
+            (
+                base_demand_kw - shed_kw,
+                shed_kw,
+                (ev_after_dr_kw - ev_kw).max(0.0),
+            )
+        } else {
+            (base_demand_kw, 0.0, 0.0)
+        };
+
+        // Curtail solar last, once the battery has absorbed what it can.
+        let mut solar_context = slow_context;
+        if config.solar_curtailment {
+            solar_context.setpoint_kw = Some(controller.solar_export_limit_kw(
+                base_demand_kw + hvac_kw + ev_kw - battery_kw - genset_kw,
+                solar_available_kw,
+                feeder.max_export_kw(),
+            ));
+        }
+        let solar_kw = pv.power_kw(&solar_context);
+        let solar_curtailed_kw = pv.curtailed_kw();
+        // Wind takes what export allowance solar left, so it is curtailed first.
+        let (wind_kw, wind_curtailed_kw) = match wind.as_mut() {
+            Some(turbine) => {
+                let mut wind_context = DeviceContext::new(slow_t);
+                if config.solar_curtailment {
+                    wind_context.setpoint_kw = Some(controller.solar_export_limit_kw(
+                        base_demand_kw + hvac_kw + ev_kw - battery_kw - genset_kw - solar_kw,
+                        wind_available_kw,
+                        feeder.max_export_kw(),
+                    ));
+                }
+                (turbine.power_kw(&wind_context), turbine.curtailed_kw())
+            }
+            None => (0.0, 0.0),
+        };
+
         feeder.reset();
-        feeder.add_net_kw(base_demand_kw);
-        feeder.add_net_kw(ev_kw);
-        feeder.add_net_kw(-solar_kw);
-        feeder.add_net_kw(-battery_kw);
+        // Cooling runs on the house circuits.
+        feeder.add_phase_kw(config.baseload_phase, base_demand_kw + hvac_kw);
+        feeder.add_phase_kw(config.ev_phase, ev_home_kw);
+        feeder.add_phase_kw(config.ev_hub_phase, ev_hub_kw);
+        feeder.add_phase_kw(config.solar_phase, -solar_kw);
+        feeder.add_phase_kw(config.wind_phase, -wind_kw);
+        feeder.add_phase_kw(config.battery_phase, -battery_kw);
+        feeder.add_phase_kw(config.genset_phase, -genset_kw);
+        let solar_kvar = pv.reactive_kvar();
+        let battery_kvar = battery.reactive_kvar();
+        feeder.add_phase_kvar(
+            config.baseload_phase,
+            (base_demand_kw + hvac_kw) * baseload_kvar_per_kw,
+        );
+        feeder.add_phase_kvar(config.solar_phase, -solar_kvar);
+        feeder.add_phase_kvar(config.battery_phase, -battery_kvar);
+        let [phase_a_kw, phase_b_kw, phase_c_kw] = feeder.phase_kw();
+        for phase_voltage_pu in feeder.phase_voltage_pu() {
+            phase_voltage_min_pu = phase_voltage_min_pu.min(phase_voltage_pu);
+            phase_voltage_max_pu = phase_voltage_max_pu.max(phase_voltage_pu);
+        }
         let feeder_kw = feeder.net_kw();
+        voltage_pu = feeder.voltage_pu();
+        voltage_min_pu = voltage_min_pu.min(voltage_pu);
+        voltage_max_pu = voltage_max_pu.max(voltage_pu);
+        // Reverse flow heats the transformer as much as forward flow.
+        let transformer_kva = feeder_kw.hypot(feeder.net_kvar());
+        if let Some(unit) = transformer.as_mut() {
+            unit.update(transformer_kva, ambient_temp_c, dt_hr);
+        }
         let tracking_error_kw = feeder_kw - target_kw;
         let feeder_name = feeder.name();
+        // Take and target-level events are measured against the schedule.
+        let dr_achieved_kw = match dr_kind {
+            Some(DemandResponseKind::Shed) => shed_achieved_kw,
+            Some(DemandResponseKind::Take) => {
+                (feeder_kw - scheduled_kw).clamp(0.0, dr_requested_kw)
+            }
+            Some(DemandResponseKind::TargetLevel) => {
+                (scheduled_kw - feeder_kw).clamp(0.0, dr_requested_kw)
+            }
+            None => 0.0,
+        };
+        if let Some((idx, _)) = dr_event {
+            dr_outcomes[idx].requested_kwh += dr_requested_kw * dt_hr;
+            dr_outcomes[idx].delivered_kwh += dr_achieved_kw * dt_hr;
+        }
 
         tracking_error_sq_sum += tracking_error_kw * tracking_error_kw;
         tracking_error_count += 1;
         requested_curtailment_sum_kw += dr_requested_kw;
         achieved_curtailment_sum_kw += dr_achieved_kw;
         feeder_peak_load_kw = feeder_peak_load_kw.max(feeder_kw);
+        if !feeder.within_limits() {
+            feeder_limit_violations += 1;
+        }
+        wind_energy_kwh += wind_kw * dt_hr;
 
         let row = TelemetryRow {
             timestep: t,
@@ -170,25 +993,90 @@ pub fn run_scenario(config: &ScenarioConfig, print_readable_log: bool) -> Simula
             ev_dispatched_kw: ev_kw,
             battery_kw,
             battery_soc: battery.soc,
+            battery_capacity_kwh: battery.capacity_kwh,
             dr_requested_kw,
             dr_achieved_kw,
+            dr_kind: dr_kind.map_or("", DemandResponseKind::name),
             limit_ok: feeder.within_limits(),
+            ev_hub_dispatched_kw: ev_hub_kw,
+            ev_hub_queued,
+            ev_unmet_kwh,
+            ambient_temp_c,
+            battery_temp_c: battery_temp_c.unwrap_or(ambient_temp_c),
+            battery_cmd_shortfall_kw: battery.command_shortfall_kw(),
+            ev_cmd_shortfall_kw,
+            feeder_kvar: feeder.net_kvar(),
+            feeder_pf: feeder.power_factor(),
+            voltage_pu,
+            solar_kvar,
+            battery_kvar,
+            solar_available_kw,
+            solar_curtailed_kw,
+            wind_kw,
+            wind_speed_ms,
+            wind_curtailed_kw,
+            genset_kw,
+            genset_fuel_l: genset.as_ref().map_or(0.0, Genset::fuel_l),
+            islanded,
+            unserved_baseload_kw,
+            unserved_ev_kw,
+            dr_prep_kw,
+            rebound_kw,
+            dr_ev_kw: ev_home_shed_kw,
+            dr_ev_hub_kw: ev_hub_shed_kw,
+            dr_baseload_kw: baseload_shed_kw,
+            dr_battery_kw: battery_dr_kw,
+            dr_genset_kw: genset_dr_kw,
+            regulation_kw,
+            frequency_hz,
+            droop_battery_kw: battery_droop_kw,
+            droop_ev_kw: ev_home_droop_kw + ev_hub_droop_kw,
+            phase_a_kw,
+            phase_b_kw,
+            phase_c_kw,
+            voltage_unbalance_pct: 100.0 * feeder.voltage_unbalance(),
+            current_unbalance_pct: 100.0 * feeder.current_unbalance(),
+            transformer_loading_pct: transformer
+                .as_ref()
+                .map_or(0.0, |unit| 100.0 * transformer_kva / unit.rated_kva()),
+            transformer_top_oil_c: transformer
+                .as_ref()
+                .map_or(0.0, TransformerThermal::top_oil_c),
+            transformer_hot_spot_c: transformer
+                .as_ref()
+                .map_or(0.0, TransformerThermal::hot_spot_c),
+            transformer_aging_factor: transformer
+                .as_ref()
+                .map_or(0.0, TransformerThermal::aging_factor),
+            import_limit_kw: feeder.max_import_kw(),
+            export_limit_kw: feeder.max_export_kw(),
+            hvac_kw,
+            indoor_temp_c: hvac
+                .as_ref()
+                .map_or(ambient_temp_c, BuildingHvac::indoor_temp_c),
+            dr_hvac_kw: hvac_shed_kw,
         };
         telemetry.push(row);
 
         let soc = battery.soc * 100.0;
-        if print_readable_log {
+        // Hours since the start of the run; whole numbers at hourly steps.
+        let hour = (t * 24) as f32 / steps_per_day as f32;
+        if print_readable_log && t % config.telemetry_interval_steps == 0 {
             println!(
-                "Time (Hr) {t}: {baseload_device}={base_demand_kw:.2} kW, \
+                "Time (Hr) {hour}: {baseload_device}={base_demand_kw:.2} kW, \
                 RawBase={base_demand_kw_raw:.2} kW, \
                 Forecast={forecast_kw:.2} kW, \
                 Target={target_kw:.2} kW, \
                 {solar_device}={solar_kw:.2} kW, \
-                {ev_device}={ev_kw:.2} kW (Req={ev_requested_kw:.2}, DR={ev_after_dr_kw:.2}, Cap={ev_capped_kw:.2}), \
+                Wind={wind_kw:.2} kW, \
+                Genset={genset_kw:.2} kW, \
+                {ev_device}={ev_kw:.2} kW (Req={ev_requested_kw:.2}, DR={ev_after_dr_kw:.2}, \
+                Cap={ev_capped_kw:.2}, Hub={ev_hub_kw:.2}, Queued={ev_hub_queued}), \
                 {battery_device}={battery_kw:.2} kW (SoC={soc:.1}%), \
                 {feeder_name}={feeder_kw:.2} kW, \
                 Error={tracking_error_kw:.2} kW, \
                 DR(req={dr_requested_kw:.2}, done={dr_achieved_kw:.2}), \
+                Islanded={islanded}, \
                 LimitOK={}",
                 feeder.within_limits()
             );
@@ -207,21 +1095,690 @@ pub fn run_scenario(config: &ScenarioConfig, print_readable_log: bool) -> Simula
         0.0
     };
 
+    for outcome in &mut dr_outcomes {
+        let window_end = (outcome.end_step + config.dr_rebound_steps).min(total_steps);
+        outcome.post_event_peak_kw = telemetry[outcome.end_step.min(window_end)..window_end]
+            .iter()
+            .map(|row| row.feeder_kw)
+            .reduce(f32::max);
+    }
+
+    let outages: Vec<OutageOutcome> = outage_schedule
+        .outages()
+        .iter()
+        .map(|outage| {
+            let rows = &telemetry[outage.start_step..outage.end_step];
+            OutageOutcome {
+                start_step: outage.start_step,
+                end_step: outage.end_step,
+                survived_steps: rows
+                    .iter()
+                    .take_while(|row| row.unserved_baseload_kw + row.unserved_ev_kw <= 1e-4)
+                    .count(),
+                baseload_shed_kwh: rows.iter().map(|row| row.unserved_baseload_kw).sum::<f32>()
+                    * dt_hr,
+                ev_shed_kwh: rows.iter().map(|row| row.unserved_ev_kw).sum::<f32>() * dt_hr,
+            }
+        })
+        .collect();
+
+    let frequency_response = droop.map(|controller| {
+        let hz = telemetry.iter().map(|row| row.frequency_hz);
+        FrequencyResponseKpis {
+            min_hz: hz.clone().fold(f32::INFINITY, f32::min),
+            max_hz: hz.clone().fold(f32::NEG_INFINITY, f32::max),
+            steps_outside_deadband: hz
+                .filter(|hz| (hz - controller.nominal_hz).abs() > controller.deadband_hz)
+                .count(),
+            peak_response_kw: telemetry
+                .iter()
+                .map(|row| (row.droop_battery_kw + row.droop_ev_kw).abs())
+                .fold(0.0, f32::max),
+            battery_energy_kwh: telemetry
+                .iter()
+                .map(|row| row.droop_battery_kw.abs())
+                .sum::<f32>()
+                * dt_hr,
+            ev_shed_kwh: telemetry.iter().map(|row| row.droop_ev_kw).sum::<f32>() * dt_hr,
+        }
+    });
+
+    let single_phase = [
+        config.baseload_phase,
+        config.solar_phase,
+        config.wind_phase,
+        config.battery_phase,
+        config.genset_phase,
+        config.ev_phase,
+        config.ev_hub_phase,
+    ]
+    .iter()
+    .any(|phase| *phase != Phase::Three);
+    let unbalance = single_phase.then(|| {
+        let phases = |row: &TelemetryRow| [row.phase_a_kw, row.phase_b_kw, row.phase_c_kw];
+        let phase_import_limit_kw = config.feeder_kw / 3.0;
+        let phase_export_limit_kw = config.feeder_kw * 0.8 / 3.0;
+        let peak_row = telemetry.iter().max_by(|a, b| {
+            let loading =
+                |row: &TelemetryRow| phases(row).map(f32::abs).into_iter().fold(0.0, f32::max);
+            loading(a).total_cmp(&loading(b))
+        });
+        UnbalanceKpis {
+            phase_peak_kw: std::array::from_fn(|p| {
+                telemetry
+                    .iter()
+                    .map(|row| phases(row)[p])
+                    .fold(0.0, f32::max)
+            }),
+            phase_overload_steps: telemetry
+                .iter()
+                .filter(|row| {
+                    phases(row)
+                        .iter()
+                        .any(|kw| *kw > phase_import_limit_kw || -kw > phase_export_limit_kw)
+                })
+                .count(),
+            phase_voltage_min_pu,
+            phase_voltage_max_pu,
+            voltage_unbalance_max_pct: telemetry
+                .iter()
+                .map(|row| row.voltage_unbalance_pct)
+                .fold(0.0, f32::max),
+            voltage_unbalance_violations: telemetry
+                .iter()
+                .filter(|row| row.voltage_unbalance_pct > VOLTAGE_UNBALANCE_LIMIT_PCT)
+                .count(),
+            current_unbalance_at_peak_pct: peak_row.map_or(0.0, |row| row.current_unbalance_pct),
+        }
+    });
+
+    let transformer = transformer.map(|unit| TransformerKpis {
+        rated_kva: unit.rated_kva(),
+        peak_loading_pct: telemetry
+            .iter()
+            .map(|row| row.transformer_loading_pct)
+            .fold(0.0, f32::max),
+        overload_steps: telemetry
+            .iter()
+            .filter(|row| row.transformer_loading_pct > 100.0)
+            .count(),
+        peak_top_oil_c: telemetry
+            .iter()
+            .map(|row| row.transformer_top_oil_c)
+            .fold(f32::NEG_INFINITY, f32::max),
+        peak_hot_spot_c: telemetry
+            .iter()
+            .map(|row| row.transformer_hot_spot_c)
+            .fold(f32::NEG_INFINITY, f32::max),
+        hot_spot_over_reference_steps: telemetry
+            .iter()
+            .filter(|row| row.transformer_hot_spot_c > REFERENCE_HOT_SPOT_C)
+            .count(),
+        equivalent_aging_factor: unit.equivalent_aging_factor(),
+        loss_of_life_hours: unit.loss_of_life_hours(),
+    });
+
+    let envelope = envelope.map(|limits| {
+        let excess_kw: Vec<f32> = telemetry
+            .iter()
+            .zip(&limits)
+            .map(|(row, limits)| limits.excess_kw(row.feeder_kw))
+            .collect();
+        EnvelopeKpis {
+            constrained_steps: limits
+                .iter()
+                .enumerate()
+                .filter(|(t, limits)| {
+                    connection_limits(*t).tightest(**limits) != connection_limits(*t)
+                })
+                .count(),
+            import_violations: excess_kw.iter().filter(|kw| **kw > 0.0).count(),
+            export_violations: excess_kw.iter().filter(|kw| **kw < 0.0).count(),
+            peak_excess_kw: excess_kw.iter().map(|kw| kw.abs()).fold(0.0, f32::max),
+            excess_kwh: excess_kw.iter().map(|kw| kw.abs()).sum::<f32>() * dt_hr,
+        }
+    });
+
+    settle_dr_events(
+        config,
+        &dr_program,
+        &outage_schedule,
+        &telemetry,
+        &mut dr_outcomes,
+    );
+
+    ev.finish(total_steps);
+    let mut ev_sessions = ev.session_outcomes().to_vec();
+    let (ev_hub_policy, ev_hub_sessions) = match ev_hub.as_mut() {
+        Some(hub) => {
+            hub.finish(total_steps);
+            (Some(config.ev_hub_policy), hub.session_outcomes().to_vec())
+        }
+        None => (None, Vec::new()),
+    };
+    ev_sessions.extend(ev_hub_sessions.iter().cloned());
+
     SimulationResult {
-        telemetry,
+        telemetry: telemetry
+            .into_iter()
+            .step_by(config.telemetry_interval_steps)
+            .collect(),
         kpis: SimulationKpis {
             rmse_tracking_kw,
             curtailment_pct,
             feeder_peak_load_kw,
+            feeder_import_limit_kw: config.feeder_kw,
+            ev_energy_requested_kwh: ev_sessions.iter().map(|s| s.requested_kwh).sum(),
+            ev_energy_delivered_kwh: ev_sessions.iter().map(|s| s.delivered_kwh).sum(),
+            ev_unmet_kwh: ev_sessions.iter().map(EvSessionOutcome::unmet_kwh).sum(),
+            ev_sessions_departed: ev_sessions.len(),
+            ev_sessions_unsatisfied: ev_sessions.iter().filter(|s| !s.is_satisfied()).count(),
+            ev_policy: config.ev_policy,
+            ev_hub_policy,
+            ev_hub_sessions,
+            battery_equivalent_full_cycles: battery.equivalent_full_cycles(),
+            battery_capacity_fade_pct: 100.0 * battery.capacity_fade(),
+            battery_peak_temp_c,
+            battery_derated_steps,
+            battery_unhonored_commands: battery.unhonored_commands(),
+            ev_unhonored_commands: ev.unhonored_commands()
+                + ev_hub.as_ref().map_or(0, EvChargingHub::unhonored_commands),
+            feeder_limit_violations,
+            solar_curtailed_kwh: pv.curtailed_kwh(),
+            wind_energy_kwh: wind.is_some().then_some(wind_energy_kwh),
+            wind_curtailed_kwh: wind.as_ref().map_or(0.0, WindTurbine::curtailed_kwh),
+            voltage_min_pu,
+            voltage_max_pu,
+            genset: genset.as_ref().map(|unit| GensetKpis {
+                energy_kwh: unit.energy_kwh(),
+                dr_energy_kwh: genset_dr_energy_kwh,
+                fuel_used_l: unit.fuel_used_l(),
+                co2_kg: unit.co2_kg(),
+                nox_kg: unit.nox_kg(),
+                starts: unit.starts(),
+            }),
+            unserved_energy_kwh: outages.iter().map(OutageOutcome::unserved_kwh).sum(),
+            outages,
+            dr_events: dr_outcomes,
+            regulation: regulation.as_mut().map(RegulationService::finish),
+            frequency_response,
+            unbalance,
+            transformer,
+            envelope,
         },
     }
 }
 
+/// Per-step operating envelope from the DSO's schedule and its hosting
+/// capacity model, the tighter of the two where both apply; `None` if
+/// neither is configured.
+///
+/// Hosting capacity is computed from a noise-free forecast of the
+/// neighbours' load and solar, as a DSO would publish it ahead of time.
+fn operating_envelope(config: &ScenarioConfig, total_steps: usize) -> Option<Vec<NodeLimits>> {
+    if config.envelope.is_empty() && config.envelope_hosting_capacity_kw.is_none() {
+        return None;
+    }
+    let neighbours = config.envelope_background_houses as f32;
+    let steps_per_day = config.steps_per_day;
+    let mut load = BaseLoad::new(
+        0.8 * neighbours,
+        0.7 * neighbours,
+        1.2,
+        0.0,
+        steps_per_day,
+        config.seed,
+    );
+    let mut pv = SolarPv::new(
+        config.solar_kw_peak_per_house * neighbours,
+        steps_per_day,
+        6 * steps_per_day / 24,
+        18 * steps_per_day / 24,
+        0.0,
+        config.seed,
+    );
+    let limits = (0..total_steps)
+        .map(|t| {
+            let context = DeviceContext::new(t);
+            let hosting = config.envelope_hosting_capacity_kw.map(|capacity_kw| {
+                let background_kw = load.power_kw(&context) - pv.available_kw(&context);
+                hosting_capacity_limits(capacity_kw, background_kw)
+            });
+            [config.envelope.limits_at(t), hosting]
+                .into_iter()
+                .flatten()
+                .fold(NodeLimits::unlimited(), NodeLimits::tightest)
+        })
+        .collect();
+    Some(limits)
+}
+
+/// Most runs of the sites while the VPP tightens site limits to relieve
+/// overloaded feeders and the substation.
+const MAX_COORDINATION_PASSES: usize = 4;
+
+/// Per-site runs and the flows through every node of a topology.
+pub struct TopologyResult {
+    /// Site runs, in scenario order.
+    pub sites: Vec<SimulationResult>,
+    pub site_flows: Vec<NodeFlow>,
+    pub feeder_flows: Vec<NodeFlow>,
+    pub substation_flow: NodeFlow,
+    /// Power flow of each step, if the topology defines lines.
+    pub power_flow: Vec<PowerFlowSolution>,
+    /// Voltage, current and loss totals, if the topology defines lines.
+    pub network: Option<NetworkKpis>,
+    /// Runs of all sites it took to settle the site limits.
+    pub coordination_passes: usize,
+}
+
+/// Bus voltages, line loading and losses over the run.
+pub struct NetworkKpis {
+    /// Line losses, included in the substation flow (kWh).
+    pub loss_kwh: f32,
+    pub voltage_min_pu: f32,
+    pub voltage_max_pu: f32,
+    /// Steps in which any bus was outside the voltage limits.
+    pub voltage_violation_steps: usize,
+    /// Steps in which the power flow did not converge.
+    pub unconverged_steps: usize,
+    /// Per bus, in network order (the substation bus first).
+    pub buses: Vec<BusKpis>,
+    /// Per line, in scenario order.
+    pub lines: Vec<LineKpis>,
+}
+
+pub struct BusKpis {
+    pub voltage_min_pu: f32,
+    pub voltage_max_pu: f32,
+    /// Steps outside the voltage limits.
+    pub violation_steps: usize,
+}
+
+pub struct LineKpis {
+    pub peak_current_a: f32,
+    pub loss_kwh: f32,
+    /// Steps above the line's ampacity.
+    pub overload_steps: usize,
+}
+
+impl TopologyResult {
+    /// Substation, feeders and sites, top down.
+    pub fn nodes(&self) -> Vec<&NodeFlow> {
+        std::iter::once(&self.substation_flow)
+            .chain(&self.feeder_flows)
+            .chain(&self.site_flows)
+            .collect()
+    }
+}
+
+/// Runs every site of `topology` with the scenario's devices and rolls the
+/// site meters up into feeders and the substation.
+///
+/// Each site controller enforces its own meter limits. With lines defined, a
+/// power flow of the site meters gives bus voltages, line currents and
+/// losses, and the substation carries the losses too. Where a feeder or the
+/// substation is overloaded, the VPP splits the excess across the sites that
+/// cause it and tightens their limits for those steps, then runs the sites
+/// again; violations left after the last pass are reported per node.
+pub fn run_topology(config: &ScenarioConfig, topology: &Topology) -> TopologyResult {
+    let total_steps = config.steps_per_day * config.days;
+    let mut site_configs: Vec<ScenarioConfig> = topology
+        .sites
+        .iter()
+        .enumerate()
+        .map(|(i, site)| ScenarioConfig {
+            houses: site.houses,
+            feeder_kw: site.limits.import_kw,
+            solar_kw_peak_per_house: site.solar_kw_peak_per_house,
+            // Each site draws its own load, solar and EV randomness.
+            seed: config.seed.wrapping_add((i as u64) << 32),
+            telemetry_interval_steps: 1,
+            topology: None,
+            import_limit_kw_by_step: vec![site.limits.import_kw; total_steps],
+            export_limit_kw_by_step: vec![site.limits.export_kw; total_steps],
+            ..config.clone()
+        })
+        .collect();
+
+    let mut passes = 0;
+    loop {
+        passes += 1;
+        let sites: Vec<SimulationResult> = site_configs
+            .iter()
+            .map(|site_config| run_scenario(site_config, false))
+            .collect();
+        let site_flows: Vec<NodeFlow> = topology
+            .sites
+            .iter()
+            .zip(&sites)
+            .map(|(site, result)| NodeFlow {
+                name: site.name.clone(),
+                level: NodeLevel::Site,
+                limits: site.limits,
+                net_kw: result.telemetry.iter().map(|row| row.feeder_kw).collect(),
+            })
+            .collect();
+        let feeder_members: Vec<Vec<usize>> = (0..topology.feeders.len())
+            .map(|f| {
+                (0..topology.sites.len())
+                    .filter(|s| topology.sites[*s].feeder == f)
+                    .collect()
+            })
+            .collect();
+        let feeder_flows: Vec<NodeFlow> = topology
+            .feeders
+            .iter()
+            .zip(&feeder_members)
+            .map(|(feeder, members)| {
+                let children: Vec<&NodeFlow> = members.iter().map(|s| &site_flows[*s]).collect();
+                NodeFlow::sum_of(&feeder.name, NodeLevel::Feeder, feeder.limits, &children)
+            })
+            .collect();
+        let mut substation_flow = NodeFlow::sum_of(
+            "Substation",
+            NodeLevel::Substation,
+            topology.substation,
+            &feeder_flows.iter().collect::<Vec<_>>(),
+        );
+        let power_flow: Vec<PowerFlowSolution> = match topology.network.as_ref() {
+            Some(network) => (0..total_steps)
+                .map(|t| {
+                    let mut load_kw = vec![0.0; network.bus_names().len()];
+                    let mut load_kvar = vec![0.0; network.bus_names().len()];
+                    for (site, result) in topology.sites.iter().zip(&sites) {
+                        if let Some(bus) = site.bus {
+                            load_kw[bus] += result.telemetry[t].feeder_kw;
+                            load_kvar[bus] += result.telemetry[t].feeder_kvar;
+                        }
+                    }
+                    network.solve(&load_kw, &load_kvar)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        for (net_kw, solution) in substation_flow.net_kw.iter_mut().zip(&power_flow) {
+            *net_kw += solution.total_loss_kw();
+        }
+
+        let all_sites: Vec<usize> = (0..topology.sites.len()).collect();
+        let upstream = feeder_flows
+            .iter()
+            .zip(&feeder_members)
+            .chain(std::iter::once((&substation_flow, &all_sites)));
+        let mut tightened = false;
+        for (flow, members) in upstream {
+            for (t, net_kw) in flow.net_kw.iter().enumerate() {
+                let excess_kw = flow.limits.excess_kw(*net_kw);
+                if excess_kw == 0.0 {
+                    continue;
+                }
+                let member_kw: Vec<f32> =
+                    members.iter().map(|s| site_flows[*s].net_kw[t]).collect();
+                for ((s, site_kw), share_kw) in members
+                    .iter()
+                    .zip(member_kw.iter())
+                    .zip(share_excess_kw(excess_kw, &member_kw))
+                {
+                    let site_config = &mut site_configs[*s];
+                    if excess_kw > 0.0 {
+                        let limit_kw = &mut site_config.import_limit_kw_by_step[t];
+                        *limit_kw = limit_kw.min((site_kw - share_kw).max(0.0));
+                    } else {
+                        let limit_kw = &mut site_config.export_limit_kw_by_step[t];
+                        *limit_kw = limit_kw.min((-site_kw - share_kw).max(0.0));
+                    }
+                }
+                tightened = true;
+            }
+        }
+
+        if !tightened || passes == MAX_COORDINATION_PASSES {
+            let dt_hr = 24.0 / config.steps_per_day as f32;
+            return TopologyResult {
+                sites,
+                site_flows,
+                feeder_flows,
+                substation_flow,
+                network: topology
+                    .network
+                    .as_ref()
+                    .map(|network| network_kpis(topology, network, &power_flow, dt_hr)),
+                power_flow,
+                coordination_passes: passes,
+            };
+        }
+    }
+}
+
+fn network_kpis(
+    topology: &Topology,
+    network: &RadialNetwork,
+    power_flow: &[PowerFlowSolution],
+    dt_hr: f32,
+) -> NetworkKpis {
+    let voltages = |bus: usize| power_flow.iter().map(move |s| s.bus_voltage_pu[bus]);
+    let buses: Vec<BusKpis> = (0..network.bus_names().len())
+        .map(|bus| BusKpis {
+            voltage_min_pu: voltages(bus).fold(f32::INFINITY, f32::min),
+            voltage_max_pu: voltages(bus).fold(f32::NEG_INFINITY, f32::max),
+            violation_steps: voltages(bus)
+                .filter(|v| !topology.voltage_within_limits(*v))
+                .count(),
+        })
+        .collect();
+    let lines: Vec<LineKpis> = network
+        .lines()
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| LineKpis {
+            peak_current_a: power_flow
+                .iter()
+                .map(|s| s.line_current_a[idx])
+                .fold(0.0, f32::max),
+            loss_kwh: power_flow.iter().map(|s| s.line_loss_kw[idx]).sum::<f32>() * dt_hr,
+            overload_steps: line.ampacity_a.map_or(0, |ampacity_a| {
+                power_flow
+                    .iter()
+                    .filter(|s| s.line_current_a[idx] > ampacity_a)
+                    .count()
+            }),
+        })
+        .collect();
+
+    NetworkKpis {
+        loss_kwh: lines.iter().map(|line| line.loss_kwh).sum(),
+        voltage_min_pu: buses
+            .iter()
+            .map(|b| b.voltage_min_pu)
+            .fold(f32::INFINITY, f32::min),
+        voltage_max_pu: buses
+            .iter()
+            .map(|b| b.voltage_max_pu)
+            .fold(f32::NEG_INFINITY, f32::max),
+        voltage_violation_steps: power_flow
+            .iter()
+            .filter(|s| {
+                s.bus_voltage_pu
+                    .iter()
+                    .any(|v| !topology.voltage_within_limits(*v))
+            })
+            .count(),
+        unconverged_steps: power_flow.iter().filter(|s| !s.converged).count(),
+        buses,
+        lines,
+    }
+}
+
+/// Seed offset of the lookback run, far from the per-site offsets of a
+/// topology so its days are independent of every site's own run.
+const LOOKBACK_SEED_OFFSET: u64 = 1 << 63;
+
+/// Settles each DR event against the scenario's customer baseline.
+///
+/// Baselines need metered days before the run, so a lookback run without
+/// events or outages provides the history that precedes day 0.
+fn settle_dr_events(
+    config: &ScenarioConfig,
+    dr_program: &DemandResponseProgram,
+    outage_schedule: &OutageSchedule,
+    telemetry: &[TelemetryRow],
+    dr_outcomes: &mut [DrEventOutcome],
+) {
+    let Some(method) = config.settlement_baseline else {
+        return;
+    };
+    let steps_per_day = config.steps_per_day;
+    let dt_hr = 24.0 / steps_per_day as f32;
+    let lookback_days = method.lookback_days();
+    // The meter history comes from the same site on earlier, ordinary days:
+    // a separate random stream (site parameters do not depend on the seed),
+    // no DR events, and nothing that only serves the grid or the run's own
+    // timeline (genset, regulation, droop, outages, envelopes, limits).
+    let lookback = run_scenario(
+        &ScenarioConfig {
+            days: lookback_days,
+            seed: config.seed.wrapping_add(LOOKBACK_SEED_OFFSET),
+            telemetry_interval_steps: 1,
+            dr_reduction_kw_per_house: 0.0,
+            dr_events: Vec::new(),
+            genset_rated_kw: 0.0,
+            outage_windows: Vec::new(),
+            outage_rate_per_day: 0.0,
+            settlement_baseline: None,
+            regulation_kw_per_house: 0.0,
+            regulation_signal_series: None,
+            frequency_events: Vec::new(),
+            frequency_series: None,
+            frequency_droop_response: false,
+            transformer_kva: 0.0,
+            envelope: OperatingEnvelope::default(),
+            envelope_hosting_capacity_kw: None,
+            topology: None,
+            import_limit_kw_by_step: Vec::new(),
+            export_limit_kw_by_step: Vec::new(),
+            ..config.clone()
+        },
+        false,
+    );
+
+    let rows = lookback.telemetry.iter().chain(telemetry);
+    let load_kw: Vec<f32> = rows.clone().map(|row| row.feeder_kw).collect();
+    let temp_c: Vec<f32> = rows.map(|row| row.ambient_temp_c).collect();
+    let mut excluded_days = vec![false; lookback_days + config.days];
+    for (start, end) in dr_program
+        .events()
+        .iter()
+        .map(|event| (event.start_step, event.end_step))
+        .chain(
+            outage_schedule
+                .outages()
+                .iter()
+                .map(|outage| (outage.start_step, outage.end_step)),
+        )
+    {
+        for day in start / steps_per_day..=(end - 1) / steps_per_day {
+            excluded_days[lookback_days + day] = true;
+        }
+    }
+    let history = MeterHistory {
+        steps_per_day,
+        load_kw: &load_kw,
+        temp_c: &temp_c,
+        excluded_days: &excluded_days,
+    };
+    let adjustment = (config.settlement_adjustment_steps > 0).then_some(SameDayAdjustment {
+        window_steps: config.settlement_adjustment_steps,
+        cap_fraction: config.settlement_adjustment_cap,
+    });
+    let terms = SettlementTerms {
+        price_per_kwh: config.settlement_price_per_kwh,
+        penalty_per_kwh: config.settlement_penalty_per_kwh,
+        min_performance: config.settlement_min_performance,
+    };
+
+    for (outcome, event) in dr_outcomes.iter_mut().zip(dr_program.events()) {
+        let day = event.start_step / steps_per_day;
+        let start = event.start_step % steps_per_day;
+        let end = start + event.duration_steps();
+        let Some(baseline) = baseline_kw(
+            method,
+            adjustment,
+            &history,
+            lookback_days + day,
+            start,
+            end,
+        ) else {
+            continue;
+        };
+        let requested_kw: Vec<f32> = (event.start_step..event.end_step)
+            .map(|t| event.requested_at_kw(t))
+            .collect();
+        let metered_kw: Vec<f32> = telemetry[event.start_step..event.end_step]
+            .iter()
+            .map(|row| row.feeder_kw)
+            .collect();
+        outcome.settlement = Some(settle_event(
+            event.kind,
+            &requested_kw,
+            &baseline,
+            &metered_kw,
+            dt_hr,
+            terms,
+        ));
+    }
+}
+
+/// Inverter of `rating_kva` with the scenario's reactive power mode and Volt-Watt setting.
+fn inverter(config: &ScenarioConfig, rating_kva: f32) -> Inverter {
+    let inverter = Inverter::new(rating_kva, config.inverter_mode);
+    if config.inverter_volt_watt {
+        inverter.with_volt_watt(VoltWattCurve::default())
+    } else {
+        inverter
+    }
+}
+
+/// Actuator limits from scenario values, where a ramp of `0.0` means unlimited.
+fn actuator_limits(
+    ramp_kw_per_min: f32,
+    latency_steps: usize,
+    min_on_steps: usize,
+    min_off_steps: usize,
+) -> ActuatorLimits {
+    ActuatorLimits {
+        ramp_kw_per_min: (ramp_kw_per_min > 0.0).then_some(ramp_kw_per_min),
+        latency_steps,
+        min_on_steps,
+        min_off_steps,
+    }
+}
+
+/// Sums unmet energy of outcomes recorded since the last call and advances `seen`.
+fn unmet_kwh_since(outcomes: &[EvSessionOutcome], seen: &mut usize) -> f32 {
+    let unmet = outcomes[*seen..]
+        .iter()
+        .map(EvSessionOutcome::unmet_kwh)
+        .sum();
+    *seen = outcomes.len();
+    unmet
+}
+
 #[cfg(test)]
 mod tests {
-    use super::run_scenario;
-    use crate::scenario::ScenarioConfig;
-    use crate::telemetry::write_telemetry_csv;
+    use super::{SimulationResult, run_scenario, run_topology};
+    use crate::devices::{EvChargingPolicy, ReactivePowerMode};
+    use crate::scenario::{DrEventSpec, FeederSpec, ScenarioConfig, SiteSpec, Topology};
+    use crate::settlement::BaselineMethod;
+    use crate::sim::dr_allocation::DrCapability;
+    use crate::sim::envelope::{EnvelopeUpdate, OperatingEnvelope};
+    use crate::sim::event::{DemandResponseEvent, DemandResponseKind};
+    use crate::sim::feeder::Phase;
+    use crate::sim::frequency::FrequencyDisturbance;
+    use crate::sim::outage::GridOutage;
+    use crate::sim::power_flow::{Line, ROOT_BUS, RadialNetwork};
+    use crate::sim::topology::NodeLimits;
+    use crate::telemetry::{TelemetryRow, write_telemetry_csv};
 
     #[test]
     fn same_scenario_and_seed_is_deterministic() {
@@ -244,4 +1801,1001 @@ mod tests {
 
         assert_eq!(out_a, out_b);
     }
+
+    #[test]
+    fn dr_curtailed_ev_shows_unmet_energy() {
+        let relaxed = ScenarioConfig {
+            feeder_kw: 100.0,
+            dr_reduction_kw_per_house: 0.0,
+            ..ScenarioConfig::default()
+        };
+        // The default seed places the EV session inside this DR window.
+        let tight = ScenarioConfig {
+            dr_start_step: 8,
+            dr_end_step: 14,
+            dr_reduction_kw_per_house: 10.0,
+            ..ScenarioConfig::default()
+        };
+
+        let relaxed_run = run_scenario(&relaxed, false);
+        assert_eq!(relaxed_run.kpis.ev_sessions_departed, 1);
+        assert_eq!(relaxed_run.kpis.ev_sessions_unsatisfied, 0);
+
+        let tight_run = run_scenario(&tight, false);
+        let kpis = &tight_run.kpis;
+        assert_eq!(kpis.ev_sessions_unsatisfied, 1);
+        assert!(kpis.ev_unmet_kwh > 0.0);
+        assert!(
+            (kpis.ev_energy_requested_kwh - kpis.ev_energy_delivered_kwh - kpis.ev_unmet_kwh).abs()
+                < 1e-3
+        );
+        let telemetry_unmet: f32 = tight_run.telemetry.iter().map(|r| r.ev_unmet_kwh).sum();
+        assert!((telemetry_unmet - kpis.ev_unmet_kwh).abs() < 1e-3);
+    }
+
+    #[test]
+    fn battery_degradation_fades_capacity_over_multi_day_run() {
+        let scenario = ScenarioConfig {
+            days: 3,
+            battery_degradation: true,
+            battery_cycle_life: 500.0,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        assert_eq!(result.telemetry.len(), 72);
+        assert!(result.kpis.battery_equivalent_full_cycles > 0.0);
+        assert!(result.kpis.battery_capacity_fade_pct > 0.0);
+        let first = result.telemetry.first().expect("telemetry row");
+        let last = result.telemetry.last().expect("telemetry row");
+        assert!(last.battery_capacity_kwh < first.battery_capacity_kwh);
+    }
+
+    #[test]
+    fn hot_afternoon_derates_battery_when_thermal_model_enabled() {
+        let scenario = ScenarioConfig {
+            battery_thermal: true,
+            ambient_temp_mean_c: 40.0,
+            ambient_temp_amplitude_c: 8.0,
+            ambient_temp_day_std_c: 0.0,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        assert!(result.kpis.battery_derated_steps > 0);
+        let peak_temp_c = result
+            .kpis
+            .battery_peak_temp_c
+            .expect("thermal model enabled");
+        assert!(peak_temp_c > 40.0);
+        let afternoon = &result.telemetry[15];
+        assert!((afternoon.ambient_temp_c - 48.0).abs() < 1e-4);
+
+        let baseline = run_scenario(&ScenarioConfig::default(), false);
+        assert_eq!(baseline.kpis.battery_peak_temp_c, None);
+        assert_eq!(baseline.kpis.battery_derated_steps, 0);
+        assert!(
+            baseline
+                .telemetry
+                .iter()
+                .all(|row| row.battery_temp_c == row.ambient_temp_c)
+        );
+    }
+
+    #[test]
+    fn undersized_transformer_in_hot_weather_loses_life_faster() {
+        let run = |transformer_kva: f32, ambient_temp_mean_c: f32| {
+            run_scenario(
+                &ScenarioConfig {
+                    transformer_kva,
+                    ambient_temp_mean_c,
+                    ..ScenarioConfig::default()
+                },
+                false,
+            )
+        };
+        let sized = run(10.0, 25.0);
+        let undersized = run(2.0, 25.0);
+        let undersized_hot = run(2.0, 40.0);
+        let kpis = |result: &SimulationResult| {
+            result
+                .kpis
+                .transformer
+                .as_ref()
+                .map(|t| (t.peak_hot_spot_c, t.loss_of_life_hours, t.overload_steps))
+                .expect("transformer rated")
+        };
+
+        let (sized_hot_spot_c, sized_life_h, sized_overloads) = kpis(&sized);
+        let (undersized_hot_spot_c, undersized_life_h, undersized_overloads) = kpis(&undersized);
+        let (_, hot_life_h, _) = kpis(&undersized_hot);
+        assert_eq!(sized_overloads, 0);
+        assert!(undersized_overloads > 0);
+        assert!(undersized_hot_spot_c > sized_hot_spot_c);
+        assert!(undersized_life_h > sized_life_h);
+        assert!(hot_life_h > undersized_life_h);
+        let row = &undersized.telemetry[15];
+        assert!(row.transformer_hot_spot_c > row.transformer_top_oil_c);
+        assert!(row.transformer_top_oil_c > row.ambient_temp_c);
+
+        let baseline = run_scenario(&ScenarioConfig::default(), false);
+        assert!(baseline.kpis.transformer.is_none());
+        assert!(
+            baseline
+                .telemetry
+                .iter()
+                .all(|row| row.transformer_aging_factor == 0.0)
+        );
+    }
+
+    #[test]
+    fn battery_ramp_limit_reports_unhonored_setpoints() {
+        let baseline = run_scenario(&ScenarioConfig::default(), false);
+        assert_eq!(baseline.kpis.battery_unhonored_commands, 0);
+        assert_eq!(baseline.kpis.ev_unhonored_commands, 0);
+
+        let scenario = ScenarioConfig {
+            battery_ramp_kw_per_min_per_house: 0.01,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        assert!(result.kpis.battery_unhonored_commands > 0);
+        // Hourly steps allow at most 0.6 kW per house of change per step,
+        // except where a full or empty battery stops abruptly.
+        let max_step_change_kw = result
+            .telemetry
+            .windows(2)
+            .filter(|w| w[1].battery_soc < 1.0 && w[1].battery_soc > 0.0)
+            .map(|w| (w[1].battery_kw - w[0].battery_kw).abs())
+            .fold(0.0, f32::max);
+        assert!(max_step_change_kw <= 0.6 * scenario.houses as f32 + 1e-4);
+        assert!(
+            result
+                .telemetry
+                .iter()
+                .any(|row| row.battery_cmd_shortfall_kw > 0.0)
+        );
+    }
+
+    #[test]
+    fn absorbing_solar_inverters_draw_reactive_power_from_feeder() {
+        let baseline = run_scenario(&ScenarioConfig::default(), false);
+        let scenario = ScenarioConfig {
+            inverter_mode: ReactivePowerMode::FixedPowerFactor {
+                power_factor: 0.9,
+                absorbing: true,
+            },
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let noon = &result.telemetry[12];
+        assert!(noon.solar_kvar < 0.0);
+        assert!(noon.feeder_kvar > baseline.telemetry[12].feeder_kvar);
+        // Baseload alone runs at a lagging power factor.
+        assert!(baseline.telemetry.iter().all(|row| row.solar_kvar == 0.0));
+        assert!(baseline.telemetry.iter().any(|row| row.feeder_pf < 1.0));
+        assert!(result.kpis.voltage_min_pu < 1.0);
+    }
+
+    #[test]
+    fn solar_curtailment_respects_export_limit() {
+        let high_solar = ScenarioConfig {
+            houses: 20,
+            feeder_kw: 200.0,
+            solar_kw_peak_per_house: 12.0,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&high_solar, false);
+
+        assert_eq!(result.kpis.feeder_limit_violations, 0);
+        assert!(result.telemetry.iter().all(|row| row.limit_ok));
+        assert!(result.kpis.solar_curtailed_kwh > 0.0);
+        let curtailed_kwh: f32 = result
+            .telemetry
+            .iter()
+            .map(|row| row.solar_curtailed_kw)
+            .sum();
+        assert!((curtailed_kwh - result.kpis.solar_curtailed_kwh).abs() < 1e-2);
+
+        let uncurtailed = run_scenario(
+            &ScenarioConfig {
+                solar_curtailment: false,
+                ..high_solar
+            },
+            false,
+        );
+        assert!(uncurtailed.kpis.feeder_limit_violations > 0);
+        assert_eq!(uncurtailed.kpis.solar_curtailed_kwh, 0.0);
+    }
+
+    #[test]
+    fn controller_holds_site_within_dynamic_operating_envelope() {
+        // Zero export over midday, relaxed again in the evening.
+        let scenario = ScenarioConfig {
+            solar_kw_peak_per_house: 6.0,
+            envelope: OperatingEnvelope::new(vec![
+                EnvelopeUpdate {
+                    start_step: 10,
+                    import_kw: 5.0,
+                    export_kw: 0.0,
+                },
+                EnvelopeUpdate {
+                    start_step: 16,
+                    import_kw: 5.0,
+                    export_kw: 4.0,
+                },
+            ]),
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+        let envelope = result.kpis.envelope.as_ref().expect("envelope set");
+        assert_eq!(envelope.constrained_steps, 6);
+        assert_eq!(envelope.export_violations, 0);
+        assert!(result.kpis.solar_curtailed_kwh > 0.0);
+        for row in &result.telemetry[10..16] {
+            assert_eq!(row.export_limit_kw, 0.0);
+            assert!(row.feeder_kw >= -1e-3);
+        }
+        assert_eq!(result.telemetry[16].export_limit_kw, 4.0);
+
+        // Neighbours' solar tightens the hosting-capacity export limit at
+        // midday, and their evening load tightens the import limit.
+        let hosting = run_scenario(
+            &ScenarioConfig {
+                envelope_hosting_capacity_kw: Some(6.0),
+                envelope_background_houses: 2,
+                ..scenario.clone()
+            },
+            false,
+        );
+        let noon = &hosting.telemetry[12];
+        let midnight = &hosting.telemetry[0];
+        assert!(noon.export_limit_kw < midnight.export_limit_kw.min(4.0));
+        assert!(
+            hosting
+                .telemetry
+                .iter()
+                .any(|row| row.import_limit_kw < 5.0)
+        );
+        assert_eq!(
+            hosting.kpis.envelope.as_ref().map(|e| e.export_violations),
+            Some(0)
+        );
+
+        assert!(
+            run_scenario(&ScenarioConfig::default(), false)
+                .kpis
+                .envelope
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn wind_turbine_follows_measured_speed_series() {
+        let scenario = ScenarioConfig {
+            wind_rated_kw: 3.0,
+            wind_speed_series: Some(vec![2.0, 15.0, 30.0]),
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        for row in &result.telemetry {
+            let expected_kw = if row.timestep % 3 == 1 { 3.0 } else { 0.0 };
+            assert_eq!(row.wind_kw + row.wind_curtailed_kw, expected_kw);
+        }
+        let energy_kwh: f32 = result.telemetry.iter().map(|row| row.wind_kw).sum();
+        let wind_energy_kwh = result.kpis.wind_energy_kwh.expect("turbine configured");
+        assert!((wind_energy_kwh - energy_kwh).abs() < 1e-3);
+
+        let without_wind = run_scenario(&ScenarioConfig::default(), false);
+        assert_eq!(without_wind.kpis.wind_energy_kwh, None);
+    }
+
+    #[test]
+    fn genset_covers_dr_shortfall_and_accounts_emissions() {
+        let scenario = ScenarioConfig {
+            dr_start_step: 17,
+            dr_end_step: 21,
+            dr_reduction_kw_per_house: 10.0,
+            genset_rated_kw: 50.0,
+            ..ScenarioConfig::default()
+        };
+        let without_genset = run_scenario(
+            &ScenarioConfig {
+                genset_rated_kw: 0.0,
+                ..scenario.clone()
+            },
+            false,
+        );
+        let result = run_scenario(&scenario, false);
+
+        assert!(without_genset.kpis.genset.is_none());
+        assert!(result.kpis.curtailment_pct > without_genset.kpis.curtailment_pct);
+        let genset = result.kpis.genset.as_ref().expect("genset configured");
+        assert_eq!(genset.starts, 1);
+        assert!(genset.dr_energy_kwh > 0.0 && genset.dr_energy_kwh <= genset.energy_kwh);
+        assert!(genset.co2_kg > 0.0 && genset.nox_kg > 0.0);
+
+        // One step of start-up delay, then the unit runs until the event ends.
+        let rows = &result.telemetry;
+        assert_eq!(rows[17].genset_kw, 0.0);
+        assert!(rows[18..21].iter().all(|row| row.genset_kw > 0.0));
+        assert!(
+            rows.iter()
+                .filter(|row| row.dr_requested_kw == 0.0)
+                .all(|row| row.genset_kw == 0.0)
+        );
+        assert!(rows[23].genset_fuel_l < rows[0].genset_fuel_l);
+    }
+
+    #[test]
+    fn islanded_site_balances_itself_and_reports_unserved_energy() {
+        let scenario = ScenarioConfig {
+            outage_windows: vec![GridOutage::new(0, 12)],
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let islanded: Vec<_> = result.telemetry.iter().filter(|row| row.islanded).collect();
+        assert_eq!(islanded.len(), 12);
+        assert!(
+            islanded
+                .iter()
+                .all(|row| row.feeder_kw.abs() < 1e-3 && row.limit_ok)
+        );
+        let outage = &result.kpis.outages[0];
+        assert!(outage.survived_steps < outage.duration_steps());
+        assert!(result.kpis.unserved_energy_kwh > 0.0);
+        assert!((outage.unserved_kwh() - result.kpis.unserved_energy_kwh).abs() < 1e-4);
+
+        // A genset carries the site once the battery runs out.
+        let with_genset = run_scenario(
+            &ScenarioConfig {
+                genset_rated_kw: 5.0,
+                genset_startup_steps: 0,
+                ..scenario
+            },
+            false,
+        );
+        assert!(with_genset.kpis.unserved_energy_kwh < result.kpis.unserved_energy_kwh);
+        assert!(
+            with_genset
+                .kpis
+                .genset
+                .expect("genset configured")
+                .energy_kwh
+                > 0.0
+        );
+    }
+
+    #[test]
+    fn runs_heterogeneous_dr_events_across_days() {
+        let take = DemandResponseEvent::new(10, 13, 2.0)
+            .with_kind(DemandResponseKind::Take)
+            .with_ramp(1, 0);
+        let target =
+            DemandResponseEvent::new(17, 20, 0.2).with_kind(DemandResponseKind::TargetLevel);
+        let scenario = ScenarioConfig {
+            days: 2,
+            feeder_kw: 20.0,
+            dr_events: vec![
+                DrEventSpec {
+                    day: Some(1),
+                    event: take,
+                },
+                DrEventSpec {
+                    day: None,
+                    event: target,
+                },
+            ],
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let events = &result.kpis.dr_events;
+        let spans: Vec<_> = events
+            .iter()
+            .map(|e| (e.kind, e.start_step, e.end_step))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (DemandResponseKind::TargetLevel, 17, 20),
+                (DemandResponseKind::Take, 34, 37),
+                (DemandResponseKind::TargetLevel, 41, 44),
+            ]
+        );
+        let rows = &result.telemetry;
+        assert_eq!(rows[34].dr_kind, "take");
+        // Ramp-in halves the first step of the take event.
+        assert!((rows[34].dr_requested_kw - 1.0).abs() < 1e-5);
+        // The take event raises the target above the same hour of day 0.
+        assert!((rows[35].target_kw - rows[11].target_kw - 2.0).abs() < 1e-4);
+        assert!(rows[10].dr_kind.is_empty());
+        for row in rows.iter().filter(|row| row.dr_kind == "target") {
+            assert!(row.feeder_kw <= 0.2 + 1e-3, "step {}", row.timestep);
+        }
+        for event in events {
+            assert!(event.delivered_kwh <= event.requested_kwh + 1e-4);
+        }
+        assert!(events[1].delivered_kwh > 0.0);
+    }
+
+    #[test]
+    fn announced_events_let_the_battery_prepare() {
+        // Without solar the battery is not already full when the event is announced.
+        let unannounced = ScenarioConfig {
+            solar_kw_peak_per_house: 0.0,
+            feeder_kw: 10.0,
+            ..ScenarioConfig::default()
+        };
+        let announced = ScenarioConfig {
+            dr_notice_steps: 6,
+            ..unannounced.clone()
+        };
+        let without_notice = run_scenario(&unannounced, false);
+        let with_notice = run_scenario(&announced, false);
+
+        let start = unannounced.dr_start_step;
+        let prep_rows = &with_notice.telemetry[start - 6..start];
+        assert!(prep_rows.iter().all(|row| row.dr_prep_kw < 0.0));
+        assert_eq!(with_notice.telemetry[start - 7].dr_prep_kw, 0.0);
+        assert!(
+            without_notice
+                .telemetry
+                .iter()
+                .all(|row| row.dr_prep_kw == 0.0)
+        );
+        // The battery enters the event fuller than without notice.
+        assert!(
+            with_notice.telemetry[start - 1].battery_soc
+                > without_notice.telemetry[start - 1].battery_soc
+        );
+    }
+
+    #[test]
+    fn announced_events_precool_the_houses() {
+        let unannounced = ScenarioConfig {
+            hvac_kw_per_house: 3.0,
+            ..ScenarioConfig::default()
+        };
+        let announced = ScenarioConfig {
+            dr_notice_steps: 3,
+            ..unannounced.clone()
+        };
+        let without = run_scenario(&unannounced, false).telemetry;
+        let with = run_scenario(&announced, false).telemetry;
+
+        let start = unannounced.dr_start_step;
+        let end = unannounced.dr_end_step;
+        // Cooling rises from the notice on, not before.
+        assert_eq!(with[start - 4].hvac_kw, without[start - 4].hvac_kw);
+        for t in start - 3..start {
+            assert!(with[t].hvac_kw > without[t].hvac_kw, "step {t}");
+        }
+        // The houses enter the event cooler and coast through more of it.
+        assert!(with[start - 1].indoor_temp_c < without[start - 1].indoor_temp_c - 1.0);
+        let event_hvac_kw =
+            |rows: &[TelemetryRow]| -> f32 { rows[start..end].iter().map(|row| row.hvac_kw).sum() };
+        assert!(event_hvac_kw(&with) < event_hvac_kw(&without));
+        let limit_c = unannounced.hvac_setpoint_c + unannounced.hvac_max_setback_c;
+        assert!(with.iter().all(|row| row.indoor_temp_c <= limit_c + 1e-3));
+    }
+
+    #[test]
+    fn shed_baseload_rebounds_after_event() {
+        let without = run_scenario(&ScenarioConfig::default(), false);
+        let scenario = ScenarioConfig {
+            days: 2,
+            dr_rebound_fraction: 1.0,
+            dr_rebound_steps: 3,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+        let rows = &result.telemetry;
+
+        assert!(rows[..21].iter().all(|row| row.rebound_kw == 0.0));
+        // The decay shape peaks right after the event.
+        assert!(rows[21].rebound_kw > rows[22].rebound_kw);
+        assert!(rows[22].rebound_kw > rows[23].rebound_kw);
+        assert!(rows[23].rebound_kw > 0.0);
+        assert_eq!(rows[24].rebound_kw, 0.0);
+
+        let event = &result.kpis.dr_events[0];
+        let peak_kw = event
+            .post_event_peak_kw
+            .expect("run continues past the event");
+        assert!(peak_kw >= without.kpis.dr_events[0].post_event_peak_kw.unwrap_or(0.0));
+        assert_eq!(result.kpis.feeder_import_limit_kw, scenario.feeder_kw);
+    }
+
+    #[test]
+    fn dr_allocation_follows_device_capabilities() {
+        let excluded = DrCapability {
+            curtailable: false,
+            ..DrCapability::with_cost(0.0)
+        };
+        let scenario = ScenarioConfig {
+            solar_kw_peak_per_house: 0.0,
+            feeder_kw: 10.0,
+            dr_ev: excluded,
+            dr_baseload: excluded,
+            dr_notice_steps: 6,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let event_rows = &result.telemetry[17..21];
+        for row in event_rows {
+            assert_eq!(row.dr_ev_kw + row.dr_baseload_kw, 0.0);
+            let contributions = row.dr_ev_kw
+                + row.dr_ev_hub_kw
+                + row.dr_baseload_kw
+                + row.dr_battery_kw
+                + row.dr_genset_kw;
+            assert!((row.dr_achieved_kw - contributions).abs() < 1e-4);
+        }
+        // The battery, charged before the event, delivers the reduction alone.
+        assert!(event_rows.iter().all(|row| row.dr_battery_kw > 1.0));
+
+        let nothing_curtailable = ScenarioConfig {
+            dr_battery: excluded,
+            ..scenario
+        };
+        let result = run_scenario(&nothing_curtailable, false);
+        assert_eq!(result.kpis.dr_events[0].delivered_kwh, 0.0);
+    }
+
+    #[test]
+    fn settles_dr_events_against_customer_baseline() {
+        let scenario = ScenarioConfig {
+            days: 2,
+            settlement_baseline: Some(BaselineMethod::HighXofY { x: 2, y: 3 }),
+            settlement_adjustment_steps: 2,
+            settlement_penalty_per_kwh: 1.0,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let event = &result.kpis.dr_events[0];
+        let settlement = event.settlement.expect("event should be settled");
+        let metered_kwh: f32 = result.telemetry[event.start_step..event.end_step]
+            .iter()
+            .map(|row| row.feeder_kw)
+            .sum();
+        assert!((settlement.metered_kwh - metered_kwh).abs() < 1e-4);
+        assert!(settlement.delivered_kwh <= settlement.committed_kwh + 1e-4);
+        assert!((settlement.payment - 0.5 * settlement.delivered_kwh).abs() < 1e-4);
+        assert!(settlement.performance >= 0.5 || settlement.penalty > 0.0);
+
+        let unsettled = run_scenario(&ScenarioConfig::default(), false);
+        assert!(unsettled.kpis.dr_events[0].settlement.is_none());
+    }
+
+    #[test]
+    fn settlement_baseline_ignores_genset_and_regulation_settings() {
+        let scenario = ScenarioConfig {
+            days: 2,
+            settlement_baseline: Some(BaselineMethod::HighXofY { x: 2, y: 3 }),
+            ..ScenarioConfig::default()
+        };
+        let baseline_kwh = |config: &ScenarioConfig| {
+            run_scenario(config, false).kpis.dr_events[0]
+                .settlement
+                .expect("event should be settled")
+                .baseline_kwh
+        };
+
+        let expected = baseline_kwh(&scenario);
+        let with_genset = ScenarioConfig {
+            genset_rated_kw: 20.0,
+            ..scenario.clone()
+        };
+        let with_regulation = ScenarioConfig {
+            regulation_kw_per_house: 1.0,
+            ..scenario.clone()
+        };
+        assert_eq!(baseline_kwh(&with_genset), expected);
+        assert_eq!(baseline_kwh(&with_regulation), expected);
+    }
+
+    #[test]
+    fn settlement_lookback_days_are_not_copies_of_the_run() {
+        let scenario = ScenarioConfig {
+            settlement_baseline: Some(BaselineMethod::HighXofY { x: 1, y: 1 }),
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+        let event = &result.kpis.dr_events[0];
+        let settlement = event.settlement.expect("event should be settled");
+
+        // Same run without the event: its day 0 is not an earlier day.
+        let no_event = run_scenario(
+            &ScenarioConfig {
+                dr_reduction_kw_per_house: 0.0,
+                settlement_baseline: None,
+                ..scenario.clone()
+            },
+            false,
+        );
+        let dt_hr = 24.0 / scenario.steps_per_day as f32;
+        let same_day_kwh: f32 = no_event.telemetry[event.start_step..event.end_step]
+            .iter()
+            .map(|row| row.feeder_kw * dt_hr)
+            .sum();
+        assert!((settlement.baseline_kwh - same_day_kwh).abs() > 1e-3);
+    }
+
+    #[test]
+    fn weather_matched_and_high_x_of_y_pick_different_days() {
+        let baseline_kwh = |method: BaselineMethod, day_std_c: f32| {
+            let scenario = ScenarioConfig {
+                days: 2,
+                settlement_baseline: Some(method),
+                ambient_temp_day_std_c: day_std_c,
+                ..ScenarioConfig::default()
+            };
+            run_scenario(&scenario, false).kpis.dr_events[0]
+                .settlement
+                .expect("event should be settled")
+                .baseline_kwh
+        };
+
+        let high = baseline_kwh(BaselineMethod::HighXofY { x: 2, y: 6 }, 4.0);
+        let weather = baseline_kwh(BaselineMethod::WeatherMatched { x: 2, y: 6 }, 4.0);
+        // Without day-to-day weather every day matches and the most recent win.
+        let most_recent = baseline_kwh(BaselineMethod::WeatherMatched { x: 2, y: 6 }, 0.0);
+        assert!((high - weather).abs() > 1e-3);
+        assert!((most_recent - weather).abs() > 1e-3);
+    }
+
+    #[test]
+    fn normal_dispatch_holds_battery_reserve() {
+        let scenario = ScenarioConfig {
+            battery_reserve_soc: 0.4,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+
+        let min_soc_outside_dr = result
+            .telemetry
+            .iter()
+            .filter(|row| row.dr_requested_kw == 0.0)
+            .map(|row| row.battery_soc)
+            .fold(f32::INFINITY, f32::min);
+        // The overnight discharge runs down to the reserve and stops there.
+        assert!((min_soc_outside_dr - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn each_house_charges_its_own_ev_session_under_the_policy() {
+        let run = |ev_policy: EvChargingPolicy| {
+            run_scenario(
+                &ScenarioConfig {
+                    houses: 6,
+                    days: 2,
+                    feeder_kw: 4.0,
+                    ev_policy,
+                    ..ScenarioConfig::default()
+                },
+                false,
+            )
+            .kpis
+        };
+        let proportional = run(EvChargingPolicy::Proportional);
+        let edf = run(EvChargingPolicy::EarliestDeadlineFirst);
+
+        assert_eq!(proportional.ev_sessions_departed, 12);
+        assert_eq!(edf.ev_sessions_departed, 12);
+        // A tight feeder curtails charging, and the policy decides which
+        // sessions go short.
+        assert!(proportional.ev_unmet_kwh > 0.0);
+        assert!((proportional.ev_unmet_kwh - edf.ev_unmet_kwh).abs() > 1e-3);
+    }
+
+    #[test]
+    fn ev_hub_from_default_config_is_capped_by_its_ports() {
+        let result = run_scenario(
+            &ScenarioConfig {
+                feeder_kw: 100.0,
+                ev_hub_ports: 4,
+                ..ScenarioConfig::default()
+            },
+            false,
+        );
+        let peak_kw = result
+            .telemetry
+            .iter()
+            .map(|row| row.ev_hub_dispatched_kw)
+            .fold(0.0, f32::max);
+        assert!(peak_kw > 0.0);
+        assert!(peak_kw <= 4.0 * 11.0 + 1e-3);
+    }
+
+    #[test]
+    fn ev_hub_adds_flexible_load_when_enabled() {
+        let without_hub = ScenarioConfig::default();
+        let with_hub = ScenarioConfig {
+            feeder_kw: 100.0,
+            ev_hub_ports: 4,
+            ev_hub_site_cap_kw: Some(44.0),
+            ..ScenarioConfig::default()
+        };
+
+        let run_without = run_scenario(&without_hub, false);
+        let run_with = run_scenario(&with_hub, false);
+
+        let hub_kwh: f32 = run_with
+            .telemetry
+            .iter()
+            .map(|row| row.ev_hub_dispatched_kw)
+            .sum();
+        assert!(hub_kwh > 0.0);
+        assert!(
+            run_without
+                .telemetry
+                .iter()
+                .all(|row| row.ev_hub_dispatched_kw == 0.0)
+        );
+    }
+
+    #[test]
+    fn battery_follows_regulation_signal_and_is_scored() {
+        let result = run_scenario(
+            &ScenarioConfig {
+                regulation_kw_per_house: 2.0,
+                ..ScenarioConfig::default()
+            },
+            false,
+        );
+        let regulation = result.kpis.regulation.expect("regulation configured");
+        assert_eq!(regulation.hours, 24.0);
+        assert!(regulation.score.composite() > 0.8);
+        assert!(regulation.capacity_payment > 0.0 && regulation.performance_payment > 0.0);
+        assert!(result.telemetry.iter().any(|row| row.regulation_kw != 0.0));
+
+        let without = run_scenario(&ScenarioConfig::default(), false);
+        assert!(without.kpis.regulation.is_none());
+        assert!(without.telemetry.iter().all(|row| row.regulation_kw == 0.0));
+    }
+
+    #[test]
+    fn slow_inputs_are_held_and_telemetry_decimated_at_minute_steps() {
+        let config = ScenarioConfig {
+            steps_per_day: 1440,
+            slow_update_steps: 15,
+            dr_reduction_kw_per_house: 0.0,
+            ..ScenarioConfig::default()
+        };
+        let full = run_scenario(&config, false);
+        assert_eq!(full.telemetry.len(), 1440);
+        for block in full.telemetry.chunks(15) {
+            assert!(
+                block
+                    .iter()
+                    .all(|row| row.baseload_kw == block[0].baseload_kw)
+            );
+            assert!(
+                block
+                    .iter()
+                    .all(|row| row.solar_available_kw == block[0].solar_available_kw)
+            );
+            assert!(
+                block
+                    .iter()
+                    .all(|row| row.ambient_temp_c == block[0].ambient_temp_c)
+            );
+        }
+        // Daylight still spans 6 AM to 6 PM at minute resolution.
+        assert_eq!(full.telemetry[300].solar_available_kw, 0.0);
+        assert!(full.telemetry[720].solar_available_kw > 0.0);
+
+        let decimated = run_scenario(
+            &ScenarioConfig {
+                telemetry_interval_steps: 60,
+                ..config
+            },
+            false,
+        );
+        assert_eq!(decimated.telemetry.len(), 24);
+        assert_eq!(decimated.telemetry[1].timestep, 60);
+        // KPIs still cover every step.
+        assert_eq!(decimated.kpis.rmse_tracking_kw, full.kpis.rmse_tracking_kw);
+    }
+
+    #[test]
+    fn battery_and_ev_charging_respond_to_frequency_by_droop() {
+        let config = ScenarioConfig {
+            solar_kw_peak_per_house: 0.0,
+            feeder_kw: 10.0,
+            frequency_events: vec![
+                FrequencyDisturbance::new(2, 6, -0.5),
+                FrequencyDisturbance::new(8, 10, 0.3),
+                FrequencyDisturbance::new(12, 14, -0.3),
+            ],
+            frequency_droop_response: true,
+            frequency_droop: 0.01,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&config, false);
+        let rows = &result.telemetry;
+        assert_eq!(rows[2].frequency_hz, 59.5);
+        assert!(rows[2].droop_battery_kw > 0.0);
+        assert!(rows[8].droop_battery_kw < 0.0);
+        assert_eq!(rows[7].droop_battery_kw, 0.0);
+        // EV charging sheds on under-frequency even when the battery has no headroom left.
+        assert!(rows[12].droop_ev_kw > 0.0);
+        assert!(rows[12].ev_dispatched_kw < rows[11].ev_dispatched_kw);
+        let kpis = result.kpis.frequency_response.expect("droop enabled");
+        assert_eq!(kpis.min_hz, 59.5);
+        assert_eq!(kpis.steps_outside_deadband, 8);
+
+        let without = run_scenario(
+            &ScenarioConfig {
+                frequency_droop_response: false,
+                ..config
+            },
+            false,
+        );
+        assert!(without.kpis.frequency_response.is_none());
+        assert!(
+            without
+                .telemetry
+                .iter()
+                .all(|row| row.droop_battery_kw == 0.0)
+        );
+    }
+
+    #[test]
+    fn vpp_tightens_site_limits_to_hold_feeder_and_substation_limits() {
+        let site = |name: &str, feeder: usize, houses: u32| SiteSpec {
+            name: name.to_string(),
+            feeder,
+            houses,
+            limits: NodeLimits::new(10.0, 8.0),
+            solar_kw_peak_per_house: 0.0,
+            bus: None,
+        };
+        let topology = |feeder_import_kw: f32, substation: NodeLimits| Topology {
+            sites: vec![site("a", 0, 2), site("b", 0, 3), site("c", 1, 2)],
+            feeders: vec![
+                FeederSpec {
+                    name: "F1".to_string(),
+                    limits: NodeLimits::new(feeder_import_kw, 100.0),
+                },
+                FeederSpec {
+                    name: "F2".to_string(),
+                    limits: NodeLimits::new(100.0, 100.0),
+                },
+            ],
+            substation,
+            network: None,
+            voltage_min_pu: 0.95,
+            voltage_max_pu: 1.05,
+        };
+        let scenario = ScenarioConfig {
+            solar_kw_peak_per_house: 0.0,
+            ..ScenarioConfig::default()
+        };
+
+        let free = run_topology(&scenario, &topology(100.0, NodeLimits::unlimited()));
+        assert_eq!(free.coordination_passes, 1);
+        let f1_peak_kw = free.feeder_flows[0].peak_import_kw();
+        let total_peak_kw = free.substation_flow.peak_import_kw();
+        for t in 0..24 {
+            let sum_kw: f32 = free.site_flows.iter().map(|site| site.net_kw[t]).sum();
+            assert!((free.substation_flow.net_kw[t] - sum_kw).abs() < 1e-4);
+        }
+
+        let feeder_limit_kw = f1_peak_kw - 0.5;
+        let substation = NodeLimits::new(total_peak_kw - 0.3, 100.0);
+        let held = run_topology(&scenario, &topology(feeder_limit_kw, substation));
+        assert!(held.coordination_passes > 1);
+        assert_eq!(held.feeder_flows[0].import_violations(), 0);
+        assert_eq!(held.substation_flow.import_violations(), 0);
+        assert!(held.feeder_flows[0].peak_import_kw() <= feeder_limit_kw + 1e-3);
+        assert!(
+            held.site_flows
+                .iter()
+                .all(|site| site.import_violations() == 0)
+        );
+        assert_eq!(held.nodes().len(), 1 + 2 + 3);
+    }
+
+    #[test]
+    fn high_solar_raises_bus_voltage_before_any_kw_limit_binds() {
+        let line = |name: &str, from: usize, to: usize| Line {
+            name: name.to_string(),
+            from,
+            to,
+            r_ohm: 0.08,
+            x_ohm: 0.02,
+            ampacity_a: Some(400.0),
+        };
+        let buses = ["b1", "b2"].map(str::to_string);
+        let network = RadialNetwork::new(
+            0.4,
+            std::iter::once(ROOT_BUS.to_string()).chain(buses).collect(),
+            vec![line("trunk", 0, 1), line("lateral", 1, 2)],
+        )
+        .expect("radial");
+        let site = |name: &str, bus: usize| SiteSpec {
+            name: name.to_string(),
+            feeder: 0,
+            bus: Some(bus),
+            houses: 4,
+            limits: NodeLimits::new(40.0, 32.0),
+            solar_kw_peak_per_house: 8.0,
+        };
+        let topology = Topology {
+            sites: vec![site("near", 1), site("far", 2), site("far2", 2)],
+            feeders: vec![FeederSpec {
+                name: "F1".to_string(),
+                limits: NodeLimits::new(200.0, 160.0),
+            }],
+            substation: NodeLimits::unlimited(),
+            network: Some(network),
+            voltage_min_pu: 0.95,
+            voltage_max_pu: 1.05,
+        };
+
+        let result = run_topology(&ScenarioConfig::default(), &topology);
+        assert!(
+            result
+                .nodes()
+                .iter()
+                .all(|node| node.import_violations() == 0 && node.export_violations() == 0)
+        );
+        let network = result.network.as_ref().expect("lines give network KPIs");
+        assert_eq!(network.unconverged_steps, 0);
+        assert!(network.voltage_violation_steps > 0);
+        // The far bus sees the rise first.
+        assert!(network.buses[2].voltage_max_pu > 1.05);
+        assert!(network.buses[2].voltage_max_pu > network.buses[1].voltage_max_pu);
+        assert_eq!(network.lines[0].overload_steps, 0);
+        // The substation carries the feeder flow plus line losses.
+        for (t, solution) in result.power_flow.iter().enumerate() {
+            let expected_kw = result.feeder_flows[0].net_kw[t] + solution.total_loss_kw();
+            assert!((result.substation_flow.net_kw[t] - expected_kw).abs() < 1e-3);
+        }
+        assert!(network.loss_kwh > 0.0);
+    }
+
+    #[test]
+    fn single_phase_ev_charging_unbalances_the_feeder() {
+        let balanced = run_scenario(&ScenarioConfig::default(), false);
+        assert!(balanced.kpis.unbalance.is_none());
+        for row in &balanced.telemetry {
+            assert!((row.phase_a_kw - row.phase_b_kw).abs() < 1e-4);
+            assert!(row.voltage_unbalance_pct < 1e-3);
+        }
+
+        let scenario = ScenarioConfig {
+            ev_phase: Phase::A,
+            ..ScenarioConfig::default()
+        };
+        let result = run_scenario(&scenario, false);
+        let unbalance = result
+            .kpis
+            .unbalance
+            .expect("single-phase EV reports unbalance");
+        let charging = result
+            .telemetry
+            .iter()
+            .find(|row| row.ev_dispatched_kw > 1.0)
+            .expect("the EV charges at some step");
+        assert!(charging.phase_a_kw > charging.phase_b_kw + 1.0);
+        assert!((charging.phase_b_kw - charging.phase_c_kw).abs() < 1e-4);
+        assert!(
+            (charging.phase_a_kw + charging.phase_b_kw + charging.phase_c_kw - charging.feeder_kw)
+                .abs()
+                < 1e-4
+        );
+        assert!(charging.current_unbalance_pct > 0.0);
+        assert!(unbalance.phase_peak_kw[0] > unbalance.phase_peak_kw[1]);
+        assert!(unbalance.voltage_unbalance_max_pct > 0.0);
+        assert!(unbalance.phase_voltage_min_pu < result.kpis.voltage_min_pu);
+    }
 }
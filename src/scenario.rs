@@ -1,16 +1,288 @@
+use crate::devices::wind::parse_wind_speed_csv;
+use crate::devices::{EvChargingPolicy, ReactivePowerMode};
+use crate::settlement::BaselineMethod;
+use crate::sim::dr_allocation::DrCapability;
+use crate::sim::envelope::{OperatingEnvelope, parse_envelope_csv};
+use crate::sim::event::{DemandResponseEvent, DemandResponseKind};
+use crate::sim::feeder::Phase;
+use crate::sim::frequency::{
+    FrequencyDisturbance, parse_frequency_csv, parse_frequency_disturbances,
+};
+use crate::sim::outage::{GridOutage, parse_outage_windows};
+use crate::sim::power_flow::{Line, ROOT_BUS, RadialNetwork};
+use crate::sim::rebound::ReboundShape;
+use crate::sim::regulation::{RegulationSignalKind, parse_regulation_signal_csv};
+use crate::sim::topology::NodeLimits;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Fields accepted in each `[[dr_events]]` table.
+const DR_EVENT_FIELDS: [&str; 9] = [
+    "kind",
+    "day",
+    "start_step",
+    "end_step",
+    "kw_per_house",
+    "ramp_in_steps",
+    "ramp_out_steps",
+    "profile_kw_per_house",
+    "notice",
+];
+
+/// Fields accepted in each `[[sites]]` table.
+const SITE_FIELDS: [&str; 7] = [
+    "name",
+    "feeder",
+    "bus",
+    "houses",
+    "import_kw",
+    "export_kw",
+    "solar_kw_peak_per_house",
+];
+
+/// Fields accepted in each `[[feeders]]` table.
+const FEEDER_FIELDS: [&str; 3] = ["name", "import_kw", "export_kw"];
+
+/// Fields accepted in each `[[lines]]` table.
+const LINE_FIELDS: [&str; 6] = ["name", "from", "to", "r_ohm", "x_ohm", "ampacity_a"];
+
+/// Scalar keys that describe the line network.
+const NETWORK_KEYS: [&str; 3] = [
+    "network_base_kv",
+    "network_voltage_min_pu",
+    "network_voltage_max_pu",
+];
+
+/// Devices that declare a demand response capability, as `dr_<device>_<field>` keys.
+const DR_DEVICES: [&str; 5] = ["ev", "ev_hub", "baseload", "battery", "hvac"];
+
+/// Fields of each device's demand response capability.
+const DR_CAPABILITY_FIELDS: [&str; 4] = [
+    "curtailable",
+    "max_shed_kw",
+    "max_shed_steps",
+    "cost_per_kwh",
+];
+
+/// A demand response event from a scenario, with kW values per house.
+#[derive(Debug, Clone)]
+pub struct DrEventSpec {
+    /// Day the event is called on; `None` repeats it every day of the run.
+    pub day: Option<usize>,
+    /// Event with start and end steps within the day.
+    pub event: DemandResponseEvent,
+}
+
+/// One site of a multi-site topology: the scenario's devices behind the
+/// site's own meter.
+#[derive(Debug, Clone)]
+pub struct SiteSpec {
+    pub name: String,
+    /// Index of the feeder the site is connected to.
+    pub feeder: usize,
+    /// Network bus the site is connected to, if lines are defined.
+    pub bus: Option<usize>,
+    pub houses: u32,
+    /// Import and export limits at the site meter.
+    pub limits: NodeLimits,
+    pub solar_kw_peak_per_house: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeederSpec {
+    pub name: String,
+    pub limits: NodeLimits,
+}
+
+/// Sites aggregated by the VPP, rolled up into feeders and one substation
+/// transformer.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    pub sites: Vec<SiteSpec>,
+    pub feeders: Vec<FeederSpec>,
+    pub substation: NodeLimits,
+    /// Lines and buses between the substation and the sites, if defined.
+    pub network: Option<RadialNetwork>,
+    pub voltage_min_pu: f32,
+    pub voltage_max_pu: f32,
+}
+
+impl Topology {
+    pub fn voltage_within_limits(&self, voltage_pu: f32) -> bool {
+        (self.voltage_min_pu..=self.voltage_max_pu).contains(&voltage_pu)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ScenarioConfig {
     pub houses: u32,
     pub feeder_kw: f32,
     pub seed: u64,
     pub steps_per_day: usize,
+    pub days: usize,
+    /// Steps between updates of slowly changing inputs (baseload, solar,
+    /// wind, ambient temperature and the load forecast), held in between.
+    pub slow_update_steps: usize,
+    /// Steps between rows kept in the returned telemetry and the readable log.
+    pub telemetry_interval_steps: usize,
     pub solar_kw_peak_per_house: f32,
     pub dr_start_step: usize,
     pub dr_end_step: usize,
     pub dr_reduction_kw_per_house: f32,
+    /// Steps before `dr_start_step` at which the single event is announced.
+    pub dr_notice_steps: usize,
+    /// Let the battery prepare for announced events before they start.
+    pub dr_pre_event_prep: bool,
+    /// Fraction of shed baseload energy that returns after each event.
+    pub dr_rebound_fraction: f32,
+    pub dr_rebound_steps: usize,
+    pub dr_rebound_shape: ReboundShape,
+    /// Demand response capability of the home EV charger.
+    pub dr_ev: DrCapability,
+    pub dr_ev_hub: DrCapability,
+    pub dr_baseload: DrCapability,
+    /// Battery discharge offered as demand response during shed events.
+    pub dr_battery: DrCapability,
+    /// Cooling shed during events, within `hvac_max_setback_c` of the setpoint.
+    pub dr_hvac: DrCapability,
+    /// Demand response events; when empty, the `dr_*` window above is a
+    /// single shed event on the first day.
+    pub dr_events: Vec<DrEventSpec>,
+    /// How a capped EV budget is shared among the houses' charging sessions.
+    pub ev_policy: EvChargingPolicy,
+    pub ev_hub_ports: usize,
+    pub ev_hub_port_kw: f32,
+    /// Shared cap on hub charging; `None` leaves only the port ratings.
+    pub ev_hub_site_cap_kw: Option<f32>,
+    pub ev_hub_arrivals_per_day: usize,
+    pub ev_hub_arrival_mean_step: f32,
+    pub ev_hub_arrival_std_steps: f32,
+    pub ev_hub_demand_kwh_min: f32,
+    pub ev_hub_demand_kwh_max: f32,
+    pub ev_hub_dwell_steps_min: usize,
+    pub ev_hub_dwell_steps_max: usize,
+    pub ev_hub_policy: EvChargingPolicy,
+    pub ev_hub_priority_share: f32,
+    pub battery_degradation: bool,
+    pub battery_cycle_life: f32,
+    pub battery_dod_exponent: f32,
+    pub battery_eol_fade: f32,
+    pub battery_calendar_fade_per_year: f32,
+    pub battery_resistance_growth: f32,
+    pub battery_soc_min: f32,
+    pub battery_soc_max: f32,
+    pub battery_reserve_soc: f32,
+    pub battery_self_discharge_per_day: f32,
+    pub battery_aux_kw_per_house: f32,
+    pub battery_thermal: bool,
+    pub ambient_temp_mean_c: f32,
+    pub ambient_temp_amplitude_c: f32,
+    /// Standard deviation of the day-to-day shift in daily mean temperature.
+    pub ambient_temp_day_std_c: f32,
+    /// Air conditioning rating per house (kW); zero leaves the houses uncooled.
+    pub hvac_kw_per_house: f32,
+    pub hvac_setpoint_c: f32,
+    /// How far below the setpoint the houses are pre-cooled once a shed or
+    /// target-level event is announced.
+    pub hvac_precool_c: f32,
+    /// How far above the setpoint indoor temperature may drift while cooling is shed.
+    pub hvac_max_setback_c: f32,
+    pub battery_ramp_kw_per_min_per_house: f32,
+    pub battery_latency_steps: usize,
+    pub battery_min_on_steps: usize,
+    pub battery_min_off_steps: usize,
+    pub ev_ramp_kw_per_min_per_house: f32,
+    pub ev_hub_ramp_kw_per_min: f32,
+    pub ev_latency_steps: usize,
+    pub ev_min_on_steps: usize,
+    pub ev_min_off_steps: usize,
+    pub inverter_mode: ReactivePowerMode,
+    pub inverter_volt_watt: bool,
+    pub solar_inverter_kva_ratio: f32,
+    pub battery_inverter_kva_ratio: f32,
+    pub baseload_power_factor: f32,
+    pub feeder_r_pu: f32,
+    pub feeder_x_pu: f32,
+    pub solar_curtailment: bool,
+    pub wind_rated_kw: f32,
+    pub wind_cut_in_ms: f32,
+    pub wind_rated_ms: f32,
+    pub wind_cut_out_ms: f32,
+    pub wind_weibull_shape: f32,
+    pub wind_weibull_scale_ms: f32,
+    pub wind_autocorrelation: f32,
+    pub wind_speed_series: Option<Vec<f32>>,
+    pub genset_rated_kw: f32,
+    pub genset_min_load_fraction: f32,
+    pub genset_startup_steps: usize,
+    pub genset_idle_fuel_l_per_h_per_kw: f32,
+    pub genset_fuel_l_per_kwh: f32,
+    /// Tank size; `None` sizes it for 8 hours at rated load.
+    pub genset_tank_l: Option<f32>,
+    pub genset_co2_kg_per_l: f32,
+    pub genset_nox_g_per_kwh: f32,
+    pub outage_windows: Vec<GridOutage>,
+    pub outage_rate_per_day: f32,
+    pub outage_mean_duration_steps: f32,
+    /// Customer baseline used to settle DR events; `None` skips settlement.
+    pub settlement_baseline: Option<BaselineMethod>,
+    /// Steps before an event used for the same-day adjustment (0 disables it).
+    pub settlement_adjustment_steps: usize,
+    pub settlement_adjustment_cap: f32,
+    pub settlement_price_per_kwh: f32,
+    pub settlement_penalty_per_kwh: f32,
+    pub settlement_min_performance: f32,
+    /// Regulation capacity the battery offers around its base point (0 disables it).
+    pub regulation_kw_per_house: f32,
+    pub regulation_signal: RegulationSignalKind,
+    /// Recorded per-second signal; replaces the synthetic `regulation_signal`.
+    pub regulation_signal_series: Option<Vec<f32>>,
+    pub regulation_response_delay_s: usize,
+    /// Capacity payment per kW per hour of service.
+    pub regulation_capacity_price: f32,
+    /// Performance payment per kW of signal mileage.
+    pub regulation_performance_price: f32,
+    pub frequency_nominal_hz: f32,
+    /// Synthetic grid frequency excursions.
+    pub frequency_events: Vec<FrequencyDisturbance>,
+    /// Measured frequency, one value per step; replaces `frequency_events`.
+    pub frequency_series: Option<Vec<f32>>,
+    /// Let the battery and EV charging respond to frequency by droop.
+    pub frequency_droop_response: bool,
+    pub frequency_droop: f32,
+    pub frequency_deadband_hz: f32,
+    /// Phase each device is connected to at the site.
+    pub baseload_phase: Phase,
+    pub solar_phase: Phase,
+    pub wind_phase: Phase,
+    pub battery_phase: Phase,
+    pub genset_phase: Phase,
+    pub ev_phase: Phase,
+    pub ev_hub_phase: Phase,
+    /// Rating of the site's distribution transformer; `0` disables the
+    /// thermal aging model.
+    pub transformer_kva: f32,
+    pub transformer_top_oil_rise_c: f32,
+    pub transformer_hot_spot_rise_c: f32,
+    pub transformer_loss_ratio: f32,
+    pub transformer_oil_time_constant_hr: f32,
+    pub transformer_winding_time_constant_hr: f32,
+    /// Operating envelope set by the DSO, from `envelope_csv` and updates
+    /// pushed through the API.
+    pub envelope: OperatingEnvelope,
+    /// Capacity of the asset the site shares with uncontrolled neighbours,
+    /// from which the DSO computes a hosting-capacity envelope.
+    pub envelope_hosting_capacity_kw: Option<f32>,
+    /// Neighbouring houses (load and solar) behind that asset.
+    pub envelope_background_houses: u32,
+    /// Sites, feeders and substation; `None` runs the single site above.
+    pub topology: Option<Topology>,
+    /// Per-step import limits at the site meter, tighter than `feeder_kw`
+    /// where the VPP holds the site back for upstream nodes; steps past the
+    /// end keep `feeder_kw`.
+    pub import_limit_kw_by_step: Vec<f32>,
+    /// Per-step export limits at the site meter, as `import_limit_kw_by_step`.
+    pub export_limit_kw_by_step: Vec<f32>,
 }
 
 impl Default for ScenarioConfig {
@@ -20,10 +292,129 @@ impl Default for ScenarioConfig {
             feeder_kw: 5.0,
             seed: 42,
             steps_per_day: 24,
+            days: 1,
+            slow_update_steps: 1,
+            telemetry_interval_steps: 1,
             solar_kw_peak_per_house: 5.0,
             dr_start_step: 17,
             dr_end_step: 21,
             dr_reduction_kw_per_house: 1.5,
+            dr_notice_steps: 0,
+            dr_pre_event_prep: true,
+            dr_rebound_fraction: 0.0,
+            dr_rebound_steps: 4,
+            dr_rebound_shape: ReboundShape::Decay,
+            dr_ev: DrCapability::with_cost(0.0),
+            dr_ev_hub: DrCapability::with_cost(0.0),
+            dr_baseload: DrCapability::with_cost(1.0),
+            dr_battery: DrCapability::with_cost(2.0),
+            dr_hvac: DrCapability::with_cost(0.5),
+            dr_events: Vec::new(),
+            ev_policy: EvChargingPolicy::Proportional,
+            ev_hub_ports: 0,
+            ev_hub_port_kw: 11.0,
+            ev_hub_site_cap_kw: None,
+            ev_hub_arrivals_per_day: 8,
+            ev_hub_arrival_mean_step: 8.0,
+            ev_hub_arrival_std_steps: 1.5,
+            ev_hub_demand_kwh_min: 5.0,
+            ev_hub_demand_kwh_max: 30.0,
+            ev_hub_dwell_steps_min: 2,
+            ev_hub_dwell_steps_max: 9,
+            ev_hub_policy: EvChargingPolicy::Proportional,
+            ev_hub_priority_share: 0.0,
+            battery_degradation: false,
+            battery_cycle_life: 4000.0,
+            battery_dod_exponent: 1.3,
+            battery_eol_fade: 0.2,
+            battery_calendar_fade_per_year: 0.02,
+            battery_resistance_growth: 0.0,
+            battery_soc_min: 0.0,
+            battery_soc_max: 1.0,
+            battery_reserve_soc: 0.0,
+            battery_self_discharge_per_day: 0.0,
+            battery_aux_kw_per_house: 0.0,
+            battery_thermal: false,
+            ambient_temp_mean_c: 25.0,
+            ambient_temp_amplitude_c: 8.0,
+            ambient_temp_day_std_c: 2.0,
+            hvac_kw_per_house: 0.0,
+            hvac_setpoint_c: 24.0,
+            hvac_precool_c: 2.0,
+            hvac_max_setback_c: 2.0,
+            battery_ramp_kw_per_min_per_house: 0.0,
+            battery_latency_steps: 0,
+            battery_min_on_steps: 0,
+            battery_min_off_steps: 0,
+            ev_ramp_kw_per_min_per_house: 0.0,
+            ev_hub_ramp_kw_per_min: 0.0,
+            ev_latency_steps: 0,
+            ev_min_on_steps: 0,
+            ev_min_off_steps: 0,
+            inverter_mode: ReactivePowerMode::UnityPowerFactor,
+            inverter_volt_watt: false,
+            solar_inverter_kva_ratio: 1.2,
+            battery_inverter_kva_ratio: 1.0,
+            baseload_power_factor: 0.95,
+            feeder_r_pu: 0.05,
+            feeder_x_pu: 0.05,
+            solar_curtailment: true,
+            wind_rated_kw: 0.0,
+            wind_cut_in_ms: 3.0,
+            wind_rated_ms: 12.0,
+            wind_cut_out_ms: 25.0,
+            wind_weibull_shape: 2.0,
+            wind_weibull_scale_ms: 7.0,
+            wind_autocorrelation: 0.9,
+            wind_speed_series: None,
+            genset_rated_kw: 0.0,
+            genset_min_load_fraction: 0.3,
+            genset_startup_steps: 1,
+            genset_idle_fuel_l_per_h_per_kw: 0.08,
+            genset_fuel_l_per_kwh: 0.246,
+            genset_tank_l: None,
+            genset_co2_kg_per_l: 2.68,
+            genset_nox_g_per_kwh: 10.0,
+            outage_windows: Vec::new(),
+            outage_rate_per_day: 0.0,
+            outage_mean_duration_steps: 4.0,
+            settlement_baseline: None,
+            settlement_adjustment_steps: 0,
+            settlement_adjustment_cap: 0.2,
+            settlement_price_per_kwh: 0.5,
+            settlement_penalty_per_kwh: 0.0,
+            settlement_min_performance: 0.5,
+            regulation_kw_per_house: 0.0,
+            regulation_signal: RegulationSignalKind::RegD,
+            regulation_signal_series: None,
+            regulation_response_delay_s: 2,
+            regulation_capacity_price: 0.01,
+            regulation_performance_price: 0.0005,
+            frequency_nominal_hz: 60.0,
+            frequency_events: Vec::new(),
+            frequency_series: None,
+            frequency_droop_response: false,
+            frequency_droop: 0.05,
+            frequency_deadband_hz: 0.036,
+            baseload_phase: Phase::Three,
+            solar_phase: Phase::Three,
+            wind_phase: Phase::Three,
+            battery_phase: Phase::Three,
+            genset_phase: Phase::Three,
+            ev_phase: Phase::Three,
+            ev_hub_phase: Phase::Three,
+            transformer_kva: 0.0,
+            transformer_top_oil_rise_c: 55.0,
+            transformer_hot_spot_rise_c: 25.0,
+            transformer_loss_ratio: 5.0,
+            transformer_oil_time_constant_hr: 3.0,
+            transformer_winding_time_constant_hr: 0.1,
+            envelope: OperatingEnvelope::default(),
+            envelope_hosting_capacity_kw: None,
+            envelope_background_houses: 0,
+            topology: None,
+            import_limit_kw_by_step: Vec::new(),
+            export_limit_kw_by_step: Vec::new(),
         }
     }
 }
@@ -83,10 +474,138 @@ impl ScenarioConfig {
                 | "feeder_kw"
                 | "seed"
                 | "steps_per_day"
+                | "days"
+                | "slow_update_steps"
+                | "telemetry_interval_steps"
                 | "solar_kw_peak_per_house"
                 | "dr_start_step"
                 | "dr_end_step"
-                | "dr_reduction_kw_per_house" => {}
+                | "dr_notice"
+                | "dr_pre_event_prep"
+                | "dr_rebound_fraction"
+                | "dr_rebound_steps"
+                | "dr_rebound_shape"
+                | "dr_reduction_kw_per_house"
+                | "ev_policy"
+                | "ev_hub_ports"
+                | "ev_hub_port_kw"
+                | "ev_hub_site_cap_kw"
+                | "ev_hub_arrivals_per_day"
+                | "ev_hub_arrival_mean_step"
+                | "ev_hub_arrival_std_steps"
+                | "ev_hub_demand_kwh_min"
+                | "ev_hub_demand_kwh_max"
+                | "ev_hub_dwell_steps_min"
+                | "ev_hub_dwell_steps_max"
+                | "ev_hub_policy"
+                | "ev_hub_priority_share"
+                | "battery_degradation"
+                | "battery_cycle_life"
+                | "battery_dod_exponent"
+                | "battery_eol_fade"
+                | "battery_calendar_fade_per_year"
+                | "battery_resistance_growth"
+                | "battery_soc_min"
+                | "battery_soc_max"
+                | "battery_reserve_soc"
+                | "battery_self_discharge_per_day"
+                | "battery_aux_kw_per_house"
+                | "battery_thermal"
+                | "ambient_temp_mean_c"
+                | "ambient_temp_amplitude_c"
+                | "ambient_temp_day_std_c"
+                | "hvac_kw_per_house"
+                | "hvac_setpoint_c"
+                | "hvac_precool_c"
+                | "hvac_max_setback_c"
+                | "battery_ramp_kw_per_min_per_house"
+                | "battery_latency_steps"
+                | "battery_min_on_steps"
+                | "battery_min_off_steps"
+                | "ev_ramp_kw_per_min_per_house"
+                | "ev_hub_ramp_kw_per_min"
+                | "ev_latency_steps"
+                | "ev_min_on_steps"
+                | "ev_min_off_steps"
+                | "inverter_mode"
+                | "inverter_power_factor"
+                | "inverter_pf_absorbing"
+                | "inverter_volt_watt"
+                | "solar_inverter_kva_ratio"
+                | "battery_inverter_kva_ratio"
+                | "baseload_power_factor"
+                | "feeder_r_pu"
+                | "feeder_x_pu"
+                | "solar_curtailment"
+                | "wind_rated_kw"
+                | "wind_cut_in_ms"
+                | "wind_rated_ms"
+                | "wind_cut_out_ms"
+                | "wind_weibull_shape"
+                | "wind_weibull_scale_ms"
+                | "wind_autocorrelation"
+                | "wind_speed_csv"
+                | "genset_rated_kw"
+                | "genset_min_load_fraction"
+                | "genset_startup_steps"
+                | "genset_idle_fuel_l_per_h_per_kw"
+                | "genset_fuel_l_per_kwh"
+                | "genset_tank_l"
+                | "genset_co2_kg_per_l"
+                | "genset_nox_g_per_kwh"
+                | "outage_windows"
+                | "outage_rate_per_day"
+                | "outage_mean_duration_steps"
+                | "settlement_baseline"
+                | "settlement_baseline_x"
+                | "settlement_baseline_y"
+                | "settlement_adjustment_steps"
+                | "settlement_adjustment_cap"
+                | "settlement_price_per_kwh"
+                | "settlement_penalty_per_kwh"
+                | "settlement_min_performance"
+                | "regulation_kw_per_house"
+                | "regulation_signal"
+                | "regulation_signal_csv"
+                | "regulation_response_delay_s"
+                | "regulation_capacity_price"
+                | "regulation_performance_price"
+                | "frequency_nominal_hz"
+                | "frequency_events"
+                | "frequency_csv"
+                | "frequency_droop_response"
+                | "frequency_droop"
+                | "frequency_deadband_hz"
+                | "substation_import_kw"
+                | "substation_export_kw"
+                | "network_base_kv"
+                | "network_voltage_min_pu"
+                | "network_voltage_max_pu"
+                | "baseload_phase"
+                | "solar_phase"
+                | "wind_phase"
+                | "battery_phase"
+                | "genset_phase"
+                | "ev_phase"
+                | "ev_hub_phase"
+                | "transformer_kva"
+                | "transformer_top_oil_rise_c"
+                | "transformer_hot_spot_rise_c"
+                | "transformer_loss_ratio"
+                | "transformer_oil_time_constant_hr"
+                | "transformer_winding_time_constant_hr"
+                | "envelope_csv"
+                | "envelope_hosting_capacity_kw"
+                | "envelope_background_houses" => {}
+                _ if key.starts_with("dr_events[") => {}
+                _ if ["sites[", "feeders[", "lines["]
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix)) => {}
+                _ if DR_DEVICES.iter().any(|device| {
+                    DR_CAPABILITY_FIELDS
+                        .iter()
+                        .any(|field| *key == format!("dr_{device}_{field}"))
+                }) => {}
                 _ => return Err(format!("at `$.{key}`: unknown key")),
             }
         }
@@ -95,6 +614,17 @@ impl ScenarioConfig {
         let feeder_kw = parse_f32(find_value(obj, "feeder_kw"), "$.feeder_kw", 5.0)?;
         let seed = parse_u64(find_value(obj, "seed"), "$.seed", 42)?;
         let steps_per_day = parse_usize(find_value(obj, "steps_per_day"), "$.steps_per_day", 24)?;
+        let days = parse_usize(find_value(obj, "days"), "$.days", 1)?;
+        let slow_update_steps = parse_usize(
+            find_value(obj, "slow_update_steps"),
+            "$.slow_update_steps",
+            1,
+        )?;
+        let telemetry_interval_steps = parse_usize(
+            find_value(obj, "telemetry_interval_steps"),
+            "$.telemetry_interval_steps",
+            1,
+        )?;
         let solar_kw_peak_per_house = parse_f32(
             find_value(obj, "solar_kw_peak_per_house"),
             "$.solar_kw_peak_per_house",
@@ -107,6 +637,415 @@ impl ScenarioConfig {
             "$.dr_reduction_kw_per_house",
             1.5,
         )?;
+        let dr_pre_event_prep = parse_bool(
+            find_value(obj, "dr_pre_event_prep"),
+            "$.dr_pre_event_prep",
+            true,
+        )?;
+        let dr_rebound_fraction = parse_f32(
+            find_value(obj, "dr_rebound_fraction"),
+            "$.dr_rebound_fraction",
+            0.0,
+        )?;
+        let dr_rebound_steps =
+            parse_usize(find_value(obj, "dr_rebound_steps"), "$.dr_rebound_steps", 4)?;
+        let dr_rebound_shape = match find_value(obj, "dr_rebound_shape") {
+            None => ReboundShape::default(),
+            Some(v) => ReboundShape::from_name(v).ok_or_else(|| {
+                format!("at `$.dr_rebound_shape`: unknown shape `{v}` (expected `flat` or `decay`)")
+            })?,
+        };
+        let ev_policy = parse_ev_policy(find_value(obj, "ev_policy"), "$.ev_policy")?;
+        let ev_hub_ports = parse_usize(find_value(obj, "ev_hub_ports"), "$.ev_hub_ports", 0)?;
+        let ev_hub_port_kw =
+            parse_f32(find_value(obj, "ev_hub_port_kw"), "$.ev_hub_port_kw", 11.0)?;
+        let ev_hub_site_cap_kw = find_value(obj, "ev_hub_site_cap_kw")
+            .map(|value| parse_f32(Some(value), "$.ev_hub_site_cap_kw", 0.0))
+            .transpose()?;
+        let ev_hub_arrivals_per_day = parse_usize(
+            find_value(obj, "ev_hub_arrivals_per_day"),
+            "$.ev_hub_arrivals_per_day",
+            8,
+        )?;
+        let ev_hub_arrival_mean_step = parse_f32(
+            find_value(obj, "ev_hub_arrival_mean_step"),
+            "$.ev_hub_arrival_mean_step",
+            8.0,
+        )?;
+        let ev_hub_arrival_std_steps = parse_f32(
+            find_value(obj, "ev_hub_arrival_std_steps"),
+            "$.ev_hub_arrival_std_steps",
+            1.5,
+        )?;
+        let ev_hub_demand_kwh_min = parse_f32(
+            find_value(obj, "ev_hub_demand_kwh_min"),
+            "$.ev_hub_demand_kwh_min",
+            5.0,
+        )?;
+        let ev_hub_demand_kwh_max = parse_f32(
+            find_value(obj, "ev_hub_demand_kwh_max"),
+            "$.ev_hub_demand_kwh_max",
+            30.0,
+        )?;
+        let ev_hub_dwell_steps_min = parse_usize(
+            find_value(obj, "ev_hub_dwell_steps_min"),
+            "$.ev_hub_dwell_steps_min",
+            2,
+        )?;
+        let ev_hub_dwell_steps_max = parse_usize(
+            find_value(obj, "ev_hub_dwell_steps_max"),
+            "$.ev_hub_dwell_steps_max",
+            9,
+        )?;
+        let ev_hub_policy = parse_ev_policy(find_value(obj, "ev_hub_policy"), "$.ev_hub_policy")?;
+        let ev_hub_priority_share = parse_f32(
+            find_value(obj, "ev_hub_priority_share"),
+            "$.ev_hub_priority_share",
+            0.0,
+        )?;
+        let battery_degradation = parse_bool(
+            find_value(obj, "battery_degradation"),
+            "$.battery_degradation",
+            false,
+        )?;
+        let battery_cycle_life = parse_f32(
+            find_value(obj, "battery_cycle_life"),
+            "$.battery_cycle_life",
+            4000.0,
+        )?;
+        let battery_dod_exponent = parse_f32(
+            find_value(obj, "battery_dod_exponent"),
+            "$.battery_dod_exponent",
+            1.3,
+        )?;
+        let battery_eol_fade = parse_f32(
+            find_value(obj, "battery_eol_fade"),
+            "$.battery_eol_fade",
+            0.2,
+        )?;
+        let battery_calendar_fade_per_year = parse_f32(
+            find_value(obj, "battery_calendar_fade_per_year"),
+            "$.battery_calendar_fade_per_year",
+            0.02,
+        )?;
+        let battery_resistance_growth = parse_f32(
+            find_value(obj, "battery_resistance_growth"),
+            "$.battery_resistance_growth",
+            0.0,
+        )?;
+        let battery_soc_min =
+            parse_f32(find_value(obj, "battery_soc_min"), "$.battery_soc_min", 0.0)?;
+        let battery_soc_max =
+            parse_f32(find_value(obj, "battery_soc_max"), "$.battery_soc_max", 1.0)?;
+        let battery_reserve_soc = parse_f32(
+            find_value(obj, "battery_reserve_soc"),
+            "$.battery_reserve_soc",
+            battery_soc_min,
+        )?;
+        let battery_self_discharge_per_day = parse_f32(
+            find_value(obj, "battery_self_discharge_per_day"),
+            "$.battery_self_discharge_per_day",
+            0.0,
+        )?;
+        let battery_aux_kw_per_house = parse_f32(
+            find_value(obj, "battery_aux_kw_per_house"),
+            "$.battery_aux_kw_per_house",
+            0.0,
+        )?;
+        let battery_thermal = parse_bool(
+            find_value(obj, "battery_thermal"),
+            "$.battery_thermal",
+            false,
+        )?;
+        let ambient_temp_mean_c = parse_f32(
+            find_value(obj, "ambient_temp_mean_c"),
+            "$.ambient_temp_mean_c",
+            25.0,
+        )?;
+        let ambient_temp_amplitude_c = parse_f32(
+            find_value(obj, "ambient_temp_amplitude_c"),
+            "$.ambient_temp_amplitude_c",
+            8.0,
+        )?;
+        let ambient_temp_day_std_c = parse_f32(
+            find_value(obj, "ambient_temp_day_std_c"),
+            "$.ambient_temp_day_std_c",
+            2.0,
+        )?;
+        let hvac_kw_per_house = parse_f32(
+            find_value(obj, "hvac_kw_per_house"),
+            "$.hvac_kw_per_house",
+            0.0,
+        )?;
+        let hvac_setpoint_c = parse_f32(
+            find_value(obj, "hvac_setpoint_c"),
+            "$.hvac_setpoint_c",
+            24.0,
+        )?;
+        let hvac_precool_c = parse_f32(find_value(obj, "hvac_precool_c"), "$.hvac_precool_c", 2.0)?;
+        let hvac_max_setback_c = parse_f32(
+            find_value(obj, "hvac_max_setback_c"),
+            "$.hvac_max_setback_c",
+            2.0,
+        )?;
+        let battery_ramp_kw_per_min_per_house = parse_f32(
+            find_value(obj, "battery_ramp_kw_per_min_per_house"),
+            "$.battery_ramp_kw_per_min_per_house",
+            0.0,
+        )?;
+        let battery_latency_steps = parse_usize(
+            find_value(obj, "battery_latency_steps"),
+            "$.battery_latency_steps",
+            0,
+        )?;
+        let battery_min_on_steps = parse_usize(
+            find_value(obj, "battery_min_on_steps"),
+            "$.battery_min_on_steps",
+            0,
+        )?;
+        let battery_min_off_steps = parse_usize(
+            find_value(obj, "battery_min_off_steps"),
+            "$.battery_min_off_steps",
+            0,
+        )?;
+        let ev_ramp_kw_per_min_per_house = parse_f32(
+            find_value(obj, "ev_ramp_kw_per_min_per_house"),
+            "$.ev_ramp_kw_per_min_per_house",
+            0.0,
+        )?;
+        let ev_hub_ramp_kw_per_min = parse_f32(
+            find_value(obj, "ev_hub_ramp_kw_per_min"),
+            "$.ev_hub_ramp_kw_per_min",
+            0.0,
+        )?;
+        let ev_latency_steps =
+            parse_usize(find_value(obj, "ev_latency_steps"), "$.ev_latency_steps", 0)?;
+        let ev_min_on_steps =
+            parse_usize(find_value(obj, "ev_min_on_steps"), "$.ev_min_on_steps", 0)?;
+        let ev_min_off_steps =
+            parse_usize(find_value(obj, "ev_min_off_steps"), "$.ev_min_off_steps", 0)?;
+        let inverter_power_factor = parse_f32(
+            find_value(obj, "inverter_power_factor"),
+            "$.inverter_power_factor",
+            0.95,
+        )?;
+        let inverter_pf_absorbing = parse_bool(
+            find_value(obj, "inverter_pf_absorbing"),
+            "$.inverter_pf_absorbing",
+            true,
+        )?;
+        let inverter_mode = parse_inverter_mode(
+            find_value(obj, "inverter_mode"),
+            "$.inverter_mode",
+            inverter_power_factor,
+            inverter_pf_absorbing,
+        )?;
+        let inverter_volt_watt = parse_bool(
+            find_value(obj, "inverter_volt_watt"),
+            "$.inverter_volt_watt",
+            false,
+        )?;
+        let solar_inverter_kva_ratio = parse_f32(
+            find_value(obj, "solar_inverter_kva_ratio"),
+            "$.solar_inverter_kva_ratio",
+            1.2,
+        )?;
+        let battery_inverter_kva_ratio = parse_f32(
+            find_value(obj, "battery_inverter_kva_ratio"),
+            "$.battery_inverter_kva_ratio",
+            1.0,
+        )?;
+        let baseload_power_factor = parse_f32(
+            find_value(obj, "baseload_power_factor"),
+            "$.baseload_power_factor",
+            0.95,
+        )?;
+        let feeder_r_pu = parse_f32(find_value(obj, "feeder_r_pu"), "$.feeder_r_pu", 0.05)?;
+        let feeder_x_pu = parse_f32(find_value(obj, "feeder_x_pu"), "$.feeder_x_pu", 0.05)?;
+        let solar_curtailment = parse_bool(
+            find_value(obj, "solar_curtailment"),
+            "$.solar_curtailment",
+            true,
+        )?;
+        let wind_rated_kw = parse_f32(find_value(obj, "wind_rated_kw"), "$.wind_rated_kw", 0.0)?;
+        let wind_cut_in_ms = parse_f32(find_value(obj, "wind_cut_in_ms"), "$.wind_cut_in_ms", 3.0)?;
+        let wind_rated_ms = parse_f32(find_value(obj, "wind_rated_ms"), "$.wind_rated_ms", 12.0)?;
+        let wind_cut_out_ms = parse_f32(
+            find_value(obj, "wind_cut_out_ms"),
+            "$.wind_cut_out_ms",
+            25.0,
+        )?;
+        let wind_weibull_shape = parse_f32(
+            find_value(obj, "wind_weibull_shape"),
+            "$.wind_weibull_shape",
+            2.0,
+        )?;
+        let wind_weibull_scale_ms = parse_f32(
+            find_value(obj, "wind_weibull_scale_ms"),
+            "$.wind_weibull_scale_ms",
+            7.0,
+        )?;
+        let wind_autocorrelation = parse_f32(
+            find_value(obj, "wind_autocorrelation"),
+            "$.wind_autocorrelation",
+            0.9,
+        )?;
+        let wind_speed_series = find_value(obj, "wind_speed_csv")
+            .map(|path| load_wind_speed_csv(path, "$.wind_speed_csv"))
+            .transpose()?;
+        let genset_rated_kw =
+            parse_f32(find_value(obj, "genset_rated_kw"), "$.genset_rated_kw", 0.0)?;
+        let genset_min_load_fraction = parse_f32(
+            find_value(obj, "genset_min_load_fraction"),
+            "$.genset_min_load_fraction",
+            0.3,
+        )?;
+        let genset_startup_steps = parse_usize(
+            find_value(obj, "genset_startup_steps"),
+            "$.genset_startup_steps",
+            1,
+        )?;
+        let genset_idle_fuel_l_per_h_per_kw = parse_f32(
+            find_value(obj, "genset_idle_fuel_l_per_h_per_kw"),
+            "$.genset_idle_fuel_l_per_h_per_kw",
+            0.08,
+        )?;
+        let genset_fuel_l_per_kwh = parse_f32(
+            find_value(obj, "genset_fuel_l_per_kwh"),
+            "$.genset_fuel_l_per_kwh",
+            0.246,
+        )?;
+        let genset_tank_l = find_value(obj, "genset_tank_l")
+            .map(|value| parse_f32(Some(value), "$.genset_tank_l", 0.0))
+            .transpose()?;
+        let genset_co2_kg_per_l = parse_f32(
+            find_value(obj, "genset_co2_kg_per_l"),
+            "$.genset_co2_kg_per_l",
+            2.68,
+        )?;
+        let genset_nox_g_per_kwh = parse_f32(
+            find_value(obj, "genset_nox_g_per_kwh"),
+            "$.genset_nox_g_per_kwh",
+            10.0,
+        )?;
+        let outage_windows = find_value(obj, "outage_windows")
+            .map(|raw| {
+                parse_outage_windows(raw).map_err(|err| format!("at `$.outage_windows`: {err}"))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let outage_rate_per_day = parse_f32(
+            find_value(obj, "outage_rate_per_day"),
+            "$.outage_rate_per_day",
+            0.0,
+        )?;
+        let outage_mean_duration_steps = parse_f32(
+            find_value(obj, "outage_mean_duration_steps"),
+            "$.outage_mean_duration_steps",
+            4.0,
+        )?;
+        let settlement_baseline_x = parse_usize(
+            find_value(obj, "settlement_baseline_x"),
+            "$.settlement_baseline_x",
+            4,
+        )?;
+        let settlement_baseline_y = parse_usize(
+            find_value(obj, "settlement_baseline_y"),
+            "$.settlement_baseline_y",
+            5,
+        )?;
+        let settlement_baseline = parse_settlement_baseline(
+            find_value(obj, "settlement_baseline"),
+            "$.settlement_baseline",
+            settlement_baseline_x,
+            settlement_baseline_y,
+        )?;
+        let settlement_adjustment_steps = parse_usize(
+            find_value(obj, "settlement_adjustment_steps"),
+            "$.settlement_adjustment_steps",
+            0,
+        )?;
+        let settlement_adjustment_cap = parse_f32(
+            find_value(obj, "settlement_adjustment_cap"),
+            "$.settlement_adjustment_cap",
+            0.2,
+        )?;
+        let settlement_price_per_kwh = parse_f32(
+            find_value(obj, "settlement_price_per_kwh"),
+            "$.settlement_price_per_kwh",
+            0.5,
+        )?;
+        let settlement_penalty_per_kwh = parse_f32(
+            find_value(obj, "settlement_penalty_per_kwh"),
+            "$.settlement_penalty_per_kwh",
+            0.0,
+        )?;
+        let settlement_min_performance = parse_f32(
+            find_value(obj, "settlement_min_performance"),
+            "$.settlement_min_performance",
+            0.5,
+        )?;
+        let regulation_kw_per_house = parse_f32(
+            find_value(obj, "regulation_kw_per_house"),
+            "$.regulation_kw_per_house",
+            0.0,
+        )?;
+        let regulation_signal = match find_value(obj, "regulation_signal") {
+            None => RegulationSignalKind::default(),
+            Some(v) => RegulationSignalKind::from_name(v).ok_or_else(|| {
+                format!(
+                    "at `$.regulation_signal`: unknown signal `{v}` (expected `reg_a` or `reg_d`)"
+                )
+            })?,
+        };
+        let regulation_signal_series = find_value(obj, "regulation_signal_csv")
+            .map(|path| load_regulation_signal_csv(path, "$.regulation_signal_csv"))
+            .transpose()?;
+        let regulation_response_delay_s = parse_usize(
+            find_value(obj, "regulation_response_delay_s"),
+            "$.regulation_response_delay_s",
+            2,
+        )?;
+        let regulation_capacity_price = parse_f32(
+            find_value(obj, "regulation_capacity_price"),
+            "$.regulation_capacity_price",
+            0.01,
+        )?;
+        let regulation_performance_price = parse_f32(
+            find_value(obj, "regulation_performance_price"),
+            "$.regulation_performance_price",
+            0.0005,
+        )?;
+        let frequency_nominal_hz = parse_f32(
+            find_value(obj, "frequency_nominal_hz"),
+            "$.frequency_nominal_hz",
+            60.0,
+        )?;
+        let frequency_events = find_value(obj, "frequency_events")
+            .map(|raw| {
+                parse_frequency_disturbances(raw)
+                    .map_err(|err| format!("at `$.frequency_events`: {err}"))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let frequency_series = find_value(obj, "frequency_csv")
+            .map(|path| load_frequency_csv(path, "$.frequency_csv"))
+            .transpose()?;
+        let frequency_droop_response = parse_bool(
+            find_value(obj, "frequency_droop_response"),
+            "$.frequency_droop_response",
+            false,
+        )?;
+        let frequency_droop = parse_f32(
+            find_value(obj, "frequency_droop"),
+            "$.frequency_droop",
+            0.05,
+        )?;
+        let frequency_deadband_hz = parse_f32(
+            find_value(obj, "frequency_deadband_hz"),
+            "$.frequency_deadband_hz",
+            0.036,
+        )?;
 
         if houses == 0 {
             return Err("at `$.houses`: must be > 0".to_string());
@@ -117,6 +1056,15 @@ impl ScenarioConfig {
         if steps_per_day == 0 {
             return Err("at `$.steps_per_day`: must be > 0".to_string());
         }
+        if days == 0 {
+            return Err("at `$.days`: must be > 0".to_string());
+        }
+        if slow_update_steps == 0 {
+            return Err("at `$.slow_update_steps`: must be > 0".to_string());
+        }
+        if telemetry_interval_steps == 0 {
+            return Err("at `$.telemetry_interval_steps`: must be > 0".to_string());
+        }
         if solar_kw_peak_per_house < 0.0 {
             return Err("at `$.solar_kw_peak_per_house`: must be >= 0".to_string());
         }
@@ -132,16 +1080,460 @@ impl ScenarioConfig {
         if dr_reduction_kw_per_house < 0.0 {
             return Err("at `$.dr_reduction_kw_per_house`: must be >= 0".to_string());
         }
+        if dr_rebound_fraction < 0.0 {
+            return Err("at `$.dr_rebound_fraction`: must be >= 0".to_string());
+        }
+        if dr_rebound_steps == 0 {
+            return Err("at `$.dr_rebound_steps`: must be > 0".to_string());
+        }
+        let dr_ev = parse_dr_capability(obj, "ev", 0.0)?;
+        let dr_ev_hub = parse_dr_capability(obj, "ev_hub", 0.0)?;
+        let dr_baseload = parse_dr_capability(obj, "baseload", 1.0)?;
+        let dr_battery = parse_dr_capability(obj, "battery", 2.0)?;
+        let dr_hvac = parse_dr_capability(obj, "hvac", 0.5)?;
+        let dr_notice_steps =
+            parse_dr_notice(find_value(obj, "dr_notice"), "$.dr_notice", steps_per_day)?;
+        let dr_events = parse_dr_events(obj, steps_per_day, days)?;
+        if !dr_events.is_empty()
+            && let Some(key) = [
+                "dr_start_step",
+                "dr_end_step",
+                "dr_reduction_kw_per_house",
+                "dr_notice",
+            ]
+            .into_iter()
+            .find(|key| find_value(obj, key).is_some())
+        {
+            return Err(format!("at `$.{key}`: cannot be combined with `dr_events`"));
+        }
+        if ev_hub_ports > 0 {
+            if ev_hub_port_kw <= 0.0 {
+                return Err("at `$.ev_hub_port_kw`: must be > 0".to_string());
+            }
+            if ev_hub_site_cap_kw.is_some_and(|cap_kw| cap_kw <= 0.0) {
+                return Err("at `$.ev_hub_site_cap_kw`: must be > 0".to_string());
+            }
+            if ev_hub_arrival_mean_step < 0.0 || ev_hub_arrival_mean_step >= steps_per_day as f32 {
+                return Err(
+                    "at `$.ev_hub_arrival_mean_step`: must be in [0, steps_per_day)".to_string(),
+                );
+            }
+            if ev_hub_arrival_std_steps < 0.0 {
+                return Err("at `$.ev_hub_arrival_std_steps`: must be >= 0".to_string());
+            }
+            if ev_hub_demand_kwh_min < 0.0 {
+                return Err("at `$.ev_hub_demand_kwh_min`: must be >= 0".to_string());
+            }
+            if ev_hub_demand_kwh_max < ev_hub_demand_kwh_min {
+                return Err(
+                    "at `$.ev_hub_demand_kwh_max`: must be >= ev_hub_demand_kwh_min".to_string(),
+                );
+            }
+            if ev_hub_dwell_steps_min == 0 {
+                return Err("at `$.ev_hub_dwell_steps_min`: must be > 0".to_string());
+            }
+            if ev_hub_dwell_steps_max < ev_hub_dwell_steps_min {
+                return Err(
+                    "at `$.ev_hub_dwell_steps_max`: must be >= ev_hub_dwell_steps_min".to_string(),
+                );
+            }
+            if !(0.0..=1.0).contains(&ev_hub_priority_share) {
+                return Err("at `$.ev_hub_priority_share`: must be in [0, 1]".to_string());
+            }
+        }
+        if battery_cycle_life <= 0.0 {
+            return Err("at `$.battery_cycle_life`: must be > 0".to_string());
+        }
+        if battery_dod_exponent < 0.0 {
+            return Err("at `$.battery_dod_exponent`: must be >= 0".to_string());
+        }
+        if !(0.0..1.0).contains(&battery_eol_fade) {
+            return Err("at `$.battery_eol_fade`: must be in [0, 1)".to_string());
+        }
+        if battery_calendar_fade_per_year < 0.0 {
+            return Err("at `$.battery_calendar_fade_per_year`: must be >= 0".to_string());
+        }
+        if battery_resistance_growth < 0.0 {
+            return Err("at `$.battery_resistance_growth`: must be >= 0".to_string());
+        }
+        if !(0.0..1.0).contains(&battery_soc_min) {
+            return Err("at `$.battery_soc_min`: must be in [0, 1)".to_string());
+        }
+        if battery_soc_max <= battery_soc_min || battery_soc_max > 1.0 {
+            return Err("at `$.battery_soc_max`: must be in (battery_soc_min, 1]".to_string());
+        }
+        if !(battery_soc_min..=battery_soc_max).contains(&battery_reserve_soc) {
+            return Err(
+                "at `$.battery_reserve_soc`: must be in [battery_soc_min, battery_soc_max]"
+                    .to_string(),
+            );
+        }
+        if !(0.0..1.0).contains(&battery_self_discharge_per_day) {
+            return Err("at `$.battery_self_discharge_per_day`: must be in [0, 1)".to_string());
+        }
+        if battery_aux_kw_per_house < 0.0 {
+            return Err("at `$.battery_aux_kw_per_house`: must be >= 0".to_string());
+        }
+        if !(-60.0..=60.0).contains(&ambient_temp_mean_c) {
+            return Err("at `$.ambient_temp_mean_c`: must be in [-60, 60]".to_string());
+        }
+        if ambient_temp_amplitude_c < 0.0 {
+            return Err("at `$.ambient_temp_amplitude_c`: must be >= 0".to_string());
+        }
+        if ambient_temp_day_std_c < 0.0 {
+            return Err("at `$.ambient_temp_day_std_c`: must be >= 0".to_string());
+        }
+        if hvac_kw_per_house < 0.0 {
+            return Err("at `$.hvac_kw_per_house`: must be >= 0".to_string());
+        }
+        if !(10.0..=35.0).contains(&hvac_setpoint_c) {
+            return Err("at `$.hvac_setpoint_c`: must be in [10, 35]".to_string());
+        }
+        if hvac_precool_c < 0.0 {
+            return Err("at `$.hvac_precool_c`: must be >= 0".to_string());
+        }
+        if hvac_max_setback_c < 0.0 {
+            return Err("at `$.hvac_max_setback_c`: must be >= 0".to_string());
+        }
+        for (key, ramp) in [
+            (
+                "battery_ramp_kw_per_min_per_house",
+                battery_ramp_kw_per_min_per_house,
+            ),
+            ("ev_ramp_kw_per_min_per_house", ev_ramp_kw_per_min_per_house),
+            ("ev_hub_ramp_kw_per_min", ev_hub_ramp_kw_per_min),
+        ] {
+            if ramp < 0.0 {
+                return Err(format!("at `$.{key}`: must be >= 0"));
+            }
+        }
+        for (key, pf) in [
+            ("inverter_power_factor", inverter_power_factor),
+            ("baseload_power_factor", baseload_power_factor),
+        ] {
+            if !(pf > 0.0 && pf <= 1.0) {
+                return Err(format!("at `$.{key}`: must be in (0, 1]"));
+            }
+        }
+        if solar_inverter_kva_ratio <= 0.0 {
+            return Err("at `$.solar_inverter_kva_ratio`: must be > 0".to_string());
+        }
+        if battery_inverter_kva_ratio <= 0.0 {
+            return Err("at `$.battery_inverter_kva_ratio`: must be > 0".to_string());
+        }
+        if feeder_r_pu < 0.0 {
+            return Err("at `$.feeder_r_pu`: must be >= 0".to_string());
+        }
+        if feeder_x_pu < 0.0 {
+            return Err("at `$.feeder_x_pu`: must be >= 0".to_string());
+        }
+        if wind_rated_kw < 0.0 {
+            return Err("at `$.wind_rated_kw`: must be >= 0".to_string());
+        }
+        if wind_rated_kw > 0.0 {
+            if wind_cut_in_ms < 0.0 {
+                return Err("at `$.wind_cut_in_ms`: must be >= 0".to_string());
+            }
+            if wind_rated_ms <= wind_cut_in_ms {
+                return Err("at `$.wind_rated_ms`: must be > wind_cut_in_ms".to_string());
+            }
+            if wind_cut_out_ms <= wind_rated_ms {
+                return Err("at `$.wind_cut_out_ms`: must be > wind_rated_ms".to_string());
+            }
+            if wind_weibull_shape <= 0.0 {
+                return Err("at `$.wind_weibull_shape`: must be > 0".to_string());
+            }
+            if wind_weibull_scale_ms <= 0.0 {
+                return Err("at `$.wind_weibull_scale_ms`: must be > 0".to_string());
+            }
+            if !(0.0..1.0).contains(&wind_autocorrelation) {
+                return Err("at `$.wind_autocorrelation`: must be in [0, 1)".to_string());
+            }
+        }
+        if genset_rated_kw < 0.0 {
+            return Err("at `$.genset_rated_kw`: must be >= 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&genset_min_load_fraction) {
+            return Err("at `$.genset_min_load_fraction`: must be in [0, 1]".to_string());
+        }
+        if genset_idle_fuel_l_per_h_per_kw < 0.0 {
+            return Err("at `$.genset_idle_fuel_l_per_h_per_kw`: must be >= 0".to_string());
+        }
+        if genset_fuel_l_per_kwh < 0.0 {
+            return Err("at `$.genset_fuel_l_per_kwh`: must be >= 0".to_string());
+        }
+        if genset_tank_l.is_some_and(|tank_l| tank_l < 0.0) {
+            return Err("at `$.genset_tank_l`: must be >= 0".to_string());
+        }
+        if genset_co2_kg_per_l < 0.0 {
+            return Err("at `$.genset_co2_kg_per_l`: must be >= 0".to_string());
+        }
+        if genset_nox_g_per_kwh < 0.0 {
+            return Err("at `$.genset_nox_g_per_kwh`: must be >= 0".to_string());
+        }
+        if outage_windows
+            .iter()
+            .any(|outage| outage.end_step > steps_per_day * days)
+        {
+            return Err("at `$.outage_windows`: end must be <= steps_per_day * days".to_string());
+        }
+        if outage_rate_per_day < 0.0 {
+            return Err("at `$.outage_rate_per_day`: must be >= 0".to_string());
+        }
+        if outage_mean_duration_steps < 1.0 {
+            return Err("at `$.outage_mean_duration_steps`: must be >= 1".to_string());
+        }
+        if settlement_baseline_y == 0 {
+            return Err("at `$.settlement_baseline_y`: must be > 0".to_string());
+        }
+        if settlement_baseline_x == 0 || settlement_baseline_x > settlement_baseline_y {
+            return Err(
+                "at `$.settlement_baseline_x`: must be in [1, settlement_baseline_y]".to_string(),
+            );
+        }
+        if settlement_adjustment_steps >= steps_per_day {
+            return Err("at `$.settlement_adjustment_steps`: must be < steps_per_day".to_string());
+        }
+        if settlement_adjustment_cap < 0.0 {
+            return Err("at `$.settlement_adjustment_cap`: must be >= 0".to_string());
+        }
+        if settlement_price_per_kwh < 0.0 {
+            return Err("at `$.settlement_price_per_kwh`: must be >= 0".to_string());
+        }
+        if settlement_penalty_per_kwh < 0.0 {
+            return Err("at `$.settlement_penalty_per_kwh`: must be >= 0".to_string());
+        }
+        if !(0.0..=1.0).contains(&settlement_min_performance) {
+            return Err("at `$.settlement_min_performance`: must be in [0, 1]".to_string());
+        }
+        // The battery is rated 5 kW per house in each direction.
+        if !(0.0..=5.0).contains(&regulation_kw_per_house) {
+            return Err("at `$.regulation_kw_per_house`: must be in [0, 5]".to_string());
+        }
+        if regulation_kw_per_house > 0.0 && 86_400 % steps_per_day != 0 {
+            return Err(
+                "at `$.steps_per_day`: must divide a day into whole seconds for regulation"
+                    .to_string(),
+            );
+        }
+        if regulation_capacity_price < 0.0 {
+            return Err("at `$.regulation_capacity_price`: must be >= 0".to_string());
+        }
+        if regulation_performance_price < 0.0 {
+            return Err("at `$.regulation_performance_price`: must be >= 0".to_string());
+        }
+        if frequency_nominal_hz <= 0.0 {
+            return Err("at `$.frequency_nominal_hz`: must be > 0".to_string());
+        }
+        if frequency_events
+            .iter()
+            .any(|event| event.end_step > steps_per_day * days)
+        {
+            return Err("at `$.frequency_events`: end must be <= steps_per_day * days".to_string());
+        }
+        if frequency_droop <= 0.0 || frequency_droop > 1.0 {
+            return Err("at `$.frequency_droop`: must be in (0, 1]".to_string());
+        }
+        if frequency_deadband_hz < 0.0 {
+            return Err("at `$.frequency_deadband_hz`: must be >= 0".to_string());
+        }
+        let topology = parse_topology(obj, houses, feeder_kw, solar_kw_peak_per_house)?;
+        let baseload_phase = parse_phase(find_value(obj, "baseload_phase"), "$.baseload_phase")?;
+        let solar_phase = parse_phase(find_value(obj, "solar_phase"), "$.solar_phase")?;
+        let wind_phase = parse_phase(find_value(obj, "wind_phase"), "$.wind_phase")?;
+        let battery_phase = parse_phase(find_value(obj, "battery_phase"), "$.battery_phase")?;
+        let genset_phase = parse_phase(find_value(obj, "genset_phase"), "$.genset_phase")?;
+        let ev_phase = parse_phase(find_value(obj, "ev_phase"), "$.ev_phase")?;
+        let ev_hub_phase = parse_phase(find_value(obj, "ev_hub_phase"), "$.ev_hub_phase")?;
+        let transformer_kva =
+            parse_f32(find_value(obj, "transformer_kva"), "$.transformer_kva", 0.0)?;
+        if transformer_kva < 0.0 {
+            return Err("at `$.transformer_kva`: must be >= 0".to_string());
+        }
+        let transformer_top_oil_rise_c = parse_f32(
+            find_value(obj, "transformer_top_oil_rise_c"),
+            "$.transformer_top_oil_rise_c",
+            55.0,
+        )?;
+        if transformer_top_oil_rise_c <= 0.0 {
+            return Err("at `$.transformer_top_oil_rise_c`: must be > 0".to_string());
+        }
+        let transformer_hot_spot_rise_c = parse_f32(
+            find_value(obj, "transformer_hot_spot_rise_c"),
+            "$.transformer_hot_spot_rise_c",
+            25.0,
+        )?;
+        if transformer_hot_spot_rise_c <= 0.0 {
+            return Err("at `$.transformer_hot_spot_rise_c`: must be > 0".to_string());
+        }
+        let transformer_loss_ratio = parse_f32(
+            find_value(obj, "transformer_loss_ratio"),
+            "$.transformer_loss_ratio",
+            5.0,
+        )?;
+        if transformer_loss_ratio < 0.0 {
+            return Err("at `$.transformer_loss_ratio`: must be >= 0".to_string());
+        }
+        let transformer_oil_time_constant_hr = parse_f32(
+            find_value(obj, "transformer_oil_time_constant_hr"),
+            "$.transformer_oil_time_constant_hr",
+            3.0,
+        )?;
+        if transformer_oil_time_constant_hr <= 0.0 {
+            return Err("at `$.transformer_oil_time_constant_hr`: must be > 0".to_string());
+        }
+        let transformer_winding_time_constant_hr = parse_f32(
+            find_value(obj, "transformer_winding_time_constant_hr"),
+            "$.transformer_winding_time_constant_hr",
+            0.1,
+        )?;
+        if transformer_winding_time_constant_hr <= 0.0 {
+            return Err("at `$.transformer_winding_time_constant_hr`: must be > 0".to_string());
+        }
+        let envelope = find_value(obj, "envelope_csv")
+            .map(|path| load_envelope_csv(path, "$.envelope_csv"))
+            .transpose()?
+            .unwrap_or_default();
+        let envelope_hosting_capacity_kw = find_value(obj, "envelope_hosting_capacity_kw")
+            .map(|value| parse_f32(Some(value), "$.envelope_hosting_capacity_kw", 0.0))
+            .transpose()?;
+        if envelope_hosting_capacity_kw.is_some_and(|kw| kw <= 0.0) {
+            return Err("at `$.envelope_hosting_capacity_kw`: must be > 0".to_string());
+        }
+        let envelope_background_houses = parse_u32(
+            find_value(obj, "envelope_background_houses"),
+            "$.envelope_background_houses",
+            0,
+        )?;
 
         Ok(Self {
             houses,
             feeder_kw,
             seed,
             steps_per_day,
+            days,
+            slow_update_steps,
+            telemetry_interval_steps,
             solar_kw_peak_per_house,
             dr_start_step,
             dr_end_step,
             dr_reduction_kw_per_house,
+            dr_notice_steps,
+            dr_pre_event_prep,
+            dr_rebound_fraction,
+            dr_rebound_steps,
+            dr_rebound_shape,
+            dr_ev,
+            dr_ev_hub,
+            dr_baseload,
+            dr_battery,
+            dr_hvac,
+            dr_events,
+            ev_policy,
+            ev_hub_ports,
+            ev_hub_port_kw,
+            ev_hub_site_cap_kw,
+            ev_hub_arrivals_per_day,
+            ev_hub_arrival_mean_step,
+            ev_hub_arrival_std_steps,
+            ev_hub_demand_kwh_min,
+            ev_hub_demand_kwh_max,
+            ev_hub_dwell_steps_min,
+            ev_hub_dwell_steps_max,
+            ev_hub_policy,
+            ev_hub_priority_share,
+            battery_degradation,
+            battery_cycle_life,
+            battery_dod_exponent,
+            battery_eol_fade,
+            battery_calendar_fade_per_year,
+            battery_resistance_growth,
+            battery_soc_min,
+            battery_soc_max,
+            battery_reserve_soc,
+            battery_self_discharge_per_day,
+            battery_aux_kw_per_house,
+            battery_thermal,
+            ambient_temp_mean_c,
+            ambient_temp_amplitude_c,
+            ambient_temp_day_std_c,
+            hvac_kw_per_house,
+            hvac_setpoint_c,
+            hvac_precool_c,
+            hvac_max_setback_c,
+            battery_ramp_kw_per_min_per_house,
+            battery_latency_steps,
+            battery_min_on_steps,
+            battery_min_off_steps,
+            ev_ramp_kw_per_min_per_house,
+            ev_hub_ramp_kw_per_min,
+            ev_latency_steps,
+            ev_min_on_steps,
+            ev_min_off_steps,
+            inverter_mode,
+            inverter_volt_watt,
+            solar_inverter_kva_ratio,
+            battery_inverter_kva_ratio,
+            baseload_power_factor,
+            feeder_r_pu,
+            feeder_x_pu,
+            solar_curtailment,
+            wind_rated_kw,
+            wind_cut_in_ms,
+            wind_rated_ms,
+            wind_cut_out_ms,
+            wind_weibull_shape,
+            wind_weibull_scale_ms,
+            wind_autocorrelation,
+            wind_speed_series,
+            genset_rated_kw,
+            genset_min_load_fraction,
+            genset_startup_steps,
+            genset_idle_fuel_l_per_h_per_kw,
+            genset_fuel_l_per_kwh,
+            genset_tank_l,
+            genset_co2_kg_per_l,
+            genset_nox_g_per_kwh,
+            outage_windows,
+            outage_rate_per_day,
+            outage_mean_duration_steps,
+            settlement_baseline,
+            settlement_adjustment_steps,
+            settlement_adjustment_cap,
+            settlement_price_per_kwh,
+            settlement_penalty_per_kwh,
+            settlement_min_performance,
+            regulation_kw_per_house,
+            regulation_signal,
+            regulation_signal_series,
+            regulation_response_delay_s,
+            regulation_capacity_price,
+            regulation_performance_price,
+            frequency_nominal_hz,
+            frequency_events,
+            frequency_series,
+            frequency_droop_response,
+            frequency_droop,
+            frequency_deadband_hz,
+            baseload_phase,
+            solar_phase,
+            wind_phase,
+            battery_phase,
+            genset_phase,
+            ev_phase,
+            ev_hub_phase,
+            transformer_kva,
+            transformer_top_oil_rise_c,
+            transformer_hot_spot_rise_c,
+            transformer_loss_ratio,
+            transformer_oil_time_constant_hr,
+            transformer_winding_time_constant_hr,
+            envelope,
+            envelope_hosting_capacity_kw,
+            envelope_background_houses,
+            topology,
+            import_limit_kw_by_step: Vec::new(),
+            export_limit_kw_by_step: Vec::new(),
         })
     }
 }
@@ -206,24 +1598,580 @@ fn parse_f32(value: Option<&str>, path: &str, default: f32) -> Result<f32, Strin
     Ok(n as f32)
 }
 
+fn parse_bool(value: Option<&str>, path: &str, default: bool) -> Result<bool, String> {
+    let Some(v) = value else {
+        return Ok(default);
+    };
+    v.parse::<bool>()
+        .map_err(|_| format!("at `{path}`: expected boolean"))
+}
+
+fn parse_ev_policy(value: Option<&str>, path: &str) -> Result<EvChargingPolicy, String> {
+    let Some(v) = value else {
+        return Ok(EvChargingPolicy::default());
+    };
+    EvChargingPolicy::from_name(v).ok_or_else(|| {
+        format!(
+            "at `{path}`: unknown policy `{v}` (expected `proportional`, `edf`, `llf`, `fair_share` or `priority`)"
+        )
+    })
+}
+
+fn parse_phase(value: Option<&str>, path: &str) -> Result<Phase, String> {
+    let Some(v) = value else {
+        return Ok(Phase::Three);
+    };
+    Phase::from_name(v).ok_or_else(|| {
+        format!("at `{path}`: unknown phase `{v}` (expected `a`, `b`, `c` or `abc`)")
+    })
+}
+
+fn parse_inverter_mode(
+    value: Option<&str>,
+    path: &str,
+    power_factor: f32,
+    absorbing: bool,
+) -> Result<ReactivePowerMode, String> {
+    let Some(v) = value else {
+        return Ok(ReactivePowerMode::default());
+    };
+    ReactivePowerMode::from_name(v, power_factor, absorbing).ok_or_else(|| {
+        format!("at `{path}`: unknown mode `{v}` (expected `unity`, `fixed_pf` or `volt_var`)")
+    })
+}
+
+fn parse_settlement_baseline(
+    value: Option<&str>,
+    path: &str,
+    x: usize,
+    y: usize,
+) -> Result<Option<BaselineMethod>, String> {
+    match value {
+        None | Some("none") => Ok(None),
+        Some(v) => BaselineMethod::from_name(v, x, y).map(Some).ok_or_else(|| {
+            format!(
+                "at `{path}`: unknown baseline `{v}` (expected `none`, `high_x_of_y` or `weather_matched`)"
+            )
+        }),
+    }
+}
+
+/// Parses the `dr_<device>_*` keys of one device's demand response capability.
+fn parse_dr_capability(
+    obj: &[(String, String)],
+    device: &str,
+    default_cost_per_kwh: f32,
+) -> Result<DrCapability, String> {
+    let key = |field: &str| format!("dr_{device}_{field}");
+    let path = |field: &str| format!("$.dr_{device}_{field}");
+
+    let curtailable = parse_bool(
+        find_value(obj, &key("curtailable")),
+        &path("curtailable"),
+        true,
+    )?;
+    let max_shed_kw = find_value(obj, &key("max_shed_kw"))
+        .map(|v| parse_f32(Some(v), &path("max_shed_kw"), 0.0))
+        .transpose()?;
+    let max_shed_steps = find_value(obj, &key("max_shed_steps"))
+        .map(|v| parse_usize(Some(v), &path("max_shed_steps"), 0))
+        .transpose()?;
+    let cost_per_kwh = parse_f32(
+        find_value(obj, &key("cost_per_kwh")),
+        &path("cost_per_kwh"),
+        default_cost_per_kwh,
+    )?;
+
+    if max_shed_kw.is_some_and(|kw| kw < 0.0) {
+        return Err(format!("at `{}`: must be >= 0", path("max_shed_kw")));
+    }
+    if max_shed_steps == Some(0) {
+        return Err(format!("at `{}`: must be > 0", path("max_shed_steps")));
+    }
+    if !cost_per_kwh.is_finite() {
+        return Err(format!("at `{}`: must be finite", path("cost_per_kwh")));
+    }
+    Ok(DrCapability {
+        curtailable,
+        max_shed_kw,
+        max_shed_steps,
+        cost_per_kwh,
+    })
+}
+
+/// Parses an event notice, `"day_ahead"` or minutes ahead of the start, into
+/// whole steps (rounded up).
+fn parse_dr_notice(value: Option<&str>, path: &str, steps_per_day: usize) -> Result<usize, String> {
+    match value {
+        None => Ok(0),
+        Some("day_ahead") => Ok(steps_per_day),
+        Some(v) => {
+            let minutes = v
+                .parse::<f32>()
+                .map_err(|_| format!("at `{path}`: expected minutes or `day_ahead`"))?;
+            if minutes.is_nan() || minutes < 0.0 {
+                return Err(format!("at `{path}`: must be >= 0"));
+            }
+            let step_minutes = 1440.0 / steps_per_day as f32;
+            Ok((minutes / step_minutes).ceil() as usize)
+        }
+    }
+}
+
+/// Parses `dr_events[i].field` pairs into events, validated against the run length.
+fn parse_dr_events(
+    obj: &[(String, String)],
+    steps_per_day: usize,
+    days: usize,
+) -> Result<Vec<DrEventSpec>, String> {
+    let count = table_array_len(obj, "dr_events", &DR_EVENT_FIELDS)?;
+    let mut specs: Vec<DrEventSpec> = Vec::with_capacity(count);
+    for idx in 0..count {
+        let path = |field: &str| format!("$.dr_events[{idx}].{field}");
+        let value = |field: &str| find_value(obj, &format!("dr_events[{idx}].{field}"));
+
+        let kind = match value("kind") {
+            None => DemandResponseKind::Shed,
+            Some(name) => DemandResponseKind::from_name(name).ok_or_else(|| {
+                format!(
+                    "at `{}`: unknown kind `{name}` (expected `shed`, `take` or `target`)",
+                    path("kind")
+                )
+            })?,
+        };
+        let day = value("day")
+            .map(|v| parse_usize(Some(v), &path("day"), 0))
+            .transpose()?;
+        let start_step = value("start_step")
+            .ok_or_else(|| format!("at `{}`: required", path("start_step")))
+            .and_then(|v| parse_usize(Some(v), &path("start_step"), 0))?;
+        let end_step = value("end_step")
+            .ok_or_else(|| format!("at `{}`: required", path("end_step")))
+            .and_then(|v| parse_usize(Some(v), &path("end_step"), 0))?;
+        let kw_per_house = parse_f32(value("kw_per_house"), &path("kw_per_house"), 0.0)?;
+        let ramp_in_steps = parse_usize(value("ramp_in_steps"), &path("ramp_in_steps"), 0)?;
+        let ramp_out_steps = parse_usize(value("ramp_out_steps"), &path("ramp_out_steps"), 0)?;
+        let notice_steps = parse_dr_notice(value("notice"), &path("notice"), steps_per_day)?;
+        let profile = value("profile_kw_per_house")
+            .map(|raw| {
+                raw.split(',')
+                    .map(|v| parse_f32(Some(v.trim()), &path("profile_kw_per_house"), 0.0))
+                    .collect::<Result<Vec<f32>, String>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        if day.is_some_and(|day| day >= days) {
+            return Err(format!("at `{}`: must be < days", path("day")));
+        }
+        if start_step >= end_step {
+            return Err(format!("at `{}`: must be < end_step", path("start_step")));
+        }
+        if end_step > steps_per_day {
+            return Err(format!(
+                "at `{}`: must be <= steps_per_day",
+                path("end_step")
+            ));
+        }
+        if kw_per_house < 0.0 {
+            return Err(format!("at `{}`: must be >= 0", path("kw_per_house")));
+        }
+        if ramp_in_steps + ramp_out_steps > end_step - start_step {
+            return Err(format!(
+                "at `{}`: ramps must fit inside the event window",
+                path("ramp_out_steps")
+            ));
+        }
+        if kind == DemandResponseKind::TargetLevel && ramp_in_steps + ramp_out_steps > 0 {
+            return Err(format!(
+                "at `{}`: target-level events cannot ramp",
+                path("ramp_in_steps")
+            ));
+        }
+        if !profile.is_empty() && profile.len() != end_step - start_step {
+            return Err(format!(
+                "at `{}`: must have one value per event step",
+                path("profile_kw_per_house")
+            ));
+        }
+        if profile.iter().any(|kw| *kw < 0.0) {
+            return Err(format!(
+                "at `{}`: must be >= 0",
+                path("profile_kw_per_house")
+            ));
+        }
+
+        let overlaps = specs.iter().any(|other| {
+            let same_day = day.is_none() || other.day.is_none() || day == other.day;
+            same_day && start_step < other.event.end_step && other.event.start_step < end_step
+        });
+        if overlaps {
+            return Err(format!(
+                "at `$.dr_events[{idx}]`: overlaps an earlier event"
+            ));
+        }
+
+        let mut event = DemandResponseEvent::new(start_step, end_step, kw_per_house)
+            .with_kind(kind)
+            .with_ramp(ramp_in_steps, ramp_out_steps)
+            .with_notice(notice_steps);
+        if !profile.is_empty() {
+            event = event.with_profile(profile);
+        }
+        specs.push(DrEventSpec { day, event });
+    }
+    Ok(specs)
+}
+
+/// Number of `[[name]]` tables flattened into `name[i].field` pairs,
+/// rejecting fields not in `fields`.
+fn table_array_len(obj: &[(String, String)], name: &str, fields: &[&str]) -> Result<usize, String> {
+    let prefix = format!("{name}[");
+    let mut count = 0;
+    for (key, _) in obj {
+        let Some(rest) = key.strip_prefix(&prefix) else {
+            continue;
+        };
+        let parsed = rest
+            .split_once("].")
+            .and_then(|(idx, field)| Some((idx.parse::<usize>().ok()?, field)));
+        match parsed {
+            Some((idx, field)) if fields.contains(&field) => count = count.max(idx + 1),
+            _ => return Err(format!("at `$.{key}`: unknown key")),
+        }
+    }
+    Ok(count)
+}
+
+/// Import limit at `import_path` (required unless `default_import_kw` is
+/// given) and export limit at `export_path`, which defaults to 80% of import
+/// like the single-site feeder.
+fn parse_node_limits(
+    obj: &[(String, String)],
+    import_path: &str,
+    export_path: &str,
+    default_import_kw: Option<f32>,
+) -> Result<NodeLimits, String> {
+    let key = |path: &str| path.trim_start_matches("$.").to_string();
+    let import_kw = match (find_value(obj, &key(import_path)), default_import_kw) {
+        (Some(v), _) => parse_f32(Some(v), import_path, 0.0)?,
+        (None, Some(kw)) => kw,
+        (None, None) => return Err(format!("at `{import_path}`: required")),
+    };
+    if import_kw <= 0.0 {
+        return Err(format!("at `{import_path}`: must be > 0"));
+    }
+    let export_kw = parse_f32(
+        find_value(obj, &key(export_path)),
+        export_path,
+        import_kw * 0.8,
+    )?;
+    if export_kw < 0.0 {
+        return Err(format!("at `{export_path}`: must be >= 0"));
+    }
+    Ok(NodeLimits::new(import_kw, export_kw))
+}
+
+/// Parses `[[sites]]`, `[[feeders]]` and the substation limits; sites take
+/// the scenario's `houses`, `feeder_kw` and `solar_kw_peak_per_house` unless
+/// they set their own.
+fn parse_topology(
+    obj: &[(String, String)],
+    houses: u32,
+    feeder_kw: f32,
+    solar_kw_peak_per_house: f32,
+) -> Result<Option<Topology>, String> {
+    let site_count = table_array_len(obj, "sites", &SITE_FIELDS)?;
+    let feeder_count = table_array_len(obj, "feeders", &FEEDER_FIELDS)?;
+    let line_count = table_array_len(obj, "lines", &LINE_FIELDS)?;
+    if site_count == 0 {
+        if feeder_count > 0 {
+            return Err("at `$.feeders`: requires `sites`".to_string());
+        }
+        if line_count > 0 {
+            return Err("at `$.lines`: requires `sites`".to_string());
+        }
+        for key in ["substation_import_kw", "substation_export_kw"]
+            .into_iter()
+            .chain(NETWORK_KEYS)
+        {
+            if find_value(obj, key).is_some() {
+                return Err(format!("at `$.{key}`: requires `sites`"));
+            }
+        }
+        return Ok(None);
+    }
+    if feeder_count == 0 {
+        return Err("at `$.feeders`: required with `sites`".to_string());
+    }
+
+    let mut feeders: Vec<FeederSpec> = Vec::with_capacity(feeder_count);
+    for idx in 0..feeder_count {
+        let name = find_value(obj, &format!("feeders[{idx}].name"))
+            .ok_or_else(|| format!("at `$.feeders[{idx}].name`: required"))?;
+        if feeders.iter().any(|feeder| feeder.name == name) {
+            return Err(format!("at `$.feeders[{idx}].name`: duplicate `{name}`"));
+        }
+        let limits = parse_node_limits(
+            obj,
+            &format!("$.feeders[{idx}].import_kw"),
+            &format!("$.feeders[{idx}].export_kw"),
+            None,
+        )?;
+        feeders.push(FeederSpec {
+            name: name.to_string(),
+            limits,
+        });
+    }
+
+    let network = parse_network(obj, line_count)?;
+    let voltage_min_pu = parse_f32(
+        find_value(obj, "network_voltage_min_pu"),
+        "$.network_voltage_min_pu",
+        0.95,
+    )?;
+    let voltage_max_pu = parse_f32(
+        find_value(obj, "network_voltage_max_pu"),
+        "$.network_voltage_max_pu",
+        1.05,
+    )?;
+    if !(0.0..1.0).contains(&voltage_min_pu) {
+        return Err("at `$.network_voltage_min_pu`: must be in [0, 1)".to_string());
+    }
+    if voltage_max_pu <= 1.0 {
+        return Err("at `$.network_voltage_max_pu`: must be > 1".to_string());
+    }
+
+    let mut sites: Vec<SiteSpec> = Vec::with_capacity(site_count);
+    for idx in 0..site_count {
+        let path = |field: &str| format!("$.sites[{idx}].{field}");
+        let value = |field: &str| find_value(obj, &format!("sites[{idx}].{field}"));
+
+        let name = value("name").map_or_else(|| format!("site{idx}"), str::to_string);
+        if sites.iter().any(|site| site.name == name) {
+            return Err(format!("at `{}`: duplicate `{name}`", path("name")));
+        }
+        let feeder_name =
+            value("feeder").ok_or_else(|| format!("at `{}`: required", path("feeder")))?;
+        let feeder = feeders
+            .iter()
+            .position(|feeder| feeder.name == feeder_name)
+            .ok_or_else(|| format!("at `{}`: unknown feeder `{feeder_name}`", path("feeder")))?;
+        let bus = match (value("bus"), network.as_ref()) {
+            (Some(bus_name), Some(network)) => Some(
+                network
+                    .bus_names()
+                    .iter()
+                    .position(|name| name == bus_name)
+                    .ok_or_else(|| format!("at `{}`: unknown bus `{bus_name}`", path("bus")))?,
+            ),
+            (Some(_), None) => return Err(format!("at `{}`: requires `lines`", path("bus"))),
+            (None, Some(_)) => return Err(format!("at `{}`: required with `lines`", path("bus"))),
+            (None, None) => None,
+        };
+        let site_houses = parse_u32(value("houses"), &path("houses"), houses)?;
+        if site_houses == 0 {
+            return Err(format!("at `{}`: must be > 0", path("houses")));
+        }
+        let limits =
+            parse_node_limits(obj, &path("import_kw"), &path("export_kw"), Some(feeder_kw))?;
+        let site_solar_kw = parse_f32(
+            value("solar_kw_peak_per_house"),
+            &path("solar_kw_peak_per_house"),
+            solar_kw_peak_per_house,
+        )?;
+        if site_solar_kw < 0.0 {
+            return Err(format!(
+                "at `{}`: must be >= 0",
+                path("solar_kw_peak_per_house")
+            ));
+        }
+        sites.push(SiteSpec {
+            name,
+            feeder,
+            bus,
+            houses: site_houses,
+            limits,
+            solar_kw_peak_per_house: site_solar_kw,
+        });
+    }
+
+    let substation = if find_value(obj, "substation_import_kw").is_some() {
+        parse_node_limits(
+            obj,
+            "$.substation_import_kw",
+            "$.substation_export_kw",
+            None,
+        )?
+    } else if find_value(obj, "substation_export_kw").is_some() {
+        parse_node_limits(
+            obj,
+            "$.substation_import_kw",
+            "$.substation_export_kw",
+            Some(f32::INFINITY),
+        )?
+    } else {
+        NodeLimits::unlimited()
+    };
+
+    Ok(Some(Topology {
+        sites,
+        feeders,
+        substation,
+        network,
+        voltage_min_pu,
+        voltage_max_pu,
+    }))
+}
+
+/// Parses `[[lines]]` into a radial network fed from the `substation` bus;
+/// buses are named by the lines' `from` and `to` ends.
+fn parse_network(
+    obj: &[(String, String)],
+    line_count: usize,
+) -> Result<Option<RadialNetwork>, String> {
+    if line_count == 0 {
+        for key in NETWORK_KEYS {
+            if find_value(obj, key).is_some() {
+                return Err(format!("at `$.{key}`: requires `lines`"));
+            }
+        }
+        return Ok(None);
+    }
+    let base_kv = parse_f32(
+        find_value(obj, "network_base_kv"),
+        "$.network_base_kv",
+        12.47,
+    )?;
+    if base_kv <= 0.0 {
+        return Err("at `$.network_base_kv`: must be > 0".to_string());
+    }
+
+    let mut bus_names = vec![ROOT_BUS.to_string()];
+    let mut bus_index = |name: &str| match bus_names.iter().position(|bus| bus == name) {
+        Some(idx) => idx,
+        None => {
+            bus_names.push(name.to_string());
+            bus_names.len() - 1
+        }
+    };
+    let mut lines: Vec<Line> = Vec::with_capacity(line_count);
+    for idx in 0..line_count {
+        let path = |field: &str| format!("$.lines[{idx}].{field}");
+        let value = |field: &str| find_value(obj, &format!("lines[{idx}].{field}"));
+
+        let name = value("name").map_or_else(|| format!("line{idx}"), str::to_string);
+        if lines.iter().any(|line| line.name == name) {
+            return Err(format!("at `{}`: duplicate `{name}`", path("name")));
+        }
+        let from = value("from").ok_or_else(|| format!("at `{}`: required", path("from")))?;
+        let to = value("to").ok_or_else(|| format!("at `{}`: required", path("to")))?;
+        let r_ohm = value("r_ohm")
+            .ok_or_else(|| format!("at `{}`: required", path("r_ohm")))
+            .and_then(|v| parse_f32(Some(v), &path("r_ohm"), 0.0))?;
+        let x_ohm = parse_f32(value("x_ohm"), &path("x_ohm"), 0.0)?;
+        let ampacity_a = value("ampacity_a")
+            .map(|v| parse_f32(Some(v), &path("ampacity_a"), 0.0))
+            .transpose()?;
+        if r_ohm < 0.0 {
+            return Err(format!("at `{}`: must be >= 0", path("r_ohm")));
+        }
+        if x_ohm < 0.0 {
+            return Err(format!("at `{}`: must be >= 0", path("x_ohm")));
+        }
+        if ampacity_a.is_some_and(|a| a <= 0.0) {
+            return Err(format!("at `{}`: must be > 0", path("ampacity_a")));
+        }
+        if from == to {
+            return Err(format!("at `{}`: must differ from `from`", path("to")));
+        }
+        lines.push(Line {
+            name,
+            from: bus_index(from),
+            to: bus_index(to),
+            r_ohm,
+            x_ohm,
+            ampacity_a,
+        });
+    }
+
+    RadialNetwork::new(base_kv, bus_names, lines)
+        .map(Some)
+        .map_err(|err| format!("at `$.lines`: {err}"))
+}
+
+fn load_wind_speed_csv(path: &str, key_path: &str) -> Result<Vec<f32>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("at `{key_path}`: failed to read `{path}`: {err}"))?;
+    parse_wind_speed_csv(&raw).map_err(|err| format!("at `{key_path}`: `{path}` {err}"))
+}
+
+fn load_regulation_signal_csv(path: &str, key_path: &str) -> Result<Vec<f32>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("at `{key_path}`: failed to read `{path}`: {err}"))?;
+    parse_regulation_signal_csv(&raw).map_err(|err| format!("at `{key_path}`: `{path}` {err}"))
+}
+
+fn load_frequency_csv(path: &str, key_path: &str) -> Result<Vec<f32>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("at `{key_path}`: failed to read `{path}`: {err}"))?;
+    parse_frequency_csv(&raw).map_err(|err| format!("at `{key_path}`: `{path}` {err}"))
+}
+
+fn load_envelope_csv(path: &str, key_path: &str) -> Result<OperatingEnvelope, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|err| format!("at `{key_path}`: failed to read `{path}`: {err}"))?;
+    parse_envelope_csv(&raw)
+        .map(OperatingEnvelope::new)
+        .map_err(|err| format!("at `{key_path}`: `{path}` {err}"))
+}
+
 fn parse_flat_toml_table(raw: &str) -> Result<Vec<(String, String)>, String> {
     let table: toml::Table =
         toml::from_str(raw).map_err(|err| format!("failed to parse TOML: {err}"))?;
 
     let mut pairs = Vec::with_capacity(table.len());
     for (key, value) in &table {
-        let as_string = toml_value_to_numeric_string(value, key)?;
-        pairs.push((key.clone(), as_string));
+        match value {
+            // Arrays of tables are flattened into `key[i].field` pairs.
+            toml::Value::Array(items)
+                if !items.is_empty() && items.iter().all(|v| v.is_table()) =>
+            {
+                for (idx, item) in items.iter().enumerate() {
+                    for (field, value) in item.as_table().into_iter().flatten() {
+                        let item_key = format!("{key}[{idx}].{field}");
+                        let as_string = toml_value_to_scalar_string(value, &item_key)?;
+                        pairs.push((item_key, as_string));
+                    }
+                }
+            }
+            _ => {
+                let as_string = toml_value_to_scalar_string(value, key)?;
+                pairs.push((key.clone(), as_string));
+            }
+        }
     }
     Ok(pairs)
 }
 
-fn toml_value_to_numeric_string(value: &toml::Value, key: &str) -> Result<String, String> {
+fn toml_value_to_scalar_string(value: &toml::Value, key: &str) -> Result<String, String> {
     match value {
         toml::Value::Integer(n) => Ok(n.to_string()),
         toml::Value::Float(n) => Ok(n.to_string()),
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        // Arrays of numbers become comma-separated lists.
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                toml::Value::Integer(n) => Ok(n.to_string()),
+                toml::Value::Float(n) => Ok(n.to_string()),
+                _ => Err(format!("at `$.{key}`: expected array of numbers")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| items.join(",")),
         _ => Err(format!(
-            "at `$.{key}`: expected numeric value (integer or float)"
+            "at `$.{key}`: expected numeric, boolean or string value"
         )),
     }
 }
@@ -231,6 +2179,12 @@ fn toml_value_to_numeric_string(value: &toml::Value, key: &str) -> Result<String
 #[cfg(test)]
 mod tests {
     use super::{ScenarioConfig, parse_flat_toml_table};
+    use crate::devices::{EvChargingPolicy, ReactivePowerMode};
+    use crate::settlement::BaselineMethod;
+    use crate::sim::event::DemandResponseKind;
+    use crate::sim::feeder::Phase;
+    use crate::sim::regulation::RegulationSignalKind;
+    use crate::sim::topology::NodeLimits;
     use std::path::Path;
 
     #[test]
@@ -255,6 +2209,498 @@ mod tests {
         assert!(pairs.iter().any(|(k, v)| k == "feeder_kw" && v == "10.5"));
     }
 
+    #[test]
+    fn ev_hub_site_cap_is_optional_and_positive() {
+        let value = vec![
+            ("ev_hub_ports".to_string(), "4".to_string()),
+            ("ev_hub_port_kw".to_string(), "7.5".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("hub config should parse");
+        assert_eq!(cfg.ev_hub_site_cap_kw, None);
+
+        let value = vec![
+            ("ev_hub_ports".to_string(), "4".to_string()),
+            ("ev_hub_site_cap_kw".to_string(), "0".to_string()),
+        ];
+        assert!(ScenarioConfig::from_kv_pairs(&value).is_err());
+    }
+
+    #[test]
+    fn parses_ev_policies_by_name() {
+        let pairs =
+            parse_flat_toml_table("ev_policy = \"edf\"\nev_hub_ports = 2\nev_hub_policy = \"llf\"")
+                .expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("policy should parse");
+        assert_eq!(cfg.ev_policy, EvChargingPolicy::EarliestDeadlineFirst);
+        assert_eq!(cfg.ev_hub_policy, EvChargingPolicy::LeastLaxityFirst);
+
+        let value = vec![("ev_hub_policy".to_string(), "random".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.ev_hub_policy"));
+    }
+
+    #[test]
+    fn parses_boolean_battery_degradation_flag() {
+        let pairs =
+            parse_flat_toml_table("battery_degradation = true\ndays = 30").expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("flag should parse");
+        assert!(cfg.battery_degradation);
+        assert_eq!(cfg.days, 30);
+    }
+
+    #[test]
+    fn battery_reserve_must_lie_inside_soc_window() {
+        let value = vec![
+            ("battery_soc_min".to_string(), "0.2".to_string()),
+            ("battery_reserve_soc".to_string(), "0.1".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.battery_reserve_soc"));
+    }
+
+    #[test]
+    fn rejects_negative_ramp_rate() {
+        let value = vec![("ev_hub_ramp_kw_per_min".to_string(), "-1".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.ev_hub_ramp_kw_per_min"));
+    }
+
+    #[test]
+    fn parses_hvac_precooling() {
+        let pairs = parse_flat_toml_table(
+            "hvac_kw_per_house = 3.0\nhvac_precool_c = 1.5\ndr_hvac_max_shed_steps = 2",
+        )
+        .expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("hvac should parse");
+        assert_eq!(cfg.hvac_kw_per_house, 3.0);
+        assert_eq!(cfg.hvac_precool_c, 1.5);
+        assert_eq!(cfg.hvac_setpoint_c, 24.0);
+        assert_eq!(cfg.dr_hvac.max_shed_steps, Some(2));
+
+        let value = vec![("hvac_setpoint_c".to_string(), "50".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.hvac_setpoint_c"));
+    }
+
+    #[test]
+    fn parses_fixed_power_factor_inverter_mode() {
+        let pairs = parse_flat_toml_table(
+            "inverter_mode = \"fixed_pf\"\ninverter_power_factor = 0.9\ninverter_pf_absorbing = false",
+        )
+        .expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("mode should parse");
+        assert_eq!(
+            cfg.inverter_mode,
+            ReactivePowerMode::FixedPowerFactor {
+                power_factor: 0.9,
+                absorbing: false
+            }
+        );
+
+        let value = vec![("inverter_power_factor".to_string(), "1.2".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.inverter_power_factor"));
+    }
+
+    #[test]
+    fn wind_curve_speeds_must_increase() {
+        let value = vec![
+            ("wind_rated_kw".to_string(), "10".to_string()),
+            ("wind_rated_ms".to_string(), "30".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.wind_cut_out_ms"));
+    }
+
+    #[test]
+    fn missing_wind_speed_csv_reports_key() {
+        let value = vec![(
+            "wind_speed_csv".to_string(),
+            "does/not/exist.csv".to_string(),
+        )];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.wind_speed_csv"));
+    }
+
+    #[test]
+    fn genset_tank_is_optional_and_validated() {
+        let pairs = parse_flat_toml_table("genset_rated_kw = 50").expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("genset should parse");
+        assert_eq!(cfg.genset_rated_kw, 50.0);
+        assert_eq!(cfg.genset_tank_l, None);
+
+        let value = vec![("genset_tank_l".to_string(), "-5".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.genset_tank_l"));
+    }
+
+    #[test]
+    fn outage_windows_must_end_inside_run() {
+        let pairs = parse_flat_toml_table("days = 2\noutage_windows = \"10-14, 30-34\"")
+            .expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("outages should parse");
+        assert_eq!(cfg.outage_windows.len(), 2);
+
+        let value = vec![("outage_windows".to_string(), "20-30".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.outage_windows"));
+    }
+
+    #[test]
+    fn parses_dr_event_tables() {
+        let raw = r#"
+days = 2
+
+[[dr_events]]
+kind = "take"
+day = 1
+start_step = 10
+end_step = 13
+kw_per_house = 2.0
+ramp_in_steps = 1
+
+[[dr_events]]
+start_step = 17
+end_step = 20
+profile_kw_per_house = [1.0, 1.5, 1]
+"#;
+        let pairs = parse_flat_toml_table(raw).expect("toml parse");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("events should parse");
+        assert_eq!(cfg.dr_events.len(), 2);
+        assert_eq!(cfg.dr_events[0].day, Some(1));
+        assert_eq!(cfg.dr_events[0].event.kind, DemandResponseKind::Take);
+        assert_eq!(cfg.dr_events[0].event.ramp_in_steps, 1);
+        assert_eq!(cfg.dr_events[1].day, None);
+        assert_eq!(cfg.dr_events[1].event.profile, vec![1.0, 1.5, 1.0]);
+    }
+
+    #[test]
+    fn dr_event_errors_report_indexed_path() {
+        let value = vec![
+            ("dr_events[0].start_step".to_string(), "17".to_string()),
+            ("dr_events[0].end_step".to_string(), "20".to_string()),
+            ("dr_events[1].start_step".to_string(), "19".to_string()),
+            ("dr_events[1].end_step".to_string(), "22".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_events[1]"));
+
+        let value = vec![
+            ("dr_events[0].start_step".to_string(), "1".to_string()),
+            ("dr_events[0].end_step".to_string(), "3".to_string()),
+            (
+                "dr_events[0].profile_kw_per_house".to_string(),
+                "1.0".to_string(),
+            ),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_events[0].profile_kw_per_house"));
+
+        let value = vec![("dr_events[0].colour".to_string(), "red".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_events[0].colour"));
+    }
+
+    #[test]
+    fn parses_dr_notice_as_steps() {
+        let value = vec![
+            ("steps_per_day".to_string(), "96".to_string()),
+            ("dr_start_step".to_string(), "68".to_string()),
+            ("dr_end_step".to_string(), "84".to_string()),
+            ("dr_notice".to_string(), "50".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("notice should parse");
+        // 50 minutes at 15-minute steps rounds up to 4 steps.
+        assert_eq!(cfg.dr_notice_steps, 4);
+
+        let value = vec![
+            ("dr_events[0].start_step".to_string(), "17".to_string()),
+            ("dr_events[0].end_step".to_string(), "20".to_string()),
+            ("dr_events[0].notice".to_string(), "day_ahead".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("day-ahead notice should parse");
+        assert_eq!(cfg.dr_events[0].event.notice_steps, 24);
+
+        let value = vec![("dr_notice".to_string(), "soon".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_notice"));
+    }
+
+    #[test]
+    fn parses_dr_device_capabilities() {
+        let value = vec![
+            ("dr_battery_curtailable".to_string(), "false".to_string()),
+            ("dr_baseload_max_shed_kw".to_string(), "0.5".to_string()),
+            ("dr_baseload_max_shed_steps".to_string(), "2".to_string()),
+            ("dr_ev_hub_cost_per_kwh".to_string(), "3.0".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("capabilities should parse");
+        assert!(!cfg.dr_battery.curtailable);
+        assert_eq!(cfg.dr_baseload.max_shed_kw, Some(0.5));
+        assert_eq!(cfg.dr_baseload.max_shed_steps, Some(2));
+        assert_eq!(cfg.dr_ev_hub.cost_per_kwh, 3.0);
+        assert_eq!(cfg.dr_ev.cost_per_kwh, 0.0);
+
+        let value = vec![("dr_ev_max_shed_steps".to_string(), "0".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_ev_max_shed_steps"));
+
+        let value = vec![("dr_pump_cost_per_kwh".to_string(), "1.0".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("must fail");
+        assert!(err.contains("$.dr_pump_cost_per_kwh"));
+    }
+
+    #[test]
+    fn parses_settlement_baseline() {
+        let value = vec![
+            (
+                "settlement_baseline".to_string(),
+                "weather_matched".to_string(),
+            ),
+            ("settlement_baseline_x".to_string(), "2".to_string()),
+            ("settlement_baseline_y".to_string(), "3".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("settlement should parse");
+        assert_eq!(
+            cfg.settlement_baseline,
+            Some(BaselineMethod::WeatherMatched { x: 2, y: 3 })
+        );
+        assert_eq!(ScenarioConfig::default().settlement_baseline, None);
+
+        let value = vec![
+            ("settlement_baseline".to_string(), "high_x_of_y".to_string()),
+            ("settlement_baseline_x".to_string(), "6".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("x > y must fail");
+        assert!(err.contains("$.settlement_baseline_x"));
+
+        let value = vec![("settlement_baseline".to_string(), "average".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("unknown baseline must fail");
+        assert!(err.contains("$.settlement_baseline"));
+    }
+
+    #[test]
+    fn parses_frequency_response_settings() {
+        let value = vec![
+            ("frequency_events".to_string(), "10-14:-0.4".to_string()),
+            ("frequency_droop_response".to_string(), "true".to_string()),
+            ("frequency_droop".to_string(), "0.01".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("frequency should parse");
+        assert_eq!(cfg.frequency_events.len(), 1);
+        assert!(cfg.frequency_droop_response);
+        assert_eq!(cfg.frequency_droop, 0.01);
+        assert_eq!(cfg.frequency_nominal_hz, 60.0);
+
+        let value = vec![("frequency_events".to_string(), "20-30:-0.4".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("event past run must fail");
+        assert!(err.contains("$.frequency_events"));
+
+        let value = vec![("frequency_droop".to_string(), "0".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("zero droop must fail");
+        assert!(err.contains("$.frequency_droop"));
+    }
+
+    #[test]
+    fn parses_topology_of_sites_feeders_and_substation() {
+        let raw = r#"
+houses = 2
+feeder_kw = 10.0
+substation_import_kw = 30.0
+
+[[feeders]]
+name = "F1"
+import_kw = 20.0
+export_kw = 5.0
+
+[[sites]]
+name = "depot"
+feeder = "F1"
+houses = 4
+import_kw = 12.0
+
+[[sites]]
+feeder = "F1"
+"#;
+        let pairs = parse_flat_toml_table(raw).expect("valid toml");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("topology should parse");
+        let topology = cfg.topology.expect("sites make a topology");
+        assert_eq!(topology.feeders[0].limits, NodeLimits::new(20.0, 5.0));
+        assert_eq!(topology.substation, NodeLimits::new(30.0, 24.0));
+        assert_eq!(topology.sites[0].houses, 4);
+        assert_eq!(topology.sites[0].limits, NodeLimits::new(12.0, 9.6));
+        // Sites default to the scenario's houses and feeder limit.
+        assert_eq!(topology.sites[1].name, "site1");
+        assert_eq!(topology.sites[1].houses, 2);
+        assert_eq!(topology.sites[1].limits, NodeLimits::new(10.0, 8.0));
+        assert!(ScenarioConfig::default().topology.is_none());
+
+        let value = vec![
+            ("feeders[0].name".to_string(), "F1".to_string()),
+            ("feeders[0].import_kw".to_string(), "20".to_string()),
+            ("sites[0].feeder".to_string(), "F2".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("unknown feeder must fail");
+        assert!(err.contains("$.sites[0].feeder"));
+
+        let value = vec![("substation_import_kw".to_string(), "30".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("substation needs sites");
+        assert!(err.contains("$.substation_import_kw"));
+    }
+
+    #[test]
+    fn parses_line_network_and_site_buses() {
+        let raw = r#"
+network_base_kv = 0.4
+network_voltage_max_pu = 1.1
+
+[[feeders]]
+name = "F1"
+import_kw = 20.0
+
+[[lines]]
+name = "lateral"
+from = "b1"
+to = "b2"
+r_ohm = 0.05
+
+[[lines]]
+from = "substation"
+to = "b1"
+r_ohm = 0.02
+x_ohm = 0.01
+ampacity_a = 200.0
+
+[[sites]]
+feeder = "F1"
+bus = "b2"
+"#;
+        let pairs = parse_flat_toml_table(raw).expect("valid toml");
+        let cfg = ScenarioConfig::from_kv_pairs(&pairs).expect("network should parse");
+        let topology = cfg.topology.expect("sites make a topology");
+        let network = topology.network.expect("lines make a network");
+        assert_eq!(network.bus_names(), ["substation", "b1", "b2"]);
+        assert_eq!(network.lines()[1].name, "line1");
+        assert_eq!(network.lines()[1].ampacity_a, Some(200.0));
+        assert_eq!(topology.sites[0].bus, Some(2));
+        assert_eq!(topology.voltage_min_pu, 0.95);
+        assert_eq!(topology.voltage_max_pu, 1.1);
+
+        let value = vec![
+            ("feeders[0].name".to_string(), "F1".to_string()),
+            ("feeders[0].import_kw".to_string(), "20".to_string()),
+            ("lines[0].from".to_string(), "b9".to_string()),
+            ("lines[0].to".to_string(), "b1".to_string()),
+            ("lines[0].r_ohm".to_string(), "0.1".to_string()),
+            ("sites[0].feeder".to_string(), "F1".to_string()),
+            ("sites[0].bus".to_string(), "b1".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("disconnected line must fail");
+        assert!(err.contains("$.lines"));
+        assert!(err.contains("`b9` is not connected"));
+
+        let value = vec![
+            ("feeders[0].name".to_string(), "F1".to_string()),
+            ("feeders[0].import_kw".to_string(), "20".to_string()),
+            ("sites[0].feeder".to_string(), "F1".to_string()),
+            ("sites[0].bus".to_string(), "b1".to_string()),
+        ];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("bus needs lines");
+        assert!(err.contains("$.sites[0].bus"));
+    }
+
+    #[test]
+    fn parses_device_phases() {
+        let value = vec![
+            ("ev_phase".to_string(), "a".to_string()),
+            ("solar_phase".to_string(), "c".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("phases should parse");
+        assert_eq!(cfg.ev_phase, Phase::A);
+        assert_eq!(cfg.solar_phase, Phase::C);
+        assert_eq!(cfg.baseload_phase, Phase::Three);
+
+        let value = vec![("battery_phase".to_string(), "ab".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("unknown phase must fail");
+        assert!(err.contains("$.battery_phase"));
+    }
+
+    #[test]
+    fn parses_transformer_settings() {
+        let value = vec![
+            ("transformer_kva".to_string(), "25".to_string()),
+            (
+                "transformer_oil_time_constant_hr".to_string(),
+                "4.5".to_string(),
+            ),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("transformer should parse");
+        assert_eq!(cfg.transformer_kva, 25.0);
+        assert_eq!(cfg.transformer_oil_time_constant_hr, 4.5);
+        assert_eq!(cfg.transformer_hot_spot_rise_c, 25.0);
+
+        let value = vec![(
+            "transformer_winding_time_constant_hr".to_string(),
+            "0".to_string(),
+        )];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("zero time constant must fail");
+        assert!(err.contains("$.transformer_winding_time_constant_hr"));
+    }
+
+    #[test]
+    fn parses_envelope_settings() {
+        let value = vec![
+            ("envelope_hosting_capacity_kw".to_string(), "8".to_string()),
+            ("envelope_background_houses".to_string(), "3".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("envelope should parse");
+        assert_eq!(cfg.envelope_hosting_capacity_kw, Some(8.0));
+        assert_eq!(cfg.envelope_background_houses, 3);
+        assert!(cfg.envelope.is_empty());
+
+        let value = vec![("envelope_csv".to_string(), "does/not/exist.csv".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("missing file must fail");
+        assert!(err.contains("$.envelope_csv"));
+    }
+
+    #[test]
+    fn parses_update_and_telemetry_intervals() {
+        let value = vec![
+            ("steps_per_day".to_string(), "86400".to_string()),
+            ("dr_start_step".to_string(), "61200".to_string()),
+            ("dr_end_step".to_string(), "75600".to_string()),
+            ("slow_update_steps".to_string(), "900".to_string()),
+            ("telemetry_interval_steps".to_string(), "60".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("intervals should parse");
+        assert_eq!(cfg.slow_update_steps, 900);
+        assert_eq!(cfg.telemetry_interval_steps, 60);
+
+        let value = vec![("telemetry_interval_steps".to_string(), "0".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("zero interval must fail");
+        assert!(err.contains("$.telemetry_interval_steps"));
+    }
+
+    #[test]
+    fn parses_regulation_settings() {
+        let value = vec![
+            ("regulation_kw_per_house".to_string(), "2".to_string()),
+            ("regulation_signal".to_string(), "reg_a".to_string()),
+        ];
+        let cfg = ScenarioConfig::from_kv_pairs(&value).expect("regulation should parse");
+        assert_eq!(cfg.regulation_kw_per_house, 2.0);
+        assert_eq!(cfg.regulation_signal, RegulationSignalKind::RegA);
+        assert_eq!(cfg.regulation_response_delay_s, 2);
+
+        let value = vec![("regulation_kw_per_house".to_string(), "6".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("above rating must fail");
+        assert!(err.contains("$.regulation_kw_per_house"));
+
+        let value = vec![("regulation_signal".to_string(), "reg_x".to_string())];
+        let err = ScenarioConfig::from_kv_pairs(&value).expect_err("unknown signal must fail");
+        assert!(err.contains("$.regulation_signal"));
+    }
+
     #[test]
     fn bare_filename_resolves_from_scenarios_dir() {
         let cfg = ScenarioConfig::from_path(Path::new("baseline.toml"))
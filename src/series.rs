@@ -0,0 +1,62 @@
+//! Measured input series read from CSV files, one value per row.
+
+/// Parses one value per row from CSV text, taking the last column of each
+/// row so both a bare list and `timestep,value` rows work.
+///
+/// A non-numeric first row is treated as a header and blank lines are
+/// skipped. Values failing `valid` are rejected with
+/// "`label` must be `requirement`".
+pub fn parse_last_column_csv(
+    raw: &str,
+    label: &str,
+    requirement: &str,
+    valid: impl Fn(f32) -> bool,
+) -> Result<Vec<f32>, String> {
+    let mut values = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let field = line.rsplit(',').next().unwrap_or(line).trim();
+        match field.parse::<f32>() {
+            Ok(value) if valid(value) => values.push(value),
+            Ok(_) => return Err(format!("line {}: {label} must be {requirement}", idx + 1)),
+            Err(_) if values.is_empty() && idx == 0 => {}
+            Err(_) => return Err(format!("line {}: invalid {label} `{field}`", idx + 1)),
+        }
+    }
+    if values.is_empty() {
+        return Err(format!("no {label} values found"));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_last_column_skipping_header_and_blank_lines() {
+        let positive = |value: f32| value > 0.0;
+        let values = parse_last_column_csv(
+            "timestep,value\n0,1.5\n\n1, 2\n",
+            "reading",
+            "> 0",
+            positive,
+        );
+        assert_eq!(values, Ok(vec![1.5, 2.0]));
+        assert_eq!(
+            parse_last_column_csv("1\n0\n", "reading", "> 0", positive),
+            Err("line 2: reading must be > 0".to_string())
+        );
+        assert_eq!(
+            parse_last_column_csv("1\nx\n", "reading", "> 0", positive),
+            Err("line 2: invalid reading `x`".to_string())
+        );
+        assert_eq!(
+            parse_last_column_csv("value\n", "reading", "> 0", positive),
+            Err("no reading values found".to_string())
+        );
+    }
+}
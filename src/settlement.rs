@@ -0,0 +1,325 @@
+//! Demand response customer baselines and event settlement.
+
+use crate::sim::event::DemandResponseKind;
+
+/// Method used to estimate what a site would have drawn without an event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaselineMethod {
+    /// Average of the `x` highest-load days among the `y` most recent
+    /// eligible days, ranked by load over the event window.
+    HighXofY { x: usize, y: usize },
+    /// Average of the `x` days among the `y` most recent eligible days whose
+    /// temperature over the event window is closest to the event day's.
+    WeatherMatched { x: usize, y: usize },
+}
+
+impl BaselineMethod {
+    /// Parses a method name used in scenario files.
+    pub fn from_name(name: &str, x: usize, y: usize) -> Option<Self> {
+        match name {
+            "high_x_of_y" => Some(Self::HighXofY { x, y }),
+            "weather_matched" => Some(Self::WeatherMatched { x, y }),
+            _ => None,
+        }
+    }
+
+    /// Number of prior eligible days the method looks back over.
+    pub fn lookback_days(self) -> usize {
+        match self {
+            Self::HighXofY { y, .. } | Self::WeatherMatched { y, .. } => y,
+        }
+    }
+}
+
+/// Additive same-day adjustment of a baseline to the load just before an event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SameDayAdjustment {
+    /// Steps immediately before the event used to compare baseline and meter.
+    pub window_steps: usize,
+    /// Largest adjustment as a fraction of the mean absolute baseline in the window.
+    pub cap_fraction: f32,
+}
+
+/// Metered interval data of a site, one value per timestep from day 0.
+#[derive(Debug, Clone, Copy)]
+pub struct MeterHistory<'a> {
+    pub steps_per_day: usize,
+    /// Metered net load (kW).
+    pub load_kw: &'a [f32],
+    /// Ambient temperature (°C).
+    pub temp_c: &'a [f32],
+    /// Days excluded from baselines (event or outage days), one flag per day.
+    pub excluded_days: &'a [bool],
+}
+
+impl MeterHistory<'_> {
+    fn window(&self, values: &[f32], day: usize, start: usize, end: usize) -> Vec<f32> {
+        let offset = day * self.steps_per_day;
+        values[offset + start..offset + end].to_vec()
+    }
+
+    fn window_mean(&self, values: &[f32], day: usize, start: usize, end: usize) -> f32 {
+        let window = self.window(values, day, start, end);
+        window.iter().sum::<f32>() / window.len().max(1) as f32
+    }
+}
+
+/// Settlement baseline for an event window `start..end` (steps within `day`).
+///
+/// Returns `None` if no eligible prior day exists.
+pub fn baseline_kw(
+    method: BaselineMethod,
+    adjustment: Option<SameDayAdjustment>,
+    history: &MeterHistory,
+    day: usize,
+    start: usize,
+    end: usize,
+) -> Option<Vec<f32>> {
+    let eligible: Vec<usize> = (0..day)
+        .rev()
+        .filter(|d| !history.excluded_days.get(*d).copied().unwrap_or(false))
+        .take(method.lookback_days())
+        .collect();
+    if eligible.is_empty() {
+        return None;
+    }
+
+    let mut ranked: Vec<(usize, f32)> = match method {
+        BaselineMethod::HighXofY { .. } => eligible
+            .iter()
+            .map(|&d| (d, -history.window_mean(history.load_kw, d, start, end)))
+            .collect(),
+        BaselineMethod::WeatherMatched { .. } => {
+            let event_temp_c = history.window_mean(history.temp_c, day, start, end);
+            eligible
+                .iter()
+                .map(|&d| {
+                    let temp_c = history.window_mean(history.temp_c, d, start, end);
+                    (d, (temp_c - event_temp_c).abs())
+                })
+                .collect()
+        }
+    };
+    // Stable sort keeps more recent days first on ties.
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let x = match method {
+        BaselineMethod::HighXofY { x, .. } | BaselineMethod::WeatherMatched { x, .. } => x,
+    };
+    let selected: Vec<usize> = ranked.iter().take(x.max(1)).map(|(d, _)| *d).collect();
+
+    let average = |from: usize, to: usize| -> Vec<f32> {
+        let mut sum = vec![0.0; to - from];
+        for &d in &selected {
+            for (acc, kw) in sum
+                .iter_mut()
+                .zip(history.window(history.load_kw, d, from, to))
+            {
+                *acc += kw;
+            }
+        }
+        sum.iter().map(|kw| kw / selected.len() as f32).collect()
+    };
+    let mut baseline = average(start, end);
+
+    if let Some(adj) = adjustment {
+        let from = start.saturating_sub(adj.window_steps);
+        if from < start {
+            let expected = average(from, start);
+            let metered = history.window(history.load_kw, day, from, start);
+            let n = expected.len() as f32;
+            let offset_kw = metered.iter().sum::<f32>() / n - expected.iter().sum::<f32>() / n;
+            let cap_kw = adj.cap_fraction * expected.iter().map(|kw| kw.abs()).sum::<f32>() / n;
+            let offset_kw = offset_kw.clamp(-cap_kw, cap_kw);
+            for kw in &mut baseline {
+                *kw += offset_kw;
+            }
+        }
+    }
+    Some(baseline)
+}
+
+/// Program payment terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SettlementTerms {
+    /// Payment per kWh of delivered load change.
+    pub price_per_kwh: f32,
+    /// Charge per kWh of shortfall when performance is below `min_performance`.
+    pub penalty_per_kwh: f32,
+    /// Performance ratio below which the shortfall is penalised.
+    pub min_performance: f32,
+}
+
+/// Measured performance and payment for one event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventSettlement {
+    /// Baseline energy over the event (kWh).
+    pub baseline_kwh: f32,
+    /// Metered energy over the event (kWh).
+    pub metered_kwh: f32,
+    /// Load change the site was asked for (kWh).
+    pub committed_kwh: f32,
+    /// Load change measured against the baseline, capped per step at the request (kWh).
+    pub delivered_kwh: f32,
+    /// `delivered_kwh / committed_kwh` (1.0 when nothing was committed).
+    pub performance: f32,
+    pub payment: f32,
+    pub penalty: f32,
+}
+
+impl EventSettlement {
+    pub fn net_payment(&self) -> f32 {
+        self.payment - self.penalty
+    }
+}
+
+/// Settles one event from per-step requested kW, baseline and meter readings.
+///
+/// Shed and take events commit to the requested kW below or above the
+/// baseline; target-level events commit to bringing load from the baseline
+/// down to the requested level.
+pub fn settle_event(
+    kind: DemandResponseKind,
+    requested_kw: &[f32],
+    baseline_kw: &[f32],
+    metered_kw: &[f32],
+    dt_hr: f32,
+    terms: SettlementTerms,
+) -> EventSettlement {
+    let mut committed_kwh = 0.0;
+    let mut delivered_kwh = 0.0;
+    for ((&requested, &baseline), &metered) in requested_kw.iter().zip(baseline_kw).zip(metered_kw)
+    {
+        let (committed, change) = match kind {
+            DemandResponseKind::Shed => (requested, baseline - metered),
+            DemandResponseKind::Take => (requested, metered - baseline),
+            DemandResponseKind::TargetLevel => {
+                ((baseline - requested).max(0.0), baseline - metered)
+            }
+        };
+        committed_kwh += committed * dt_hr;
+        delivered_kwh += change.clamp(0.0, committed) * dt_hr;
+    }
+
+    let performance = if committed_kwh > 0.0 {
+        delivered_kwh / committed_kwh
+    } else {
+        1.0
+    };
+    let penalty = if performance < terms.min_performance {
+        terms.penalty_per_kwh * (committed_kwh - delivered_kwh)
+    } else {
+        0.0
+    };
+    EventSettlement {
+        baseline_kwh: baseline_kw.iter().sum::<f32>() * dt_hr,
+        metered_kwh: metered_kw.iter().sum::<f32>() * dt_hr,
+        committed_kwh,
+        delivered_kwh,
+        performance,
+        payment: terms.price_per_kwh * delivered_kwh,
+        penalty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Four days of two steps each; day 3 is the event day.
+    fn history<'a>(
+        load_kw: &'a [f32],
+        temp_c: &'a [f32],
+        excluded: &'a [bool],
+    ) -> MeterHistory<'a> {
+        MeterHistory {
+            steps_per_day: 2,
+            load_kw,
+            temp_c,
+            excluded_days: excluded,
+        }
+    }
+
+    #[test]
+    fn high_x_of_y_averages_highest_eligible_days() {
+        let load = [1.0, 2.0, 5.0, 6.0, 3.0, 4.0, 0.0, 0.0];
+        let temp = [20.0; 8];
+        let excluded = [false, false, false, false];
+        let meter = history(&load, &temp, &excluded);
+        let method = BaselineMethod::HighXofY { x: 2, y: 3 };
+        assert_eq!(
+            baseline_kw(method, None, &meter, 3, 0, 2),
+            Some(vec![4.0, 5.0])
+        );
+
+        // Excluded days are skipped.
+        let excluded = [false, true, false, false];
+        let meter = history(&load, &temp, &excluded);
+        assert_eq!(
+            baseline_kw(method, None, &meter, 3, 0, 2),
+            Some(vec![2.0, 3.0])
+        );
+        assert_eq!(baseline_kw(method, None, &meter, 0, 0, 2), None);
+    }
+
+    #[test]
+    fn weather_matched_picks_closest_temperature_day() {
+        let load = [1.0, 1.0, 5.0, 5.0, 3.0, 3.0, 0.0, 0.0];
+        let temp = [30.0, 30.0, 20.0, 20.0, 25.0, 25.0, 29.0, 29.0];
+        let excluded = [false; 4];
+        let meter = history(&load, &temp, &excluded);
+        let method = BaselineMethod::WeatherMatched { x: 1, y: 3 };
+        assert_eq!(
+            baseline_kw(method, None, &meter, 3, 0, 2),
+            Some(vec![1.0, 1.0])
+        );
+    }
+
+    #[test]
+    fn same_day_adjustment_is_capped() {
+        let load = [2.0, 2.0, 2.0, 2.0, 2.0, 2.0, 3.0, 0.0];
+        let temp = [20.0; 8];
+        let excluded = [false; 4];
+        let meter = history(&load, &temp, &excluded);
+        let method = BaselineMethod::HighXofY { x: 3, y: 3 };
+        let adjustment = SameDayAdjustment {
+            window_steps: 1,
+            cap_fraction: 0.2,
+        };
+        // Pre-event load is 1 kW above baseline, capped at 20% of 2 kW.
+        let baseline = baseline_kw(method, Some(adjustment), &meter, 3, 1, 2).expect("baseline");
+        assert!((baseline[0] - 2.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn settlement_pays_delivery_and_penalises_shortfall() {
+        let terms = SettlementTerms {
+            price_per_kwh: 1.0,
+            penalty_per_kwh: 2.0,
+            min_performance: 0.5,
+        };
+        let good = settle_event(
+            DemandResponseKind::Shed,
+            &[2.0, 2.0],
+            &[5.0, 5.0],
+            &[3.0, 2.0],
+            1.0,
+            terms,
+        );
+        // Over-delivery in the second step is capped at the request.
+        assert_eq!(good.delivered_kwh, 4.0);
+        assert_eq!(good.performance, 1.0);
+        assert_eq!(good.net_payment(), 4.0);
+
+        let poor = settle_event(
+            DemandResponseKind::TargetLevel,
+            &[3.0, 3.0],
+            &[5.0, 5.0],
+            &[4.5, 5.0],
+            1.0,
+            terms,
+        );
+        assert_eq!(poor.committed_kwh, 4.0);
+        assert_eq!(poor.delivered_kwh, 0.5);
+        assert_eq!(poor.penalty, 7.0);
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Minimal complex arithmetic for phasor calculations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn conj(self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn abs(self) -> f64 {
+        self.norm_sqr().sqrt()
+    }
+
+    /// Unit phasor at `degrees`.
+    pub fn from_degrees(degrees: f64) -> Self {
+        let radians = degrees.to_radians();
+        Self::new(radians.cos(), radians.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        Self::new(self.re * factor, self.im * factor)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        self * other.conj() / other.norm_sqr()
+    }
+}
+
+impl Div<f64> for Complex {
+    type Output = Self;
+
+    fn div(self, divisor: f64) -> Self {
+        Self::new(self.re / divisor, self.im / divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators_follow_complex_arithmetic() {
+        let a = Complex::new(3.0, 4.0);
+        let b = Complex::new(1.0, -2.0);
+        assert_eq!(a + b, Complex::new(4.0, 2.0));
+        assert_eq!(a - b, Complex::new(2.0, 6.0));
+        assert_eq!(-a, Complex::new(-3.0, -4.0));
+        assert_eq!(a * b, Complex::new(11.0, -2.0));
+        assert_eq!(a * 2.0, Complex::new(6.0, 8.0));
+        assert_eq!(a * b / b, a);
+        assert_eq!(a / 2.0, Complex::new(1.5, 2.0));
+    }
+}
@@ -58,34 +58,148 @@ impl NaiveRtController {
         }
     }
 
-    /// Apply demand response by shedding flexible load first, then curtailable baseload.
+    /// Limit solar output so feeder export stays within `max_export_kw`.
     ///
-    /// Returns `(baseload_after_kw, flexible_after_kw, achieved_reduction_kw)`.
-    pub fn apply_demand_response_kw(
+    /// Feeder model: `feeder_kw = net_without_solar_kw - solar_kw`, so solar may
+    /// produce at most `net_without_solar_kw + max_export_kw`.
+    pub fn solar_export_limit_kw(
+        &self,
+        net_without_solar_kw: f32,
+        available_solar_kw: f32,
+        max_export_kw: f32,
+    ) -> f32 {
+        available_solar_kw.min((net_without_solar_kw + max_export_kw).max(0.0))
+    }
+
+    /// Load to shed so feeder net load can be held at `level_kw`.
+    ///
+    /// Only the part the battery cannot cover by discharging is shed.
+    pub fn target_level_shed_kw(
+        &self,
+        net_without_battery_kw: f32,
+        battery_max_discharge_kw: f32,
+        level_kw: f32,
+    ) -> f32 {
+        (net_without_battery_kw - battery_max_discharge_kw - level_kw).max(0.0)
+    }
+
+    /// Backup generation requested for the part of a DR reduction that load
+    /// shedding could not deliver.
+    ///
+    /// Generators are a last resort because of their fuel and emissions cost,
+    /// so they only cover `requested - achieved`, up to `genset_rated_kw`.
+    pub fn backup_generation_kw(
         &self,
-        baseload_kw: f32,
-        flexible_load_kw: f32,
         requested_reduction_kw: f32,
-    ) -> (f32, f32, f32) {
-        let mut remaining_reduction = requested_reduction_kw.max(0.0);
+        achieved_reduction_kw: f32,
+        genset_rated_kw: f32,
+    ) -> f32 {
+        (requested_reduction_kw - achieved_reduction_kw).clamp(0.0, genset_rated_kw.max(0.0))
+    }
 
-        let flexible = flexible_load_kw.max(0.0);
-        let flex_shed = flexible.min(remaining_reduction);
-        let flexible_after = flexible - flex_shed;
-        remaining_reduction -= flex_shed;
+    /// Backup generation requested while islanded, once the battery is exhausted.
+    ///
+    /// `net_load_kw` is site load less renewable output; the genset covers what
+    /// the battery cannot, up to `genset_rated_kw`.
+    pub fn island_generation_kw(
+        &self,
+        net_load_kw: f32,
+        battery_max_discharge_kw: f32,
+        genset_rated_kw: f32,
+    ) -> f32 {
+        (net_load_kw - battery_max_discharge_kw).clamp(0.0, genset_rated_kw.max(0.0))
+    }
+
+    /// Baseload to drop while islanded so load does not exceed `supply_kw`.
+    pub fn island_baseload_shed_kw(&self, load_kw: f32, supply_kw: f32, baseload_kw: f32) -> f32 {
+        (load_kw - supply_kw).clamp(0.0, baseload_kw.max(0.0))
+    }
 
-        let base = baseload_kw.max(0.0);
-        let base_shed = base.min(remaining_reduction);
-        let baseload_after = base - base_shed;
+    /// Battery power that moves state of charge to `prep_soc` by the start of
+    /// an announced event, spread evenly over the `steps_left` (positive =
+    /// discharge).
+    pub fn pre_event_battery_kw(
+        &self,
+        soc: f32,
+        prep_soc: f32,
+        capacity_kwh: f32,
+        steps_left: usize,
+        dt_hr: f32,
+    ) -> f32 {
+        (soc - prep_soc) * capacity_kwh / (steps_left.max(1) as f32 * dt_hr)
+    }
 
-        let achieved = flex_shed + base_shed;
-        (baseload_after, flexible_after, achieved)
+    /// Feeder target that makes the battery move at least `prep_kw` while it
+    /// prepares for an event, and otherwise keeps tracking `target_kw`.
+    ///
+    /// Feeder model: `feeder_kw = net_without_battery - battery_kw`.
+    pub fn pre_event_target_kw(
+        &self,
+        target_kw: f32,
+        net_without_battery_kw: f32,
+        prep_kw: f32,
+    ) -> f32 {
+        let prep_target_kw = net_without_battery_kw - prep_kw;
+        if prep_kw < 0.0 {
+            target_kw.max(prep_target_kw)
+        } else if prep_kw > 0.0 {
+            target_kw.min(prep_target_kw)
+        } else {
+            target_kw
+        }
+    }
+}
+
+/// Primary frequency response by proportional droop.
+///
+/// Outside a deadband around nominal, output changes in proportion to the
+/// frequency deviation: with droop `R`, a deviation of `R * nominal_hz`
+/// beyond the deadband moves a resource through its full rating. Works
+/// alongside [`NaiveRtController`]: the response is added on top of the
+/// tracking setpoints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DroopController {
+    pub nominal_hz: f32,
+    pub deadband_hz: f32,
+    /// Per-unit frequency change for a full-rating response (e.g. 0.05 = 5%).
+    pub droop: f32,
+}
+
+impl DroopController {
+    /// Share of rating to respond with, in [-1, 1]; positive raises output
+    /// (or lowers consumption) on under-frequency.
+    pub fn response_fraction(&self, frequency_hz: f32) -> f32 {
+        let deviation_hz = frequency_hz - self.nominal_hz;
+        let beyond_hz = deviation_hz.abs() - self.deadband_hz;
+        if beyond_hz <= 0.0 {
+            return 0.0;
+        }
+        (-deviation_hz.signum() * beyond_hz / (self.droop * self.nominal_hz)).clamp(-1.0, 1.0)
+    }
+
+    /// Battery response (kW, positive = discharge) for a `rated_kw` battery,
+    /// limited to what it can still discharge or charge beyond its setpoint.
+    pub fn battery_kw(
+        &self,
+        frequency_hz: f32,
+        rated_kw: f32,
+        discharge_headroom_kw: f32,
+        charge_headroom_kw: f32,
+    ) -> f32 {
+        (self.response_fraction(frequency_hz) * rated_kw)
+            .clamp(-charge_headroom_kw.max(0.0), discharge_headroom_kw.max(0.0))
+    }
+
+    /// Reduction (kW) of a curtailable load drawing `load_kw`; loads only
+    /// shed on under-frequency.
+    pub fn load_shed_kw(&self, frequency_hz: f32, load_kw: f32) -> f32 {
+        self.response_fraction(frequency_hz).max(0.0) * load_kw.max(0.0)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::NaiveRtController;
+    use super::{DroopController, NaiveRtController};
 
     #[test]
     fn discharges_when_load_is_above_target() {
@@ -141,20 +255,77 @@ mod tests {
     }
 
     #[test]
-    fn demand_response_sheds_flexible_then_baseload() {
+    fn solar_limit_keeps_export_within_limit() {
+        let controller = NaiveRtController;
+        let solar_kw = controller.solar_export_limit_kw(1.0, 8.0, 4.0);
+        assert_eq!(solar_kw, 5.0);
+        assert_eq!(controller.solar_export_limit_kw(1.0, 3.0, 4.0), 3.0);
+        // No allowance when the rest of the site already exceeds the export limit.
+        assert_eq!(controller.solar_export_limit_kw(-6.0, 3.0, 4.0), 0.0);
+    }
+
+    #[test]
+    fn backup_generation_covers_only_dr_shortfall() {
         let controller = NaiveRtController;
-        let (base_after, flex_after, achieved) = controller.apply_demand_response_kw(3.0, 2.0, 4.0);
-        assert_eq!(flex_after, 0.0);
-        assert_eq!(base_after, 1.0);
-        assert_eq!(achieved, 4.0);
+        assert_eq!(controller.backup_generation_kw(10.0, 10.0, 50.0), 0.0);
+        assert_eq!(controller.backup_generation_kw(10.0, 4.0, 50.0), 6.0);
+        assert_eq!(controller.backup_generation_kw(100.0, 4.0, 50.0), 50.0);
     }
 
     #[test]
-    fn demand_response_limited_by_available_load() {
+    fn island_generation_starts_when_battery_falls_short() {
         let controller = NaiveRtController;
-        let (base_after, flex_after, achieved) = controller.apply_demand_response_kw(1.0, 0.5, 3.0);
-        assert_eq!(flex_after, 0.0);
-        assert_eq!(base_after, 0.0);
-        assert_eq!(achieved, 1.5);
+        assert_eq!(controller.island_generation_kw(3.0, 5.0, 10.0), 0.0);
+        assert_eq!(controller.island_generation_kw(8.0, 5.0, 10.0), 3.0);
+        assert_eq!(controller.island_generation_kw(30.0, 5.0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn island_sheds_baseload_down_to_supply() {
+        let controller = NaiveRtController;
+        assert_eq!(controller.island_baseload_shed_kw(4.0, 5.0, 3.0), 0.0);
+        assert_eq!(controller.island_baseload_shed_kw(6.0, 5.0, 3.0), 1.0);
+        assert_eq!(controller.island_baseload_shed_kw(10.0, 5.0, 3.0), 3.0);
+    }
+
+    #[test]
+    fn target_level_sheds_what_battery_cannot_cover() {
+        let controller = NaiveRtController;
+        assert_eq!(controller.target_level_shed_kw(6.0, 3.0, 4.0), 0.0);
+        assert_eq!(controller.target_level_shed_kw(9.0, 3.0, 4.0), 2.0);
+    }
+
+    #[test]
+    fn pre_event_preparation_moves_battery_toward_prep_soc() {
+        let controller = NaiveRtController;
+        // 4 kWh to add over 2 one-hour steps.
+        let prep_kw = controller.pre_event_battery_kw(0.5, 0.9, 10.0, 2, 1.0);
+        assert!((prep_kw + 2.0).abs() < 1e-5);
+        // Charging at least 2 kW raises the feeder target above net load.
+        assert_eq!(controller.pre_event_target_kw(1.0, 3.0, prep_kw), 5.0);
+        // A target already asking for more charge is kept.
+        assert_eq!(controller.pre_event_target_kw(6.0, 3.0, prep_kw), 6.0);
+        // Discharge preparation lowers the target.
+        assert_eq!(controller.pre_event_target_kw(1.0, 3.0, 4.0), -1.0);
+        assert_eq!(controller.pre_event_target_kw(1.0, 3.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn droop_responds_beyond_deadband_in_proportion_to_deviation() {
+        let droop = DroopController {
+            nominal_hz: 60.0,
+            deadband_hz: 0.1,
+            droop: 0.01,
+        };
+        assert_eq!(droop.response_fraction(59.95), 0.0);
+        // 0.3 Hz beyond the deadband is half of the 0.6 Hz full-response span.
+        assert!((droop.response_fraction(59.6) - 0.5).abs() < 1e-4);
+        assert!((droop.response_fraction(60.4) + 0.5).abs() < 1e-4);
+        assert_eq!(droop.response_fraction(58.0), 1.0);
+
+        assert!((droop.battery_kw(59.6, 4.0, 5.0, 5.0) - 2.0).abs() < 1e-4);
+        assert_eq!(droop.battery_kw(59.6, 4.0, 1.0, 5.0), 1.0);
+        assert!((droop.load_shed_kw(59.6, 3.0) - 1.5).abs() < 1e-4);
+        assert_eq!(droop.load_shed_kw(60.4, 3.0), 0.0);
     }
 }
@@ -0,0 +1,132 @@
+/// Demand response behaviour a device declares to the [`DrAllocator`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrCapability {
+    /// Whether the device takes part in demand response at all.
+    pub curtailable: bool,
+    /// Largest reduction per step (kW); `None` leaves only the device's own limit.
+    pub max_shed_kw: Option<f32>,
+    /// Most steps the device may be shed within one event; `None` for the whole event.
+    pub max_shed_steps: Option<usize>,
+    /// Cost of shedding per kWh; cheaper devices are shed first.
+    pub cost_per_kwh: f32,
+}
+
+impl DrCapability {
+    /// Curtailable without limits at `cost_per_kwh`.
+    pub fn with_cost(cost_per_kwh: f32) -> Self {
+        Self {
+            curtailable: true,
+            max_shed_kw: None,
+            max_shed_steps: None,
+            cost_per_kwh,
+        }
+    }
+
+    /// Reduction the device can offer now, given what it could shed and how
+    /// many steps of the event it has already been shed for.
+    fn offer_kw(&self, available_kw: f32, shed_steps: usize) -> f32 {
+        if !self.curtailable || self.max_shed_steps.is_some_and(|max| shed_steps >= max) {
+            return 0.0;
+        }
+        let kw = available_kw.max(0.0);
+        self.max_shed_kw.map_or(kw, |max_kw| kw.min(max_kw))
+    }
+}
+
+/// Splits a demand response reduction across devices in order of cost.
+///
+/// Each device offers what it can shed this step within its declared limits;
+/// the cheapest offers are used first until the request is covered. Ties keep
+/// the order the devices were registered in.
+#[derive(Debug, Clone)]
+pub struct DrAllocator {
+    capabilities: Vec<DrCapability>,
+    order: Vec<usize>,
+    shed_steps: Vec<usize>,
+    event: Option<usize>,
+}
+
+impl DrAllocator {
+    pub fn new(capabilities: Vec<DrCapability>) -> Self {
+        let mut order: Vec<usize> = (0..capabilities.len()).collect();
+        order.sort_by(|a, b| {
+            capabilities[*a]
+                .cost_per_kwh
+                .total_cmp(&capabilities[*b].cost_per_kwh)
+        });
+        Self {
+            shed_steps: vec![0; capabilities.len()],
+            capabilities,
+            order,
+            event: None,
+        }
+    }
+
+    /// Reduction per device (kW) for one step of event `event_idx`.
+    ///
+    /// `available_kw[i]` is what device `i` could shed right now; devices are
+    /// in registration order.
+    pub fn allocate<const N: usize>(
+        &mut self,
+        event_idx: usize,
+        available_kw: [f32; N],
+        requested_kw: f32,
+    ) -> [f32; N] {
+        assert_eq!(N, self.capabilities.len());
+        if self.event != Some(event_idx) {
+            self.event = Some(event_idx);
+            self.shed_steps.fill(0);
+        }
+
+        let mut remaining_kw = requested_kw.max(0.0);
+        let mut allocation = [0.0; N];
+        for &i in &self.order {
+            let kw = self.capabilities[i]
+                .offer_kw(available_kw[i], self.shed_steps[i])
+                .min(remaining_kw);
+            if kw > 0.0 {
+                allocation[i] = kw;
+                remaining_kw -= kw;
+                self.shed_steps[i] += 1;
+            }
+        }
+        allocation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cheapest_devices_are_shed_first() {
+        let mut allocator = DrAllocator::new(vec![
+            DrCapability::with_cost(1.0),
+            DrCapability::with_cost(0.0),
+        ]);
+        // Flexible load (index 1) is cheaper, so it is shed before baseload.
+        assert_eq!(allocator.allocate(0, [3.0, 2.0], 4.0), [2.0, 2.0]);
+        assert_eq!(allocator.allocate(0, [1.0, 0.5], 3.0), [1.0, 0.5]);
+        assert_eq!(allocator.allocate(0, [3.0, 2.0], 1.0), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn limits_cap_each_device() {
+        let capped = DrCapability {
+            max_shed_kw: Some(1.0),
+            max_shed_steps: Some(2),
+            ..DrCapability::with_cost(0.0)
+        };
+        let excluded = DrCapability {
+            curtailable: false,
+            ..DrCapability::with_cost(0.0)
+        };
+        let mut allocator = DrAllocator::new(vec![capped, excluded, DrCapability::with_cost(5.0)]);
+        assert_eq!(allocator.allocate(0, [4.0, 4.0, 4.0], 3.0), [1.0, 0.0, 2.0]);
+        assert_eq!(allocator.allocate(0, [4.0, 4.0, 4.0], 3.0), [1.0, 0.0, 2.0]);
+        // The capped device has used its two steps for this event.
+        assert_eq!(allocator.allocate(0, [4.0, 4.0, 4.0], 3.0), [0.0, 0.0, 3.0]);
+        // A new event resets the duration limit.
+        assert_eq!(allocator.allocate(1, [4.0, 4.0, 4.0], 3.0), [1.0, 0.0, 2.0]);
+    }
+}
@@ -0,0 +1,160 @@
+//! Dynamic operating envelopes: time-varying import and export limits a
+//! distribution system operator (DSO) sets at a site's connection point.
+
+use crate::sim::topology::NodeLimits;
+use serde::{Deserialize, Serialize};
+
+/// Limits (kW) that hold from `start_step` until the next update.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnvelopeUpdate {
+    pub start_step: usize,
+    pub import_kw: f32,
+    pub export_kw: f32,
+}
+
+impl EnvelopeUpdate {
+    pub fn limits(&self) -> NodeLimits {
+        NodeLimits::new(self.import_kw, self.export_kw)
+    }
+}
+
+/// Piecewise-constant envelope built from updates; steps before the first
+/// update are left to the connection limits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OperatingEnvelope {
+    /// Updates ordered by start step; of two at the same step the later wins.
+    updates: Vec<EnvelopeUpdate>,
+}
+
+impl OperatingEnvelope {
+    pub fn new(updates: Vec<EnvelopeUpdate>) -> Self {
+        let mut envelope = Self::default();
+        for update in updates {
+            envelope.push(update);
+        }
+        envelope
+    }
+
+    /// Adds an update that replaces the envelope from its start step until
+    /// the next later update.
+    pub fn push(&mut self, update: EnvelopeUpdate) {
+        assert!(update.import_kw >= 0.0);
+        assert!(update.export_kw >= 0.0);
+
+        let idx = self
+            .updates
+            .partition_point(|u| u.start_step <= update.start_step);
+        self.updates.insert(idx, update);
+    }
+
+    pub fn updates(&self) -> &[EnvelopeUpdate] {
+        &self.updates
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updates.is_empty()
+    }
+
+    /// Limits in force at `step`, if any update has started.
+    pub fn limits_at(&self, step: usize) -> Option<NodeLimits> {
+        let idx = self.updates.partition_point(|u| u.start_step <= step);
+        idx.checked_sub(1).map(|idx| self.updates[idx].limits())
+    }
+}
+
+/// Envelope a DSO can offer a site that shares an asset of `capacity_kw`
+/// with uncontrolled neighbours drawing `background_kw` (negative while their
+/// solar exports): their load uses up import headroom and absorbs export.
+pub fn hosting_capacity_limits(capacity_kw: f32, background_kw: f32) -> NodeLimits {
+    NodeLimits::new(
+        (capacity_kw - background_kw).max(0.0),
+        (capacity_kw + background_kw).max(0.0),
+    )
+}
+
+/// Parses envelope updates from CSV text with `timestep,import_kw,export_kw`
+/// rows, each holding until the next row.
+///
+/// A header line is skipped.
+pub fn parse_envelope_csv(raw: &str) -> Result<Vec<EnvelopeUpdate>, String> {
+    let mut updates = Vec::new();
+    for (idx, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [step, import_kw, export_kw] = fields[..] else {
+            return Err(format!(
+                "line {}: expected `timestep,import_kw,export_kw`",
+                idx + 1
+            ));
+        };
+        let Ok(start_step) = step.parse::<usize>() else {
+            if updates.is_empty() && idx == 0 {
+                continue;
+            }
+            return Err(format!("line {}: invalid timestep `{step}`", idx + 1));
+        };
+        let limit = |field: &str, name: &str| {
+            field
+                .parse::<f32>()
+                .ok()
+                .filter(|kw| *kw >= 0.0 && kw.is_finite())
+                .ok_or_else(|| format!("line {}: {name} must be a number >= 0", idx + 1))
+        };
+        updates.push(EnvelopeUpdate {
+            start_step,
+            import_kw: limit(import_kw, "import_kw")?,
+            export_kw: limit(export_kw, "export_kw")?,
+        });
+    }
+    if updates.is_empty() {
+        return Err("no envelope rows found".to_string());
+    }
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(start_step: usize, import_kw: f32, export_kw: f32) -> EnvelopeUpdate {
+        EnvelopeUpdate {
+            start_step,
+            import_kw,
+            export_kw,
+        }
+    }
+
+    #[test]
+    fn updates_hold_until_the_next_and_later_pushes_win() {
+        let mut envelope = OperatingEnvelope::new(vec![update(10, 4.0, 2.0), update(4, 6.0, 3.0)]);
+        assert_eq!(envelope.limits_at(3), None);
+        assert_eq!(envelope.limits_at(4), Some(NodeLimits::new(6.0, 3.0)));
+        assert_eq!(envelope.limits_at(99), Some(NodeLimits::new(4.0, 2.0)));
+
+        envelope.push(update(10, 1.0, 0.0));
+        assert_eq!(envelope.limits_at(12), Some(NodeLimits::new(1.0, 0.0)));
+        assert_eq!(envelope.updates().len(), 3);
+    }
+
+    #[test]
+    fn parses_csv_and_hosting_capacity() {
+        let updates =
+            parse_envelope_csv("timestep,import_kw,export_kw\n0,5,4\n12, 3.5, 0\n").expect("valid");
+        assert_eq!(updates, vec![update(0, 5.0, 4.0), update(12, 3.5, 0.0)]);
+        assert!(parse_envelope_csv("0,5\n").is_err());
+        assert!(parse_envelope_csv("0,5,-1\n").is_err());
+        assert!(parse_envelope_csv("timestep,import_kw,export_kw\n").is_err());
+
+        assert_eq!(
+            hosting_capacity_limits(10.0, 4.0),
+            NodeLimits::new(6.0, 14.0)
+        );
+        assert_eq!(
+            hosting_capacity_limits(10.0, -12.0),
+            NodeLimits::new(22.0, 0.0)
+        );
+    }
+}
@@ -1,12 +1,55 @@
-/// External demand response event requesting temporary load reduction.
-#[derive(Debug, Clone, Copy)]
+/// Type of demand response event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemandResponseKind {
+    /// Reduce site load by the requested kW.
+    #[default]
+    Shed,
+    /// Increase site load by the requested kW (e.g. to absorb surplus renewables).
+    Take,
+    /// Keep feeder net load at or below the requested kW level.
+    TargetLevel,
+}
+
+impl DemandResponseKind {
+    /// Parses a kind name used in scenario files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "shed" => Some(Self::Shed),
+            "take" => Some(Self::Take),
+            "target" => Some(Self::TargetLevel),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Shed => "shed",
+            Self::Take => "take",
+            Self::TargetLevel => "target",
+        }
+    }
+}
+
+/// External demand response event requesting a temporary change in site load.
+#[derive(Debug, Clone, PartialEq)]
 pub struct DemandResponseEvent {
+    /// What the event asks of the site.
+    pub kind: DemandResponseKind,
     /// Start timestep (inclusive).
     pub start_step: usize,
     /// End timestep (exclusive).
     pub end_step: usize,
-    /// Requested reduction while event is active.
-    pub requested_reduction_kw: f32,
+    /// Requested kW while event is active: the reduction for shed events, the
+    /// increase for take events and the feeder level for target-level events.
+    pub requested_kw: f32,
+    /// Steps over which the request ramps up from zero at the start.
+    pub ramp_in_steps: usize,
+    /// Steps over which the request ramps down to zero at the end.
+    pub ramp_out_steps: usize,
+    /// Per-step requested kW replacing `requested_kw`; empty for a flat request.
+    pub profile: Vec<f32>,
+    /// Steps before `start_step` at which the event is announced.
+    pub notice_steps: usize,
 }
 
 impl DemandResponseEvent {
@@ -15,28 +58,152 @@ impl DemandResponseEvent {
         assert!(requested_reduction_kw >= 0.0);
 
         Self {
+            kind: DemandResponseKind::Shed,
             start_step,
             end_step,
-            requested_reduction_kw,
+            requested_kw: requested_reduction_kw,
+            ramp_in_steps: 0,
+            ramp_out_steps: 0,
+            profile: Vec::new(),
+            notice_steps: 0,
         }
     }
 
+    pub fn with_kind(mut self, kind: DemandResponseKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_ramp(mut self, ramp_in_steps: usize, ramp_out_steps: usize) -> Self {
+        assert!(ramp_in_steps + ramp_out_steps <= self.duration_steps());
+        self.ramp_in_steps = ramp_in_steps;
+        self.ramp_out_steps = ramp_out_steps;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: Vec<f32>) -> Self {
+        assert_eq!(profile.len(), self.duration_steps());
+        assert!(profile.iter().all(|kw| *kw >= 0.0));
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_notice(mut self, notice_steps: usize) -> Self {
+        self.notice_steps = notice_steps;
+        self
+    }
+
+    /// Copy of the event with all kW values multiplied by `factor`.
+    pub fn scaled(mut self, factor: f32) -> Self {
+        self.requested_kw *= factor;
+        for kw in &mut self.profile {
+            *kw *= factor;
+        }
+        self
+    }
+
+    /// Copy of the event moved `steps` later.
+    pub fn shifted(mut self, steps: usize) -> Self {
+        self.start_step += steps;
+        self.end_step += steps;
+        self
+    }
+
+    pub fn duration_steps(&self) -> usize {
+        self.end_step - self.start_step
+    }
+
     pub fn is_active(&self, timestep: usize) -> bool {
         timestep >= self.start_step && timestep < self.end_step
     }
 
-    pub fn requested_reduction_at_kw(&self, timestep: usize) -> f32 {
-        if self.is_active(timestep) {
-            self.requested_reduction_kw
+    /// Timestep at which the event becomes known (0 if announced before the run).
+    pub fn announced_step(&self) -> usize {
+        self.start_step.saturating_sub(self.notice_steps)
+    }
+
+    /// Whether the event has been announced but not yet started at `timestep`.
+    pub fn is_upcoming(&self, timestep: usize) -> bool {
+        timestep >= self.announced_step() && timestep < self.start_step
+    }
+
+    /// Requested kW at `timestep` after profile and ramps; zero outside the window.
+    pub fn requested_at_kw(&self, timestep: usize) -> f32 {
+        if !self.is_active(timestep) {
+            return 0.0;
+        }
+        let offset = timestep - self.start_step;
+        let kw = self
+            .profile
+            .get(offset)
+            .copied()
+            .unwrap_or(self.requested_kw);
+
+        let steps_left = self.end_step - timestep;
+        let ramp_in = if offset < self.ramp_in_steps {
+            (offset + 1) as f32 / (self.ramp_in_steps + 1) as f32
         } else {
-            0.0
+            1.0
+        };
+        let ramp_out = if steps_left <= self.ramp_out_steps {
+            steps_left as f32 / (self.ramp_out_steps + 1) as f32
+        } else {
+            1.0
+        };
+        kw * ramp_in.min(ramp_out)
+    }
+
+    /// Load reduction requested at `timestep` by a shed event; zero otherwise.
+    #[cfg(test)]
+    pub fn requested_reduction_at_kw(&self, timestep: usize) -> f32 {
+        match self.kind {
+            DemandResponseKind::Shed => self.requested_at_kw(timestep),
+            _ => 0.0,
         }
     }
 }
 
+/// Demand response events of a run, sorted by start step.
+#[derive(Debug, Clone, Default)]
+pub struct DemandResponseProgram {
+    events: Vec<DemandResponseEvent>,
+}
+
+impl DemandResponseProgram {
+    /// Builds a program from non-overlapping events in any order.
+    pub fn new(mut events: Vec<DemandResponseEvent>) -> Self {
+        events.sort_by_key(|event| event.start_step);
+        assert!(
+            events.windows(2).all(|w| w[0].end_step <= w[1].start_step),
+            "demand response events must not overlap"
+        );
+        Self { events }
+    }
+
+    pub fn events(&self) -> &[DemandResponseEvent] {
+        &self.events
+    }
+
+    /// Index and event active at `timestep`, if any.
+    pub fn active(&self, timestep: usize) -> Option<(usize, &DemandResponseEvent)> {
+        self.events
+            .iter()
+            .enumerate()
+            .find(|(_, event)| event.is_active(timestep))
+    }
+
+    /// Index and event announced but not yet started at `timestep`, if any.
+    pub fn upcoming(&self, timestep: usize) -> Option<(usize, &DemandResponseEvent)> {
+        self.events
+            .iter()
+            .enumerate()
+            .find(|(_, event)| event.is_upcoming(timestep))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::DemandResponseEvent;
+    use super::*;
 
     #[test]
     fn active_only_inside_window() {
@@ -55,4 +222,48 @@ mod tests {
         assert_eq!(event.requested_reduction_at_kw(11), 1.5);
         assert_eq!(event.requested_reduction_at_kw(12), 0.0);
     }
+
+    #[test]
+    fn ramps_scale_request_at_window_edges() {
+        let event = DemandResponseEvent::new(0, 6, 3.0).with_ramp(2, 1);
+        let kws: Vec<f32> = (0..6).map(|t| event.requested_at_kw(t)).collect();
+        assert_eq!(kws, vec![1.0, 2.0, 3.0, 3.0, 3.0, 1.5]);
+    }
+
+    #[test]
+    fn profile_replaces_flat_request() {
+        let event = DemandResponseEvent::new(2, 5, 9.0)
+            .with_kind(DemandResponseKind::Take)
+            .with_profile(vec![1.0, 2.0, 0.5])
+            .scaled(2.0)
+            .shifted(24);
+        assert_eq!(event.requested_at_kw(27), 4.0);
+        assert_eq!(event.requested_at_kw(28), 1.0);
+        assert_eq!(event.requested_reduction_at_kw(27), 0.0);
+    }
+
+    #[test]
+    fn program_finds_active_event() {
+        let program = DemandResponseProgram::new(vec![
+            DemandResponseEvent::new(10, 12, 1.0),
+            DemandResponseEvent::new(2, 4, 1.0),
+        ]);
+        assert_eq!(program.active(3).map(|(i, _)| i), Some(0));
+        assert_eq!(program.active(11).map(|(i, _)| i), Some(1));
+        assert!(program.active(5).is_none());
+    }
+
+    #[test]
+    fn announced_events_are_upcoming_until_they_start() {
+        let program = DemandResponseProgram::new(vec![
+            DemandResponseEvent::new(2, 4, 1.0).with_notice(5),
+            DemandResponseEvent::new(10, 12, 1.0).with_notice(3),
+        ]);
+        assert_eq!(program.events()[0].announced_step(), 0);
+        assert_eq!(program.upcoming(0).map(|(i, _)| i), Some(0));
+        assert!(program.upcoming(2).is_none());
+        assert!(program.upcoming(6).is_none());
+        assert_eq!(program.upcoming(7).map(|(i, _)| i), Some(1));
+        assert!(program.upcoming(10).is_none());
+    }
 }
@@ -1,14 +1,79 @@
+use crate::sim::complex::Complex;
+
+/// Imbalance (kW) accepted as zero while islanded.
+const ISLAND_TOLERANCE_KW: f32 = 1e-3;
+
+/// Positive-sequence current or voltage (pu) below which unbalance is zero.
+const MIN_SEQUENCE_MAGNITUDE: f64 = 1e-9;
+
+/// Nominal angle of each phase voltage (degrees), A-B-C rotation.
+const PHASE_ANGLES_DEG: [f64; 3] = [0.0, -120.0, 120.0];
+
+/// Phase a device is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    A,
+    B,
+    C,
+    /// Balanced across all three phases.
+    Three,
+}
+
+impl Phase {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "a" => Some(Self::A),
+            "b" => Some(Self::B),
+            "c" => Some(Self::C),
+            "abc" => Some(Self::Three),
+            _ => None,
+        }
+    }
+
+    /// Share of the device's power carried by each phase.
+    fn shares(self) -> [f32; 3] {
+        match self {
+            Self::A => [1.0, 0.0, 0.0],
+            Self::B => [0.0, 1.0, 0.0],
+            Self::C => [0.0, 0.0, 1.0],
+            Self::Three => [1.0 / 3.0; 3],
+        }
+    }
+}
+
 /// A simple feeder model that aggregates device power into net load.
 ///
 /// Net load convention:
 /// - Positive values increase feeder load (consumption)
 /// - Negative values reduce feeder load (generation)
+///
+/// Reactive power follows the same convention (positive kVAR is drawn from the
+/// grid). Voltage at the site is estimated from the net flow through a single
+/// series impedance given in per unit on a base of the rated `max_import_kw`.
+///
+/// Devices may be connected to one phase or all three; per-phase flows give
+/// per-phase voltages and the voltage and current unbalance factors, each the
+/// ratio of negative- to positive-sequence magnitude.
+///
+/// Limits may be tightened step by step (see [`Feeder::set_limits`]), e.g. when
+/// an upstream node needs the site to hold back; the voltage base stays at the
+/// rated import limit.
+///
+/// While islanded (grid outage) both limits are zero, so the site must balance
+/// itself.
 #[derive(Debug, Clone)]
 pub struct Feeder {
     name: &'static str,
     net_kw: f32,
+    net_kvar: f32,
+    phase_kw: [f32; 3],
+    phase_kvar: [f32; 3],
     max_import_kw: f32,
     max_export_kw: f32,
+    base_kw: f32,
+    r_pu: f32,
+    x_pu: f32,
+    islanded: bool,
 }
 
 impl Feeder {
@@ -17,8 +82,15 @@ impl Feeder {
         Self {
             name,
             net_kw: 0.0,
+            net_kvar: 0.0,
+            phase_kw: [0.0; 3],
+            phase_kvar: [0.0; 3],
             max_import_kw: f32::INFINITY,
             max_export_kw: f32::INFINITY,
+            base_kw: f32::INFINITY,
+            r_pu: 0.0,
+            x_pu: 0.0,
+            islanded: false,
         }
     }
 
@@ -29,42 +101,180 @@ impl Feeder {
         Self {
             name,
             net_kw: 0.0,
+            net_kvar: 0.0,
+            phase_kw: [0.0; 3],
+            phase_kvar: [0.0; 3],
             max_import_kw,
             max_export_kw,
+            base_kw: max_import_kw,
+            r_pu: 0.0,
+            x_pu: 0.0,
+            islanded: false,
         }
     }
 
+    /// Sets the series resistance and reactance used for the voltage estimate.
+    pub fn with_impedance(mut self, r_pu: f32, x_pu: f32) -> Self {
+        assert!(r_pu >= 0.0 && x_pu >= 0.0);
+        self.r_pu = r_pu;
+        self.x_pu = x_pu;
+        self
+    }
+
+    /// Replaces the import and export limits from this step on.
+    pub fn set_limits(&mut self, max_import_kw: f32, max_export_kw: f32) {
+        assert!(max_import_kw >= 0.0);
+        assert!(max_export_kw >= 0.0);
+        self.max_import_kw = max_import_kw;
+        self.max_export_kw = max_export_kw;
+    }
+
+    /// Disconnects from (`true`) or reconnects to (`false`) the upstream grid.
+    pub fn set_islanded(&mut self, islanded: bool) {
+        self.islanded = islanded;
+    }
+
     pub fn reset(&mut self) {
         self.net_kw = 0.0;
+        self.net_kvar = 0.0;
+        self.phase_kw = [0.0; 3];
+        self.phase_kvar = [0.0; 3];
     }
 
-    /// Adds a signed contribution to feeder net load.
+    /// Adds a signed contribution to feeder net load, balanced across phases.
+    #[cfg(test)]
     pub fn add_net_kw(&mut self, kw: f32) {
+        self.add_phase_kw(Phase::Three, kw);
+    }
+
+    /// Adds a signed contribution to feeder net load on `phase`.
+    pub fn add_phase_kw(&mut self, phase: Phase, kw: f32) {
         self.net_kw += kw;
+        for (phase_kw, share) in self.phase_kw.iter_mut().zip(phase.shares()) {
+            *phase_kw += kw * share;
+        }
     }
 
     pub fn net_kw(&self) -> f32 {
         self.net_kw
     }
 
+    /// Net load on phases A, B and C.
+    pub fn phase_kw(&self) -> [f32; 3] {
+        self.phase_kw
+    }
+
+    /// Adds a signed reactive power contribution, balanced across phases.
+    #[cfg(test)]
+    pub fn add_net_kvar(&mut self, kvar: f32) {
+        self.add_phase_kvar(Phase::Three, kvar);
+    }
+
+    /// Adds a signed reactive power contribution on `phase`.
+    pub fn add_phase_kvar(&mut self, phase: Phase, kvar: f32) {
+        self.net_kvar += kvar;
+        for (phase_kvar, share) in self.phase_kvar.iter_mut().zip(phase.shares()) {
+            *phase_kvar += kvar * share;
+        }
+    }
+
+    pub fn net_kvar(&self) -> f32 {
+        self.net_kvar
+    }
+
+    /// Power factor of the net flow (1.0 when there is no flow).
+    pub fn power_factor(&self) -> f32 {
+        let kva = self.net_kw.hypot(self.net_kvar);
+        if kva > 0.0 {
+            self.net_kw.abs() / kva
+        } else {
+            1.0
+        }
+    }
+
+    /// Estimated site voltage in per unit: `1 - (R·P + X·Q) / S_base`.
+    ///
+    /// Import lowers voltage and export raises it. Returns `1.0` when the
+    /// feeder has no finite rated import limit to use as base.
+    pub fn voltage_pu(&self) -> f32 {
+        if !self.base_kw.is_finite() || self.base_kw <= 0.0 {
+            return 1.0;
+        }
+        1.0 - (self.r_pu * self.net_kw + self.x_pu * self.net_kvar) / self.base_kw
+    }
+
+    /// Phase current phasors (kVA per unit of nominal phase voltage),
+    /// `I = conj(S / E)` at nominal voltage `E`.
+    fn phase_currents(&self) -> [Complex; 3] {
+        std::array::from_fn(|p| {
+            let power = Complex::new(f64::from(self.phase_kw[p]), f64::from(self.phase_kvar[p]));
+            power.conj() * Complex::from_degrees(PHASE_ANGLES_DEG[p])
+        })
+    }
+
+    /// Phase voltage phasors (pu) after the drop across the series
+    /// impedance, each phase on a third of the rated base.
+    fn phase_voltages(&self) -> [Complex; 3] {
+        let currents = self.phase_currents();
+        std::array::from_fn(|p| {
+            let nominal = Complex::from_degrees(PHASE_ANGLES_DEG[p]);
+            if !self.base_kw.is_finite() || self.base_kw <= 0.0 {
+                return nominal;
+            }
+            let impedance = Complex::new(f64::from(self.r_pu), f64::from(self.x_pu));
+            nominal - impedance * currents[p] * (3.0 / f64::from(self.base_kw))
+        })
+    }
+
+    /// Estimated voltage magnitude of phases A, B and C (per unit).
+    pub fn phase_voltage_pu(&self) -> [f32; 3] {
+        self.phase_voltages().map(|v| v.abs() as f32)
+    }
+
+    /// Voltage unbalance factor, `|V2| / |V1|`.
+    pub fn voltage_unbalance(&self) -> f32 {
+        sequence_unbalance(self.phase_voltages())
+    }
+
+    /// Current unbalance factor, `|I2| / |I1|`; zero without positive-sequence
+    /// current. Export on one phase and import on another can push it above 1.
+    pub fn current_unbalance(&self) -> f32 {
+        sequence_unbalance(self.phase_currents())
+    }
+
     pub fn max_import_kw(&self) -> f32 {
-        self.max_import_kw
+        if self.islanded {
+            0.0
+        } else {
+            self.max_import_kw
+        }
     }
 
     pub fn max_export_kw(&self) -> f32 {
-        self.max_export_kw
+        if self.islanded {
+            0.0
+        } else {
+            self.max_export_kw
+        }
     }
 
     pub fn min_net_kw(&self) -> f32 {
-        -self.max_export_kw
+        -self.max_export_kw()
     }
 
     pub fn max_net_kw(&self) -> f32 {
-        self.max_import_kw
+        self.max_import_kw()
     }
 
+    /// Whether net load is within limits, allowing for rounding when islanded.
     pub fn within_limits(&self) -> bool {
-        self.net_kw >= self.min_net_kw() && self.net_kw <= self.max_net_kw()
+        let tolerance_kw = if self.islanded {
+            ISLAND_TOLERANCE_KW
+        } else {
+            0.0
+        };
+        self.net_kw >= self.min_net_kw() - tolerance_kw
+            && self.net_kw <= self.max_net_kw() + tolerance_kw
     }
 
     pub fn name(&self) -> &'static str {
@@ -72,6 +282,19 @@ impl Feeder {
     }
 }
 
+/// Ratio of negative- to positive-sequence magnitude of A-B-C phasors.
+fn sequence_unbalance(phasors: [Complex; 3]) -> f32 {
+    let a = Complex::from_degrees(120.0);
+    let a2 = Complex::from_degrees(240.0);
+    let [pa, pb, pc] = phasors;
+    let positive = (pa + a * pb + a2 * pc) / 3.0;
+    let negative = (pa + a2 * pb + a * pc) / 3.0;
+    if positive.abs() < MIN_SEQUENCE_MAGNITUDE {
+        return 0.0;
+    }
+    (negative.abs() / positive.abs()) as f32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,4 +347,75 @@ mod tests {
         feeder.add_net_kw(-2.5);
         assert!(!feeder.within_limits());
     }
+
+    #[test]
+    fn islanded_feeder_has_zero_limits() {
+        let mut feeder = Feeder::with_limits("FeederA", 4.0, 2.0);
+        feeder.set_islanded(true);
+        assert_eq!(feeder.max_import_kw(), 0.0);
+        assert_eq!(feeder.max_export_kw(), 0.0);
+        feeder.add_net_kw(0.5);
+        assert!(!feeder.within_limits());
+
+        feeder.set_islanded(false);
+        assert!(feeder.within_limits());
+        assert_eq!(feeder.max_import_kw(), 4.0);
+    }
+
+    #[test]
+    fn tightened_limits_keep_rated_voltage_base() {
+        let mut feeder = Feeder::with_limits("FeederA", 10.0, 8.0).with_impedance(0.1, 0.0);
+        feeder.set_limits(4.0, 0.0);
+        assert_eq!(feeder.max_import_kw(), 4.0);
+        assert_eq!(feeder.max_export_kw(), 0.0);
+        feeder.add_net_kw(5.0);
+        assert!(!feeder.within_limits());
+        // 0.1 * 5 / 10 = 0.05 pu drop on the rated base.
+        assert!((feeder.voltage_pu() - 0.95).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_power_factor_and_voltage_estimate() {
+        let mut feeder = Feeder::with_limits("FeederA", 10.0, 10.0).with_impedance(0.05, 0.1);
+        assert_eq!(feeder.power_factor(), 1.0);
+        assert_eq!(feeder.voltage_pu(), 1.0);
+
+        feeder.add_net_kw(8.0);
+        feeder.add_net_kvar(6.0);
+        assert!((feeder.power_factor() - 0.8).abs() < 1e-6);
+        // 0.05 * 8 / 10 + 0.1 * 6 / 10 = 0.1 pu drop.
+        assert!((feeder.voltage_pu() - 0.9).abs() < 1e-6);
+
+        feeder.reset();
+        feeder.add_net_kw(-8.0);
+        assert!(feeder.voltage_pu() > 1.0);
+    }
+
+    #[test]
+    fn single_phase_load_unbalances_phases() {
+        let mut feeder = Feeder::with_limits("FeederA", 30.0, 24.0).with_impedance(0.05, 0.05);
+        feeder.add_net_kw(9.0);
+        assert!(feeder.current_unbalance() < 1e-6);
+        assert!(feeder.voltage_unbalance() < 1e-6);
+        let [va, vb, vc] = feeder.phase_voltage_pu();
+        assert!((va - vb).abs() < 1e-6 && (vb - vc).abs() < 1e-6);
+
+        feeder.add_phase_kw(Phase::A, 6.0);
+        assert_eq!(feeder.phase_kw(), [9.0, 3.0, 3.0]);
+        assert_eq!(feeder.net_kw(), 15.0);
+        // 3 kW balanced plus 6 kW on A: I1 = 3 + 6/3, I2 = 6/3.
+        assert!((feeder.current_unbalance() - 0.4).abs() < 1e-5);
+        let [va, vb, _] = feeder.phase_voltage_pu();
+        assert!(va < vb);
+        assert!(feeder.voltage_unbalance() > 0.0);
+
+        // Solar exporting on B while A imports: I1 = |2 - 1| / 3 and
+        // I2 = |2 + 1∠-60°| / 3, so the factor exceeds 100%.
+        feeder.reset();
+        feeder.add_phase_kw(Phase::A, 2.0);
+        feeder.add_phase_kw(Phase::B, -1.0);
+        assert!((feeder.current_unbalance() - 7f32.sqrt()).abs() < 1e-4);
+        assert_eq!(Phase::from_name("abc"), Some(Phase::Three));
+        assert_eq!(Phase::from_name("d"), None);
+    }
 }
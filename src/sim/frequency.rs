@@ -0,0 +1,152 @@
+use crate::series::parse_last_column_csv;
+
+/// A grid frequency excursion, such as the loss of a large generator.
+///
+/// Frequency drops (or rises) by `deviation_hz` at `start_step` and recovers
+/// linearly to nominal by `end_step`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrequencyDisturbance {
+    /// Start timestep (inclusive).
+    pub start_step: usize,
+    /// End timestep (exclusive).
+    pub end_step: usize,
+    /// Deviation from nominal at the start (Hz, negative for under-frequency).
+    pub deviation_hz: f32,
+}
+
+impl FrequencyDisturbance {
+    pub fn new(start_step: usize, end_step: usize, deviation_hz: f32) -> Self {
+        assert!(start_step < end_step);
+
+        Self {
+            start_step,
+            end_step,
+            deviation_hz,
+        }
+    }
+
+    /// Deviation (Hz) at `timestep`; zero outside the disturbance.
+    fn deviation_at(&self, timestep: usize) -> f32 {
+        if timestep < self.start_step || timestep >= self.end_step {
+            return 0.0;
+        }
+        let elapsed = (timestep - self.start_step) as f32;
+        let duration = (self.end_step - self.start_step) as f32;
+        self.deviation_hz * (1.0 - elapsed / duration)
+    }
+}
+
+/// Grid frequency seen at the point of connection.
+#[derive(Debug, Clone)]
+pub struct GridFrequency {
+    nominal_hz: f32,
+    disturbances: Vec<FrequencyDisturbance>,
+    series: Option<Vec<f32>>,
+}
+
+impl GridFrequency {
+    /// Nominal frequency with synthetic `disturbances`, which add up where
+    /// they overlap.
+    pub fn new(nominal_hz: f32, disturbances: Vec<FrequencyDisturbance>) -> Self {
+        assert!(nominal_hz > 0.0);
+
+        Self {
+            nominal_hz,
+            disturbances,
+            series: None,
+        }
+    }
+
+    /// Replaces the synthetic frequency with measured values, one per
+    /// timestep, repeated if shorter than the run.
+    pub fn with_series(mut self, series: Vec<f32>) -> Self {
+        assert!(!series.is_empty());
+        self.series = Some(series);
+        self
+    }
+
+    /// Frequency (Hz) at `timestep`.
+    pub fn hz(&self, timestep: usize) -> f32 {
+        match &self.series {
+            Some(series) => series[timestep % series.len()],
+            None => {
+                self.nominal_hz
+                    + self
+                        .disturbances
+                        .iter()
+                        .map(|d| d.deviation_at(timestep))
+                        .sum::<f32>()
+            }
+        }
+    }
+}
+
+/// Parses comma-separated `start-end:deviation_hz` disturbances,
+/// e.g. `"600-900:-0.3, 2000-2100:0.1"`.
+pub fn parse_frequency_disturbances(raw: &str) -> Result<Vec<FrequencyDisturbance>, String> {
+    let mut disturbances = Vec::new();
+    for item in raw.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        let (window, deviation) = item
+            .split_once(':')
+            .ok_or_else(|| format!("expected `start-end:deviation_hz`, got `{item}`"))?;
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| format!("expected `start-end:deviation_hz`, got `{item}`"))?;
+        let start_step = start
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid start step in `{item}`"))?;
+        let end_step = end
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid end step in `{item}`"))?;
+        let deviation_hz = deviation
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|hz| hz.is_finite())
+            .ok_or_else(|| format!("invalid deviation in `{item}`"))?;
+        if end_step <= start_step {
+            return Err(format!("end must be > start in `{item}`"));
+        }
+        disturbances.push(FrequencyDisturbance::new(
+            start_step,
+            end_step,
+            deviation_hz,
+        ));
+    }
+    Ok(disturbances)
+}
+
+/// Parses measured frequency (Hz) from CSV text, one row per timestep.
+///
+/// The last column is used and a header line is skipped.
+pub fn parse_frequency_csv(raw: &str) -> Result<Vec<f32>, String> {
+    parse_last_column_csv(raw, "frequency", "> 0", |hz| hz > 0.0 && hz.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disturbance_drops_and_recovers_linearly() {
+        let frequency = GridFrequency::new(60.0, vec![FrequencyDisturbance::new(10, 14, -0.4)]);
+        let hz: Vec<f32> = (9..15).map(|t| frequency.hz(t)).collect();
+        assert_eq!(hz, vec![60.0, 59.6, 59.7, 59.8, 59.9, 60.0]);
+    }
+
+    #[test]
+    fn parses_disturbances_and_csv() {
+        let parsed = parse_frequency_disturbances("600-900:-0.3, 2000-2100:0.1").expect("valid");
+        assert_eq!(parsed[0], FrequencyDisturbance::new(600, 900, -0.3));
+        assert_eq!(parsed[1].deviation_hz, 0.1);
+        assert!(parse_frequency_disturbances("600-900").is_err());
+        assert!(parse_frequency_disturbances("900-600:-0.3").is_err());
+
+        let series = parse_frequency_csv("timestep,hz\n0,59.98\n1,60.01\n").expect("valid");
+        let frequency = GridFrequency::new(60.0, Vec::new()).with_series(series);
+        assert_eq!(frequency.hz(2), 59.98);
+        assert!(parse_frequency_csv("0\n").is_err());
+    }
+}
@@ -1,5 +1,16 @@
 pub mod clock;
+pub mod complex;
 pub mod controller;
+pub mod dr_allocation;
+pub mod envelope;
 pub mod event;
 pub mod feeder;
+pub mod frequency;
+pub mod outage;
+pub mod power_flow;
+pub mod rebound;
+pub mod regulation;
 pub mod schedule;
+pub mod topology;
+pub mod transformer;
+pub mod weather;
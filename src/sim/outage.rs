@@ -0,0 +1,169 @@
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+
+/// Loss of the upstream grid; the site runs islanded while active.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridOutage {
+    /// Start timestep (inclusive).
+    pub start_step: usize,
+    /// End timestep (exclusive).
+    pub end_step: usize,
+}
+
+impl GridOutage {
+    pub fn new(start_step: usize, end_step: usize) -> Self {
+        assert!(start_step < end_step);
+
+        Self {
+            start_step,
+            end_step,
+        }
+    }
+
+    pub fn is_active(&self, timestep: usize) -> bool {
+        timestep >= self.start_step && timestep < self.end_step
+    }
+}
+
+/// Grid outages of a run, sorted by start and non-overlapping.
+#[derive(Debug, Clone, Default)]
+pub struct OutageSchedule {
+    outages: Vec<GridOutage>,
+}
+
+impl OutageSchedule {
+    /// Builds a schedule from outages in any order; overlapping or adjacent
+    /// outages are merged.
+    pub fn new(mut outages: Vec<GridOutage>) -> Self {
+        outages.sort_by_key(|outage| outage.start_step);
+        let mut merged: Vec<GridOutage> = Vec::with_capacity(outages.len());
+        for outage in outages {
+            match merged.last_mut() {
+                Some(last) if outage.start_step <= last.end_step => {
+                    last.end_step = last.end_step.max(outage.end_step);
+                }
+                _ => merged.push(outage),
+            }
+        }
+        Self { outages: merged }
+    }
+
+    /// Adds random outages over `total_steps`.
+    ///
+    /// Outages start as a Poisson process with `rate_per_day` expected starts
+    /// per day and last an exponentially distributed number of steps with
+    /// mean `mean_duration_steps` (at least one step).
+    pub fn with_stochastic(
+        self,
+        rate_per_day: f32,
+        mean_duration_steps: f32,
+        steps_per_day: usize,
+        total_steps: usize,
+        seed: u64,
+    ) -> Self {
+        assert!(rate_per_day >= 0.0);
+        assert!(mean_duration_steps >= 1.0);
+        assert!(steps_per_day > 0);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let start_probability = (rate_per_day / steps_per_day as f32).min(1.0);
+        let mut outages = self.outages;
+        let mut t = 0;
+        while t < total_steps {
+            if rng.random::<f32>() < start_probability {
+                let u: f32 = rng.random_range(f32::EPSILON..1.0);
+                let duration = ((-mean_duration_steps * u.ln()).round() as usize).max(1);
+                let end_step = (t + duration).min(total_steps);
+                outages.push(GridOutage::new(t, end_step));
+                t = end_step;
+            } else {
+                t += 1;
+            }
+        }
+        Self::new(outages)
+    }
+
+    pub fn outages(&self) -> &[GridOutage] {
+        &self.outages
+    }
+
+    /// Index of the outage active at `timestep`, if any.
+    pub fn active_index(&self, timestep: usize) -> Option<usize> {
+        self.outages
+            .iter()
+            .position(|outage| outage.is_active(timestep))
+    }
+}
+
+/// Parses outage windows written as `start-end` step ranges separated by
+/// commas, e.g. `"10-14, 30-34"`. End steps are exclusive.
+pub fn parse_outage_windows(raw: &str) -> Result<Vec<GridOutage>, String> {
+    let mut outages = Vec::new();
+    for window in raw.split(',').map(str::trim).filter(|w| !w.is_empty()) {
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| format!("expected `start-end`, got `{window}`"))?;
+        let start_step = start
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid start step in `{window}`"))?;
+        let end_step = end
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid end step in `{window}`"))?;
+        if end_step <= start_step {
+            return Err(format!("end must be > start in `{window}`"));
+        }
+        outages.push(GridOutage::new(start_step, end_step));
+    }
+    Ok(outages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_outages_are_merged() {
+        let schedule = OutageSchedule::new(vec![
+            GridOutage::new(10, 12),
+            GridOutage::new(2, 4),
+            GridOutage::new(11, 15),
+        ]);
+        assert_eq!(
+            schedule.outages(),
+            &[GridOutage::new(2, 4), GridOutage::new(10, 15)]
+        );
+        assert_eq!(schedule.active_index(3), Some(0));
+        assert_eq!(schedule.active_index(14), Some(1));
+        assert_eq!(schedule.active_index(15), None);
+    }
+
+    #[test]
+    fn stochastic_outages_follow_rate_and_stay_in_run() {
+        let total_steps = 24 * 365;
+        let schedule = OutageSchedule::default().with_stochastic(0.1, 4.0, 24, total_steps, 11);
+        let count = schedule.outages().len();
+        // About 36 starts expected over a year.
+        assert!((20..=55).contains(&count), "count {count}");
+        assert!(
+            schedule
+                .outages()
+                .iter()
+                .all(|outage| outage.end_step <= total_steps)
+        );
+        let none = OutageSchedule::default().with_stochastic(0.0, 4.0, 24, total_steps, 11);
+        assert!(none.outages().is_empty());
+    }
+
+    #[test]
+    fn parses_outage_windows() {
+        let outages = parse_outage_windows("10-14, 30-34").expect("windows should parse");
+        assert_eq!(
+            outages,
+            vec![GridOutage::new(10, 14), GridOutage::new(30, 34)]
+        );
+        assert!(parse_outage_windows("").expect("empty list").is_empty());
+        assert!(parse_outage_windows("5").is_err());
+        assert!(parse_outage_windows("6-6").is_err());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::sim::complex::Complex;
+
+/// Name of the root bus, held at 1.0 pu by the substation.
+pub const ROOT_BUS: &str = "substation";
+
+/// Base apparent power (kVA) of the per-unit system.
+const BASE_KVA: f64 = 1000.0;
+
+/// Sweeps before the solver gives up.
+const MAX_ITERATIONS: usize = 30;
+
+/// Largest voltage change (pu) between sweeps accepted as converged.
+const TOLERANCE_PU: f64 = 1e-9;
+
+/// A line of a radial network, from the bus nearer the substation to the bus
+/// it feeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub r_ohm: f32,
+    pub x_ohm: f32,
+    /// Thermal rating; `None` leaves the current unconstrained.
+    pub ampacity_a: Option<f32>,
+}
+
+/// A radial (tree-shaped) network fed from [`ROOT_BUS`], solved per phase as
+/// a balanced three-phase system.
+#[derive(Debug, Clone)]
+pub struct RadialNetwork {
+    base_kv: f32,
+    bus_names: Vec<String>,
+    lines: Vec<Line>,
+    /// Line indices ordered so every line comes after the line feeding it.
+    order: Vec<usize>,
+}
+
+/// Bus voltages, line currents and losses of one solved step.
+#[derive(Debug, Clone)]
+pub struct PowerFlowSolution {
+    pub bus_voltage_pu: Vec<f32>,
+    pub line_current_a: Vec<f32>,
+    pub line_loss_kw: Vec<f32>,
+    pub converged: bool,
+}
+
+impl PowerFlowSolution {
+    pub fn total_loss_kw(&self) -> f32 {
+        self.line_loss_kw.iter().sum()
+    }
+}
+
+impl RadialNetwork {
+    /// Network of `lines` between `bus_names`, where bus 0 is [`ROOT_BUS`]
+    /// and `base_kv` is the line-to-line nominal voltage.
+    ///
+    /// Fails unless every bus but the root is fed by exactly one line and
+    /// connected to the root.
+    pub fn new(base_kv: f32, bus_names: Vec<String>, lines: Vec<Line>) -> Result<Self, String> {
+        assert!(base_kv > 0.0);
+        assert_eq!(bus_names.first().map(String::as_str), Some(ROOT_BUS));
+
+        let mut feeding_line: Vec<Option<usize>> = vec![None; bus_names.len()];
+        for (idx, line) in lines.iter().enumerate() {
+            if line.to == 0 {
+                return Err(format!("line `{}` feeds `{ROOT_BUS}`", line.name));
+            }
+            if feeding_line[line.to].replace(idx).is_some() {
+                return Err(format!(
+                    "line `{}`: bus `{}` is fed by more than one line",
+                    line.name, bus_names[line.to]
+                ));
+            }
+        }
+
+        let mut order = Vec::with_capacity(lines.len());
+        let mut reached = vec![false; bus_names.len()];
+        reached[0] = true;
+        let mut frontier = vec![0];
+        while let Some(bus) = frontier.pop() {
+            for (idx, line) in lines.iter().enumerate() {
+                if line.from == bus && !reached[line.to] {
+                    reached[line.to] = true;
+                    order.push(idx);
+                    frontier.push(line.to);
+                }
+            }
+        }
+        if let Some(bus) = reached.iter().position(|reached| !reached) {
+            return Err(format!(
+                "bus `{}` is not connected to `{ROOT_BUS}`",
+                bus_names[bus]
+            ));
+        }
+
+        Ok(Self {
+            base_kv,
+            bus_names,
+            lines,
+            order,
+        })
+    }
+
+    pub fn bus_names(&self) -> &[String] {
+        &self.bus_names
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Solves the network for per-bus loads (kW and kVAR, positive for
+    /// consumption) by backward/forward sweep.
+    ///
+    /// Each sweep sums load currents toward the root, then drops voltage
+    /// along each line away from it, until bus voltages settle.
+    pub fn solve(&self, load_kw: &[f32], load_kvar: &[f32]) -> PowerFlowSolution {
+        assert_eq!(load_kw.len(), self.bus_names.len());
+        assert_eq!(load_kvar.len(), self.bus_names.len());
+
+        let base_kv = f64::from(self.base_kv);
+        let base_ohm = base_kv * base_kv * 1000.0 / BASE_KVA;
+        let base_a = BASE_KVA / (3f64.sqrt() * base_kv);
+        let impedance: Vec<Complex> = self
+            .lines
+            .iter()
+            .map(|line| {
+                Complex::new(
+                    f64::from(line.r_ohm) / base_ohm,
+                    f64::from(line.x_ohm) / base_ohm,
+                )
+            })
+            .collect();
+        let load: Vec<Complex> = load_kw
+            .iter()
+            .zip(load_kvar)
+            .map(|(p, q)| Complex::new(f64::from(*p) / BASE_KVA, f64::from(*q) / BASE_KVA))
+            .collect();
+
+        let mut voltage = vec![Complex::new(1.0, 0.0); self.bus_names.len()];
+        let mut current = vec![Complex::new(0.0, 0.0); self.lines.len()];
+        let mut converged = false;
+        for _ in 0..MAX_ITERATIONS {
+            // Backward: current drawn at each bus, I = conj(S / V), summed
+            // toward the root.
+            let mut bus_current: Vec<Complex> = load
+                .iter()
+                .zip(&voltage)
+                .map(|(s, v)| (*s / *v).conj())
+                .collect();
+            for &idx in self.order.iter().rev() {
+                let line = &self.lines[idx];
+                current[idx] = bus_current[line.to];
+                bus_current[line.from] = bus_current[line.from] + current[idx];
+            }
+
+            // Forward: voltage drop along each line from the root outward.
+            let mut max_change: f64 = 0.0;
+            for &idx in &self.order {
+                let line = &self.lines[idx];
+                let updated = voltage[line.from] - impedance[idx] * current[idx];
+                max_change = max_change.max((updated - voltage[line.to]).abs());
+                voltage[line.to] = updated;
+            }
+            if max_change < TOLERANCE_PU {
+                converged = true;
+                break;
+            }
+        }
+
+        PowerFlowSolution {
+            bus_voltage_pu: voltage.iter().map(|v| v.abs() as f32).collect(),
+            line_current_a: current.iter().map(|i| (i.abs() * base_a) as f32).collect(),
+            line_loss_kw: current
+                .iter()
+                .zip(&impedance)
+                .map(|(i, z)| (i.norm_sqr() * z.re * BASE_KVA) as f32)
+                .collect(),
+            converged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: usize, to: usize, r_ohm: f32) -> Line {
+        Line {
+            name: format!("L{to}"),
+            from,
+            to,
+            r_ohm,
+            x_ohm: 0.0,
+            ampacity_a: None,
+        }
+    }
+
+    fn buses(count: usize) -> Vec<String> {
+        std::iter::once(ROOT_BUS.to_string())
+            .chain((1..count).map(|b| format!("b{b}")))
+            .collect()
+    }
+
+    #[test]
+    fn single_line_matches_closed_form_solution() {
+        // 0.1 ohm on a 0.4 kV base is 0.625 pu; 10 kW is 0.01 pu, so
+        // V^2 - V + 0.00625 = 0.
+        let network = RadialNetwork::new(0.4, buses(2), vec![line(0, 1, 0.1)]).expect("radial");
+        let solution = network.solve(&[0.0, 10.0], &[0.0, 0.0]);
+        assert!(solution.converged);
+        let expected_v = (1.0 + (1.0f32 - 4.0 * 0.00625).sqrt()) / 2.0;
+        assert!((solution.bus_voltage_pu[1] - expected_v).abs() < 1e-5);
+        assert_eq!(solution.bus_voltage_pu[0], 1.0);
+        // I = P / V on a 1443 A base; losses I^2 R.
+        let current_pu = 0.01 / expected_v;
+        assert!((solution.line_current_a[0] - current_pu * 1443.376).abs() < 1e-2);
+        assert!((solution.total_loss_kw() - current_pu * current_pu * 0.625 * 1000.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn export_raises_voltage_along_the_feeder() {
+        // substation - b1 - b2, with b2 exporting solar.
+        let network = RadialNetwork::new(0.4, buses(3), vec![line(1, 2, 0.05), line(0, 1, 0.05)])
+            .expect("radial");
+        let solution = network.solve(&[0.0, 5.0, -40.0], &[0.0, 0.0, 0.0]);
+        assert!(solution.converged);
+        assert!(solution.bus_voltage_pu[2] > solution.bus_voltage_pu[1]);
+        assert!(solution.bus_voltage_pu[1] > 1.0);
+        // The line to b1 carries only the net export of both buses.
+        assert!(solution.line_current_a[1] < solution.line_current_a[0]);
+    }
+
+    #[test]
+    fn rejects_meshed_or_disconnected_networks() {
+        let err = RadialNetwork::new(0.4, buses(2), vec![line(0, 1, 0.1), line(0, 1, 0.1)])
+            .expect_err("two feeds");
+        assert!(err.contains("more than one line"));
+        let err = RadialNetwork::new(0.4, buses(3), vec![line(0, 1, 0.1), line(2, 2, 0.1)])
+            .expect_err("island");
+        assert!(err.contains("`b2` is not connected"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+/// How deferred load returns after a demand response event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReboundShape {
+    /// Spread evenly over the rebound window.
+    Flat,
+    /// Peak right after the event, falling linearly to zero.
+    #[default]
+    Decay,
+}
+
+impl ReboundShape {
+    /// Parses a shape name used in scenario files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "flat" => Some(Self::Flat),
+            "decay" => Some(Self::Decay),
+            _ => None,
+        }
+    }
+
+    /// Share of the returning energy in each step of an `n`-step window.
+    fn weights(self, n: usize) -> Vec<f32> {
+        let raw: Vec<f32> = match self {
+            Self::Flat => vec![1.0; n],
+            Self::Decay => (0..n).map(|i| (n - i) as f32).collect(),
+        };
+        let total: f32 = raw.iter().sum();
+        raw.iter().map(|w| w / total).collect()
+    }
+}
+
+/// Post-event rebound (snapback) of load shed during demand response.
+///
+/// Shed energy is deferred while an event is active. Once no event is active,
+/// `fraction` of it returns as extra load over the next `duration_steps`,
+/// distributed by `shape`. A fraction above one models thermal recovery that
+/// costs more energy than was shed.
+#[derive(Debug, Clone)]
+pub struct Rebound {
+    fraction: f32,
+    duration_steps: usize,
+    shape: ReboundShape,
+    dt_hours: f32,
+    deferred_kwh: f32,
+    pending_kw: VecDeque<f32>,
+}
+
+impl Rebound {
+    pub fn new(
+        fraction: f32,
+        duration_steps: usize,
+        shape: ReboundShape,
+        steps_per_day: usize,
+    ) -> Self {
+        assert!(fraction >= 0.0);
+        assert!(duration_steps > 0);
+        assert!(steps_per_day > 0);
+
+        Self {
+            fraction,
+            duration_steps,
+            shape,
+            dt_hours: 24.0 / steps_per_day as f32,
+            deferred_kwh: 0.0,
+            pending_kw: VecDeque::new(),
+        }
+    }
+
+    /// Records load shed during the current step.
+    pub fn defer(&mut self, shed_kw: f32) {
+        self.deferred_kwh += shed_kw.max(0.0) * self.fraction * self.dt_hours;
+    }
+
+    /// Rebound load for the current step.
+    ///
+    /// Deferred energy is released once `event_active` is false; energy
+    /// released earlier and still pending adds up with it.
+    pub fn next_kw(&mut self, event_active: bool) -> f32 {
+        if !event_active && self.deferred_kwh > 0.0 {
+            let kw = self.deferred_kwh / self.dt_hours;
+            for (step, weight) in self
+                .shape
+                .weights(self.duration_steps)
+                .into_iter()
+                .enumerate()
+            {
+                match self.pending_kw.get_mut(step) {
+                    Some(pending) => *pending += kw * weight,
+                    None => self.pending_kw.push_back(kw * weight),
+                }
+            }
+            self.deferred_kwh = 0.0;
+        }
+        self.pending_kw.pop_front().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deferred_energy_returns_after_event() {
+        let mut rebound = Rebound::new(1.0, 4, ReboundShape::Flat, 24);
+        assert_eq!(rebound.next_kw(true), 0.0);
+        rebound.defer(2.0);
+        assert_eq!(rebound.next_kw(true), 0.0);
+        rebound.defer(2.0);
+
+        // 4 kWh shed returns as 1 kW over 4 steps.
+        let kws: Vec<f32> = (0..5).map(|_| rebound.next_kw(false)).collect();
+        assert_eq!(kws, vec![1.0, 1.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn decay_shape_peaks_after_event_and_fraction_scales_energy() {
+        let mut rebound = Rebound::new(0.5, 4, ReboundShape::Decay, 24);
+        rebound.defer(10.0);
+        let kws: Vec<f32> = (0..4).map(|_| rebound.next_kw(false)).collect();
+        assert_eq!(kws, vec![2.0, 1.5, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn shape_names_parse() {
+        assert_eq!(ReboundShape::from_name("flat"), Some(ReboundShape::Flat));
+        assert_eq!(ReboundShape::from_name("decay"), Some(ReboundShape::Decay));
+        assert_eq!(ReboundShape::from_name("spike"), None);
+    }
+}
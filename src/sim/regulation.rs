@@ -0,0 +1,437 @@
+use std::collections::VecDeque;
+
+use rand::{RngExt, SeedableRng, rngs::StdRng};
+
+use crate::series::parse_last_column_csv;
+
+/// Longest response delay searched when scoring, in seconds.
+const MAX_DELAY_S: usize = 300;
+/// Resolution of the delay search, in seconds.
+const DELAY_STEP_S: usize = 10;
+/// Length of one scoring window, in seconds.
+const SCORE_WINDOW_S: usize = 3600;
+/// Shortest trailing window that is still scored at the end of a run.
+const MIN_SCORE_WINDOW_S: usize = 2 * MAX_DELAY_S;
+
+/// Kind of synthetic regulation signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RegulationSignalKind {
+    /// Slow signal for traditional resources; not energy neutral.
+    RegA,
+    /// Fast, roughly energy-neutral signal for storage.
+    #[default]
+    RegD,
+}
+
+impl RegulationSignalKind {
+    /// Parses a signal name used in scenario files.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "reg_a" => Some(Self::RegA),
+            "reg_d" => Some(Self::RegD),
+            _ => None,
+        }
+    }
+
+    /// Range of seconds the signal takes to move to a new level.
+    fn swing_s(self) -> (usize, usize) {
+        match self {
+            Self::RegA => (300, 900),
+            Self::RegD => (30, 120),
+        }
+    }
+}
+
+/// Where the per-second regulation signal comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegulationSignalSource {
+    Synthetic(RegulationSignalKind),
+    /// Recorded values in [-1, 1], one per second, repeated if shorter than the run.
+    Series(Vec<f32>),
+}
+
+/// Normalised regulation signal in [-1, 1]; positive asks for more output
+/// (battery discharge), negative for less (battery charge).
+#[derive(Debug)]
+struct RegulationSignal {
+    source: RegulationSignalSource,
+    rng: StdRng,
+    second: usize,
+    value: f32,
+    level: f32,
+    seconds_to_level: usize,
+}
+
+impl RegulationSignal {
+    fn new(source: RegulationSignalSource, seed: u64) -> Self {
+        Self {
+            source,
+            rng: StdRng::seed_from_u64(seed),
+            second: 0,
+            value: 0.0,
+            level: 0.0,
+            seconds_to_level: 0,
+        }
+    }
+
+    /// Signal for the next second.
+    ///
+    /// The synthetic signal ramps linearly toward random levels, each reached
+    /// after a random swing time, which gives the triangular shape of
+    /// dispatched regulation signals.
+    fn next(&mut self) -> f32 {
+        let second = self.second;
+        self.second += 1;
+        let kind = match &self.source {
+            RegulationSignalSource::Series(values) => {
+                self.value = values[second % values.len()];
+                return self.value;
+            }
+            RegulationSignalSource::Synthetic(kind) => *kind,
+        };
+        if self.seconds_to_level == 0 {
+            let (min_s, max_s) = kind.swing_s();
+            self.level = self.rng.random_range(-1.0..=1.0);
+            self.seconds_to_level = self.rng.random_range(min_s..=max_s);
+        }
+        self.value += (self.level - self.value) / self.seconds_to_level as f32;
+        self.seconds_to_level -= 1;
+        self.value
+    }
+}
+
+/// Parses a regulation signal CSV: one value in [-1, 1] per line, one line
+/// per second. The last column is used and a header line is skipped.
+pub fn parse_regulation_signal_csv(raw: &str) -> Result<Vec<f32>, String> {
+    parse_last_column_csv(raw, "signal", "in [-1, 1]", |value| {
+        (-1.0..=1.0).contains(&value)
+    })
+}
+
+/// PJM-style performance score components, each in [0, 1].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RegulationScore {
+    /// Highest correlation between signal and delayed response.
+    pub correlation: f32,
+    /// How quickly the response follows: 1 with no delay, 0 at five minutes.
+    pub delay: f32,
+    /// One minus the mean absolute error relative to the mean absolute signal.
+    pub precision: f32,
+}
+
+impl RegulationScore {
+    /// Equal-weight average of the three components.
+    pub fn composite(&self) -> f32 {
+        (self.correlation + self.delay + self.precision) / 3.0
+    }
+}
+
+/// Scores a normalised `response` against `signal`, both one value per second.
+///
+/// The response is shifted by 0 to 5 minutes in 10 s steps; the shift with
+/// the highest correlation sets the correlation and delay scores. Precision
+/// compares the unshifted series.
+pub fn score_window(signal: &[f32], response: &[f32]) -> RegulationScore {
+    let n = signal.len().min(response.len());
+    let (signal, response) = (&signal[..n], &response[..n]);
+
+    let mut best = (0.0_f32, 0_usize);
+    for delay_s in (0..=MAX_DELAY_S.min(n.saturating_sub(2))).step_by(DELAY_STEP_S) {
+        let corr = correlation(&signal[..n - delay_s], &response[delay_s..]);
+        if corr > best.0 {
+            best = (corr, delay_s);
+        }
+    }
+    let (correlation, delay_s) = best;
+
+    let mean_abs_signal = signal.iter().map(|s| s.abs()).sum::<f32>() / n.max(1) as f32;
+    let mean_abs_error = signal
+        .iter()
+        .zip(response)
+        .map(|(s, r)| (r - s).abs())
+        .sum::<f32>()
+        / n.max(1) as f32;
+    let precision = if mean_abs_signal > 0.0 {
+        1.0 - mean_abs_error / mean_abs_signal
+    } else if mean_abs_error > 0.0 {
+        0.0
+    } else {
+        1.0
+    };
+
+    RegulationScore {
+        correlation,
+        delay: if correlation > 0.0 {
+            (MAX_DELAY_S - delay_s) as f32 / MAX_DELAY_S as f32
+        } else {
+            0.0
+        },
+        precision: precision.clamp(0.0, 1.0),
+    }
+}
+
+/// Pearson correlation; 0 if either series is constant.
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let mean_a = a.iter().sum::<f32>() / n;
+    let mean_b = b.iter().sum::<f32>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a) * (x - mean_a);
+        var_b += (y - mean_b) * (y - mean_b);
+    }
+    if var_a <= f32::EPSILON || var_b <= f32::EPSILON {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+/// Offered regulation capacity, response limits and market prices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegulationParams {
+    /// Capacity offered symmetrically around the base point (kW).
+    pub capacity_kw: f32,
+    /// Seconds between the signal and the start of the response.
+    pub response_delay_s: usize,
+    /// Largest change of the response per second (kW); 0 for no limit.
+    pub ramp_kw_per_s: f32,
+    /// Capacity payment per kW per hour, scaled by the score.
+    pub capacity_price: f32,
+    /// Performance payment per kW of signal mileage, scaled by the score.
+    pub performance_price: f32,
+}
+
+/// Scores and payments of a regulation service over a run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RegulationOutcome {
+    pub capacity_kw: f32,
+    /// Hours the service was provided.
+    pub hours: f32,
+    /// Average of the hourly scores.
+    pub score: RegulationScore,
+    /// Total movement of the normalised signal, `sum |signal(t) - signal(t-1)|`.
+    pub mileage: f32,
+    pub capacity_payment: f32,
+    pub performance_payment: f32,
+}
+
+impl RegulationOutcome {
+    pub fn total_payment(&self) -> f32 {
+        self.capacity_payment + self.performance_payment
+    }
+}
+
+/// A battery following a regulation signal around its base point.
+///
+/// Each simulation step is played out second by second: the response
+/// chases the delayed signal times the offered capacity, limited by ramp
+/// rate and by the power the battery has left around its base point.
+/// Performance is scored per hour of service.
+#[derive(Debug)]
+pub struct RegulationService {
+    params: RegulationParams,
+    signal: RegulationSignal,
+    delayed: VecDeque<f32>,
+    previous_signal: Option<f32>,
+    response_kw: f32,
+    window_signal: Vec<f32>,
+    window_response: Vec<f32>,
+    window_mileage: f32,
+    scored: Vec<(RegulationScore, f32)>,
+    seconds: usize,
+    mileage: f32,
+    capacity_payment: f32,
+    performance_payment: f32,
+}
+
+impl RegulationService {
+    pub fn new(params: RegulationParams, source: RegulationSignalSource, seed: u64) -> Self {
+        assert!(params.capacity_kw > 0.0);
+        Self {
+            params,
+            signal: RegulationSignal::new(source, seed),
+            delayed: VecDeque::new(),
+            previous_signal: None,
+            response_kw: 0.0,
+            window_signal: Vec::with_capacity(SCORE_WINDOW_S),
+            window_response: Vec::with_capacity(SCORE_WINDOW_S),
+            window_mileage: 0.0,
+            scored: Vec::new(),
+            seconds: 0,
+            mileage: 0.0,
+            capacity_payment: 0.0,
+            performance_payment: 0.0,
+        }
+    }
+
+    pub fn capacity_kw(&self) -> f32 {
+        self.params.capacity_kw
+    }
+
+    /// Follows the signal for `seconds` and returns the mean response (kW,
+    /// positive discharging) to add to the battery base point.
+    ///
+    /// `discharge_headroom_kw` and `charge_headroom_kw` are what the battery
+    /// can still discharge and charge beyond its base point.
+    pub fn follow(
+        &mut self,
+        seconds: usize,
+        discharge_headroom_kw: f32,
+        charge_headroom_kw: f32,
+    ) -> f32 {
+        let capacity_kw = self.params.capacity_kw;
+        let mut sum_kw = 0.0;
+        for _ in 0..seconds {
+            let signal = self.signal.next();
+            let step_mileage = self
+                .previous_signal
+                .map_or(0.0, |prev| (signal - prev).abs());
+            self.previous_signal = Some(signal);
+            self.window_mileage += step_mileage;
+            self.mileage += step_mileage;
+
+            self.delayed.push_back(signal);
+            let commanded = if self.delayed.len() > self.params.response_delay_s {
+                self.delayed.pop_front().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            let mut target_kw = commanded * capacity_kw;
+            if self.params.ramp_kw_per_s > 0.0 {
+                let ramp_kw = self.params.ramp_kw_per_s;
+                target_kw = target_kw.clamp(self.response_kw - ramp_kw, self.response_kw + ramp_kw);
+            }
+            self.response_kw =
+                target_kw.clamp(-charge_headroom_kw.max(0.0), discharge_headroom_kw.max(0.0));
+            sum_kw += self.response_kw;
+
+            self.window_signal.push(signal);
+            self.window_response.push(self.response_kw / capacity_kw);
+            self.seconds += 1;
+            if self.window_signal.len() == SCORE_WINDOW_S {
+                self.close_window();
+            }
+        }
+        if seconds > 0 {
+            sum_kw / seconds as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Scores the window collected so far and credits its payments.
+    fn close_window(&mut self) {
+        let hours = self.window_signal.len() as f32 / 3600.0;
+        let score = score_window(&self.window_signal, &self.window_response);
+        let performance = score.composite();
+        self.capacity_payment +=
+            self.params.capacity_kw * hours * performance * self.params.capacity_price;
+        self.performance_payment += self.params.capacity_kw
+            * self.window_mileage
+            * performance
+            * self.params.performance_price;
+        self.scored.push((score, hours));
+        self.window_signal.clear();
+        self.window_response.clear();
+        self.window_mileage = 0.0;
+    }
+
+    /// Scores the trailing partial window and returns the run's outcome.
+    pub fn finish(&mut self) -> RegulationOutcome {
+        if self.window_signal.len() >= MIN_SCORE_WINDOW_S {
+            self.close_window();
+        }
+        let scored_hours: f32 = self.scored.iter().map(|(_, hours)| hours).sum();
+        let mut score = RegulationScore::default();
+        if scored_hours > 0.0 {
+            for (window, hours) in &self.scored {
+                score.correlation += window.correlation * hours / scored_hours;
+                score.delay += window.delay * hours / scored_hours;
+                score.precision += window.precision * hours / scored_hours;
+            }
+        }
+        RegulationOutcome {
+            capacity_kw: self.params.capacity_kw,
+            hours: self.seconds as f32 / 3600.0,
+            score,
+            mileage: self.mileage,
+            capacity_payment: self.capacity_payment,
+            performance_payment: self.performance_payment,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> RegulationParams {
+        RegulationParams {
+            capacity_kw: 2.0,
+            response_delay_s: 0,
+            ramp_kw_per_s: 0.0,
+            capacity_price: 0.01,
+            performance_price: 0.001,
+        }
+    }
+
+    #[test]
+    fn perfect_response_scores_one() {
+        let signal: Vec<f32> = (0..3600).map(|s| (s as f32 / 120.0).sin()).collect();
+        let score = score_window(&signal, &signal);
+        assert!((score.correlation - 1.0).abs() < 1e-4);
+        assert_eq!(score.delay, 1.0);
+        assert_eq!(score.precision, 1.0);
+    }
+
+    #[test]
+    fn delayed_response_loses_delay_score() {
+        let signal: Vec<f32> = (0..3600).map(|s| (s as f32 / 120.0).sin()).collect();
+        let mut response = vec![0.0; 60];
+        response.extend_from_slice(&signal[..3540]);
+        let score = score_window(&signal, &response);
+        assert!(score.correlation > 0.99);
+        assert!((score.delay - 0.8).abs() < 1e-6);
+        assert!(score.precision < 1.0);
+    }
+
+    #[test]
+    fn headroom_limits_hurt_precision() {
+        let service = || {
+            RegulationService::new(
+                params(),
+                RegulationSignalSource::Synthetic(RegulationSignalKind::RegD),
+                7,
+            )
+        };
+        let (mut free, mut limited) = (service(), service());
+        for _ in 0..2 {
+            free.follow(1800, 2.0, 2.0);
+            // No discharge headroom: only the charging half can be followed.
+            let mean_kw = limited.follow(1800, 0.0, 2.0);
+            assert!(mean_kw <= 0.0);
+        }
+        let free = free.finish();
+        let limited = limited.finish();
+        assert_eq!(free.hours, 1.0);
+        assert!(free.score.composite() > 0.99);
+        assert!(limited.score.precision < free.score.precision);
+        assert!(limited.total_payment() < free.total_payment());
+        assert!(free.mileage > 10.0);
+    }
+
+    #[test]
+    fn series_signal_repeats_and_csv_parses() {
+        let values = parse_regulation_signal_csv("signal\n0.5\n-1.0\n").expect("valid csv");
+        assert_eq!(values, vec![0.5, -1.0]);
+        let mut signal = RegulationSignal::new(RegulationSignalSource::Series(values), 0);
+        let seconds: Vec<f32> = (0..3).map(|_| signal.next()).collect();
+        assert_eq!(seconds, vec![0.5, -1.0, 0.5]);
+        assert!(parse_regulation_signal_csv("1.5\n").is_err());
+        assert_eq!(
+            RegulationSignalKind::from_name("reg_a"),
+            Some(RegulationSignalKind::RegA)
+        );
+    }
+}
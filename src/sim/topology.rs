@@ -0,0 +1,187 @@
+/// Flow (kW) beyond a limit that is still accepted as within it.
+const LIMIT_TOLERANCE_KW: f32 = 1e-3;
+
+/// Import and export limits of one node of the network.
+///
+/// Limits are magnitudes in kW; `f32::INFINITY` leaves a direction
+/// unconstrained.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeLimits {
+    pub import_kw: f32,
+    pub export_kw: f32,
+}
+
+impl NodeLimits {
+    pub fn new(import_kw: f32, export_kw: f32) -> Self {
+        assert!(import_kw >= 0.0);
+        assert!(export_kw >= 0.0);
+
+        Self {
+            import_kw,
+            export_kw,
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(f32::INFINITY, f32::INFINITY)
+    }
+
+    /// Tighter of the two limits in each direction.
+    pub fn tightest(self, other: NodeLimits) -> Self {
+        Self::new(
+            self.import_kw.min(other.import_kw),
+            self.export_kw.min(other.export_kw),
+        )
+    }
+
+    /// Signed flow beyond the limits: positive above the import limit,
+    /// negative beyond the export limit, zero within them.
+    pub fn excess_kw(&self, net_kw: f32) -> f32 {
+        if net_kw > self.import_kw + LIMIT_TOLERANCE_KW {
+            net_kw - self.import_kw
+        } else if net_kw < -self.export_kw - LIMIT_TOLERANCE_KW {
+            net_kw + self.export_kw
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Level of a node in the site → feeder → substation hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeLevel {
+    Site,
+    Feeder,
+    Substation,
+}
+
+impl NodeLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Site => "site",
+            Self::Feeder => "feeder",
+            Self::Substation => "substation",
+        }
+    }
+}
+
+/// Net flow through one node over the run, with the limits it is held to.
+#[derive(Debug, Clone)]
+pub struct NodeFlow {
+    pub name: String,
+    pub level: NodeLevel,
+    pub limits: NodeLimits,
+    /// Net flow per step (kW, positive for import from upstream).
+    pub net_kw: Vec<f32>,
+}
+
+impl NodeFlow {
+    /// Node whose flow is the sum of `children` at each step.
+    pub fn sum_of(
+        name: &str,
+        level: NodeLevel,
+        limits: NodeLimits,
+        children: &[&NodeFlow],
+    ) -> Self {
+        let steps = children.iter().map(|c| c.net_kw.len()).max().unwrap_or(0);
+        let net_kw = (0..steps)
+            .map(|t| children.iter().filter_map(|c| c.net_kw.get(t)).sum())
+            .collect();
+        Self {
+            name: name.to_string(),
+            level,
+            limits,
+            net_kw,
+        }
+    }
+
+    /// Steps above the import limit.
+    pub fn import_violations(&self) -> usize {
+        self.net_kw
+            .iter()
+            .filter(|kw| self.limits.excess_kw(**kw) > 0.0)
+            .count()
+    }
+
+    /// Steps beyond the export limit.
+    pub fn export_violations(&self) -> usize {
+        self.net_kw
+            .iter()
+            .filter(|kw| self.limits.excess_kw(**kw) < 0.0)
+            .count()
+    }
+
+    /// Highest import (kW), zero if the node never imports.
+    pub fn peak_import_kw(&self) -> f32 {
+        self.net_kw.iter().fold(0.0, |peak, kw| peak.max(*kw))
+    }
+
+    /// Highest export (kW), zero if the node never exports.
+    pub fn peak_export_kw(&self) -> f32 {
+        self.net_kw.iter().fold(0.0, |peak, kw| peak.max(-kw))
+    }
+}
+
+/// Splits `excess_kw` at a parent node across its children in proportion to
+/// their flow in the same direction, so the children that cause an overload
+/// relieve it.
+///
+/// Returns the reduction (kW, as a magnitude) asked of each child.
+pub fn share_excess_kw(excess_kw: f32, child_net_kw: &[f32]) -> Vec<f32> {
+    let direction = excess_kw.signum();
+    let weights: Vec<f32> = child_net_kw
+        .iter()
+        .map(|kw| (kw * direction).max(0.0))
+        .collect();
+    let total: f32 = weights.iter().sum();
+    if excess_kw == 0.0 || total <= 0.0 {
+        return vec![0.0; child_net_kw.len()];
+    }
+    weights
+        .iter()
+        .map(|weight| excess_kw.abs() * weight / total)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_up_flows_and_counts_violations_per_direction() {
+        let limits = NodeLimits::new(5.0, 2.0);
+        let a = NodeFlow {
+            name: "a".to_string(),
+            level: NodeLevel::Site,
+            limits,
+            net_kw: vec![3.0, -1.0, 1.0],
+        };
+        let b = NodeFlow {
+            net_kw: vec![3.0, -2.0, 1.0],
+            ..a.clone()
+        };
+        let feeder = NodeFlow::sum_of("f", NodeLevel::Feeder, limits, &[&a, &b]);
+        assert_eq!(feeder.net_kw, vec![6.0, -3.0, 2.0]);
+        assert_eq!(feeder.import_violations(), 1);
+        assert_eq!(feeder.export_violations(), 1);
+        assert_eq!(feeder.peak_import_kw(), 6.0);
+        assert_eq!(feeder.peak_export_kw(), 3.0);
+        assert_eq!(limits.excess_kw(6.0), 1.0);
+        assert_eq!(limits.excess_kw(-3.0), -1.0);
+        assert_eq!(NodeLimits::unlimited().excess_kw(1e9), 0.0);
+        assert_eq!(
+            limits.tightest(NodeLimits::new(8.0, 1.0)),
+            NodeLimits::new(5.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn excess_is_shared_by_children_flowing_the_same_way() {
+        assert_eq!(share_excess_kw(2.0, &[3.0, 1.0, -2.0]), vec![1.5, 0.5, 0.0]);
+        assert_eq!(
+            share_excess_kw(-1.0, &[3.0, -1.0, -3.0]),
+            vec![0.0, 0.25, 0.75]
+        );
+        assert_eq!(share_excess_kw(0.0, &[3.0]), vec![0.0]);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::devices::types::gaussian_noise;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Synthetic daily ambient temperature profile.
+///
+/// Temperature follows a cosine around `mean_c` with its maximum at
+/// `peak_hour` (mid-afternoon by default) and minimum twelve hours later.
+/// With daily variation each day's mean is shifted by its own random offset,
+/// so some days run warmer than others.
+#[derive(Debug, Clone)]
+pub struct AmbientTemperature {
+    /// Daily mean temperature in °C.
+    pub mean_c: f32,
+    /// Half of the daily peak-to-trough swing in °C.
+    pub amplitude_c: f32,
+    /// Hour of day with the highest temperature.
+    pub peak_hour: f32,
+    /// Number of simulation steps per day.
+    pub steps_per_day: usize,
+    /// Offset of each day's mean from `mean_c` in °C; days past the end use none.
+    day_offsets_c: Vec<f32>,
+}
+
+impl AmbientTemperature {
+    pub fn new(mean_c: f32, amplitude_c: f32, steps_per_day: usize) -> Self {
+        assert!(amplitude_c >= 0.0);
+        assert!(steps_per_day > 0);
+
+        Self {
+            mean_c,
+            amplitude_c,
+            peak_hour: 15.0,
+            steps_per_day,
+            day_offsets_c: Vec::new(),
+        }
+    }
+
+    /// Shifts the mean of each of the first `days` days by a normal offset
+    /// with standard deviation `day_std_c`.
+    pub fn with_daily_variation(mut self, day_std_c: f32, days: usize, seed: u64) -> Self {
+        assert!(day_std_c >= 0.0);
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        self.day_offsets_c = (0..days)
+            .map(|_| gaussian_noise(&mut rng, day_std_c))
+            .collect();
+        self
+    }
+
+    /// Ambient temperature in °C at `timestep`.
+    pub fn temp_c(&self, timestep: usize) -> f32 {
+        let hour = (timestep % self.steps_per_day) as f32 * 24.0 / self.steps_per_day as f32;
+        let angle = 2.0 * std::f32::consts::PI * (hour - self.peak_hour) / 24.0;
+        let day_offset_c = self
+            .day_offsets_c
+            .get(timestep / self.steps_per_day)
+            .copied()
+            .unwrap_or(0.0);
+        self.mean_c + day_offset_c + self.amplitude_c * angle.cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AmbientTemperature;
+
+    #[test]
+    fn peaks_mid_afternoon_and_bottoms_out_overnight() {
+        let ambient = AmbientTemperature::new(20.0, 5.0, 24);
+        assert!((ambient.temp_c(15) - 25.0).abs() < 1e-4);
+        assert!((ambient.temp_c(3) - 15.0).abs() < 1e-4);
+        assert_eq!(ambient.temp_c(15), ambient.temp_c(39));
+    }
+
+    #[test]
+    fn daily_variation_shifts_whole_days() {
+        let ambient = AmbientTemperature::new(20.0, 5.0, 24).with_daily_variation(3.0, 3, 7);
+        let shift = |day: usize| ambient.temp_c(day * 24 + 15) - 25.0;
+        assert!((ambient.temp_c(3) - 15.0 - shift(0)).abs() < 1e-4);
+        assert_ne!(shift(0), shift(1));
+        assert_ne!(shift(1), shift(2));
+        assert_eq!(ambient.temp_c(3 * 24 + 15), 25.0);
+    }
+}
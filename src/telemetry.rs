@@ -1,9 +1,13 @@
+use crate::runner::TopologyResult;
+use crate::scenario::Topology;
 use serde::Serialize;
 use std::fs::File;
 use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
-pub const TELEMETRY_SCHEMA_V1_HEADER: &str = "timestep,time_hr,target_kw,feeder_kw,tracking_error_kw,baseload_kw,solar_kw,ev_requested_kw,ev_dispatched_kw,battery_kw,battery_soc,dr_requested_kw,dr_achieved_kw,limit_ok";
+pub const TELEMETRY_SCHEMA_V1_HEADER: &str = "timestep,time_hr,target_kw,feeder_kw,tracking_error_kw,baseload_kw,solar_kw,ev_requested_kw,ev_dispatched_kw,battery_kw,battery_soc,dr_requested_kw,dr_achieved_kw,limit_ok,ev_hub_dispatched_kw,ev_hub_queued,ev_unmet_kwh,ambient_temp_c,battery_temp_c,battery_cmd_shortfall_kw,ev_cmd_shortfall_kw,feeder_kvar,feeder_pf,voltage_pu,solar_kvar,battery_kvar,solar_available_kw,solar_curtailed_kw,wind_kw,wind_speed_ms,wind_curtailed_kw,genset_kw,genset_fuel_l,islanded,unserved_baseload_kw,unserved_ev_kw,dr_prep_kw,rebound_kw,dr_ev_kw,dr_ev_hub_kw,dr_baseload_kw,dr_battery_kw,dr_genset_kw,regulation_kw,frequency_hz,droop_battery_kw,droop_ev_kw,phase_a_kw,phase_b_kw,phase_c_kw,voltage_unbalance_pct,current_unbalance_pct,transformer_loading_pct,transformer_top_oil_c,transformer_hot_spot_c,transformer_aging_factor,import_limit_kw,export_limit_kw,dr_kind,battery_capacity_kwh,hvac_kw,indoor_temp_c,dr_hvac_kw";
+
+pub const TOPOLOGY_SCHEMA_V1_HEADER: &str = "timestep,time_hr,node,level,net_kw,import_limit_kw,export_limit_kw,limit_ok,voltage_pu,current_a,loss_kw";
 
 #[derive(Clone, Debug, Serialize)]
 pub struct TelemetryRow {
@@ -21,6 +25,57 @@ pub struct TelemetryRow {
     pub dr_requested_kw: f32,
     pub dr_achieved_kw: f32,
     pub limit_ok: bool,
+    pub ev_hub_dispatched_kw: f32,
+    pub ev_hub_queued: usize,
+    pub ev_unmet_kwh: f32,
+    pub ambient_temp_c: f32,
+    pub battery_temp_c: f32,
+    pub battery_cmd_shortfall_kw: f32,
+    pub ev_cmd_shortfall_kw: f32,
+    pub feeder_kvar: f32,
+    pub feeder_pf: f32,
+    pub voltage_pu: f32,
+    pub solar_kvar: f32,
+    pub battery_kvar: f32,
+    pub solar_available_kw: f32,
+    pub solar_curtailed_kw: f32,
+    pub wind_kw: f32,
+    pub wind_speed_ms: f32,
+    pub wind_curtailed_kw: f32,
+    pub genset_kw: f32,
+    pub genset_fuel_l: f32,
+    pub islanded: bool,
+    pub unserved_baseload_kw: f32,
+    pub unserved_ev_kw: f32,
+    pub dr_prep_kw: f32,
+    pub rebound_kw: f32,
+    pub dr_ev_kw: f32,
+    pub dr_ev_hub_kw: f32,
+    pub dr_baseload_kw: f32,
+    pub dr_battery_kw: f32,
+    pub dr_genset_kw: f32,
+    pub regulation_kw: f32,
+    pub frequency_hz: f32,
+    pub droop_battery_kw: f32,
+    pub droop_ev_kw: f32,
+    pub phase_a_kw: f32,
+    pub phase_b_kw: f32,
+    pub phase_c_kw: f32,
+    pub voltage_unbalance_pct: f32,
+    pub current_unbalance_pct: f32,
+    pub transformer_loading_pct: f32,
+    pub transformer_top_oil_c: f32,
+    pub transformer_hot_spot_c: f32,
+    pub transformer_aging_factor: f32,
+    pub import_limit_kw: f32,
+    pub export_limit_kw: f32,
+    /// Kind of the active DR event (`shed`, `take` or `target`), empty if none.
+    pub dr_kind: &'static str,
+    pub battery_capacity_kwh: f32,
+    pub hvac_kw: f32,
+    /// Indoor temperature of the cooled houses; ambient when there is no HVAC.
+    pub indoor_temp_c: f32,
+    pub dr_hvac_kw: f32,
 }
 
 pub fn write_telemetry_csv<W: Write>(writer: &mut W, rows: &[TelemetryRow]) -> io::Result<()> {
@@ -28,7 +83,7 @@ pub fn write_telemetry_csv<W: Write>(writer: &mut W, rows: &[TelemetryRow]) -> i
     for row in rows {
         writeln!(
             writer,
-            "{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{}",
+            "{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{},{:.6},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{:.6},{},{:.6},{:.6},{:.6},{:.6}",
             row.timestep,
             row.time_hr,
             row.target_kw,
@@ -42,7 +97,56 @@ pub fn write_telemetry_csv<W: Write>(writer: &mut W, rows: &[TelemetryRow]) -> i
             row.battery_soc,
             row.dr_requested_kw,
             row.dr_achieved_kw,
-            row.limit_ok
+            row.limit_ok,
+            row.ev_hub_dispatched_kw,
+            row.ev_hub_queued,
+            row.ev_unmet_kwh,
+            row.ambient_temp_c,
+            row.battery_temp_c,
+            row.battery_cmd_shortfall_kw,
+            row.ev_cmd_shortfall_kw,
+            row.feeder_kvar,
+            row.feeder_pf,
+            row.voltage_pu,
+            row.solar_kvar,
+            row.battery_kvar,
+            row.solar_available_kw,
+            row.solar_curtailed_kw,
+            row.wind_kw,
+            row.wind_speed_ms,
+            row.wind_curtailed_kw,
+            row.genset_kw,
+            row.genset_fuel_l,
+            row.islanded,
+            row.unserved_baseload_kw,
+            row.unserved_ev_kw,
+            row.dr_prep_kw,
+            row.rebound_kw,
+            row.dr_ev_kw,
+            row.dr_ev_hub_kw,
+            row.dr_baseload_kw,
+            row.dr_battery_kw,
+            row.dr_genset_kw,
+            row.regulation_kw,
+            row.frequency_hz,
+            row.droop_battery_kw,
+            row.droop_ev_kw,
+            row.phase_a_kw,
+            row.phase_b_kw,
+            row.phase_c_kw,
+            row.voltage_unbalance_pct,
+            row.current_unbalance_pct,
+            row.transformer_loading_pct,
+            row.transformer_top_oil_c,
+            row.transformer_hot_spot_c,
+            row.transformer_aging_factor,
+            row.import_limit_kw,
+            row.export_limit_kw,
+            row.dr_kind,
+            row.battery_capacity_kwh,
+            row.hvac_kw,
+            row.indoor_temp_c,
+            row.dr_hvac_kw
         )?;
     }
     Ok(())
@@ -55,11 +159,114 @@ pub fn write_telemetry_to_path(path: &Path, rows: &[TelemetryRow]) -> io::Result
     writer.flush()
 }
 
+/// Writes one row per node, bus and line for every `interval_steps`-th step.
+///
+/// Nodes are the substation, feeders and sites; buses (other than the
+/// substation bus) and lines follow when the topology defines lines. Columns
+/// that do not apply to a row are left empty, and unconstrained limits are
+/// written as `inf`.
+pub fn write_topology_csv<W: Write>(
+    writer: &mut W,
+    topology: &Topology,
+    result: &TopologyResult,
+    steps_per_day: usize,
+    interval_steps: usize,
+) -> io::Result<()> {
+    writeln!(writer, "{TOPOLOGY_SCHEMA_V1_HEADER}")?;
+    let dt_hr = 24.0 / steps_per_day as f32;
+    let site_buses: Vec<Option<usize>> = std::iter::once(Some(0))
+        .chain(result.feeder_flows.iter().map(|_| None))
+        .chain(topology.sites.iter().map(|site| site.bus))
+        .collect();
+    let nodes = result.nodes();
+    for t in (0..result.substation_flow.net_kw.len()).step_by(interval_steps) {
+        let time_hr = t as f32 * dt_hr;
+        let solution = result.power_flow.get(t);
+        for (node, bus) in nodes.iter().zip(&site_buses) {
+            let net_kw = node.net_kw[t];
+            let voltage_pu = bus.zip(solution).map(|(bus, s)| s.bus_voltage_pu[bus]);
+            writeln!(
+                writer,
+                "{},{:.6},{},{},{:.6},{:.6},{:.6},{},{},,",
+                t,
+                time_hr,
+                node.name,
+                node.level.name(),
+                net_kw,
+                node.limits.import_kw,
+                node.limits.export_kw,
+                node.limits.excess_kw(net_kw) == 0.0,
+                optional(voltage_pu)
+            )?;
+        }
+        let (Some(network), Some(solution)) = (topology.network.as_ref(), solution) else {
+            continue;
+        };
+        for (bus, name) in network.bus_names().iter().enumerate().skip(1) {
+            let load_kw: f32 = topology
+                .sites
+                .iter()
+                .zip(&result.site_flows)
+                .filter(|(site, _)| site.bus == Some(bus))
+                .map(|(_, flow)| flow.net_kw[t])
+                .sum();
+            let voltage_pu = solution.bus_voltage_pu[bus];
+            writeln!(
+                writer,
+                "{},{:.6},{},bus,{:.6},,,{},{:.6},,",
+                t,
+                time_hr,
+                name,
+                load_kw,
+                topology.voltage_within_limits(voltage_pu),
+                voltage_pu
+            )?;
+        }
+        for (idx, line) in network.lines().iter().enumerate() {
+            let current_a = solution.line_current_a[idx];
+            writeln!(
+                writer,
+                "{},{:.6},{},line,,,,{},,{:.6},{:.6}",
+                t,
+                time_hr,
+                line.name,
+                line.ampacity_a.is_none_or(|a| current_a <= a),
+                current_a,
+                solution.line_loss_kw[idx]
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats an optional value, leaving the CSV field empty when absent.
+fn optional(value: Option<f32>) -> String {
+    value.map_or(String::new(), |v| format!("{v:.6}"))
+}
+
+pub fn write_topology_to_path(
+    path: &Path,
+    topology: &Topology,
+    result: &TopologyResult,
+    steps_per_day: usize,
+    interval_steps: usize,
+) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    write_topology_csv(&mut writer, topology, result, steps_per_day, interval_steps)?;
+    writer.flush()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{TELEMETRY_SCHEMA_V1_HEADER, write_telemetry_csv};
-    use crate::runner::run_scenario;
-    use crate::scenario::ScenarioConfig;
+    use super::{
+        TELEMETRY_SCHEMA_V1_HEADER, TOPOLOGY_SCHEMA_V1_HEADER, write_telemetry_csv,
+        write_topology_csv,
+    };
+    use crate::runner::{run_scenario, run_topology};
+    use crate::scenario::{FeederSpec, ScenarioConfig, SiteSpec, Topology};
+    use crate::sim::power_flow::{Line, ROOT_BUS, RadialNetwork};
+    use crate::sim::topology::NodeLimits;
 
     #[test]
     fn telemetry_csv_has_schema_v1_header_and_rows_per_timestep() {
@@ -75,6 +282,20 @@ mod tests {
         assert_eq!(lines.count(), 24);
     }
 
+    #[test]
+    fn schema_v1_only_appends_columns_after_the_original_ones() {
+        // Consumers read v1 columns by position, so these must never move.
+        const ORIGINAL_COLUMNS: &str = "timestep,time_hr,target_kw,feeder_kw,tracking_error_kw,baseload_kw,solar_kw,ev_requested_kw,ev_dispatched_kw,battery_kw,battery_soc,dr_requested_kw,dr_achieved_kw,limit_ok,";
+        assert!(TELEMETRY_SCHEMA_V1_HEADER.starts_with(ORIGINAL_COLUMNS));
+
+        let result = run_scenario(&ScenarioConfig::default(), false);
+        let mut out = Vec::new();
+        write_telemetry_csv(&mut out, &result.telemetry).expect("csv export should succeed");
+        let csv = String::from_utf8(out).expect("csv output should be valid UTF-8");
+        let columns = TELEMETRY_SCHEMA_V1_HEADER.split(',').count();
+        assert!(csv.lines().all(|line| line.split(',').count() == columns));
+    }
+
     #[test]
     fn telemetry_export_is_deterministic_for_fixed_seed_and_config() {
         let run_a = run_scenario(&ScenarioConfig::default(), false);
@@ -88,4 +309,57 @@ mod tests {
 
         assert_eq!(out_a, out_b);
     }
+
+    #[test]
+    fn topology_csv_has_rows_per_node_bus_and_line_at_kept_steps() {
+        let network = RadialNetwork::new(
+            0.4,
+            vec![ROOT_BUS.to_string(), "b1".to_string()],
+            vec![Line {
+                name: "L1".to_string(),
+                from: 0,
+                to: 1,
+                r_ohm: 0.05,
+                x_ohm: 0.0,
+                ampacity_a: Some(400.0),
+            }],
+        )
+        .expect("radial");
+        let topology = Topology {
+            sites: vec![SiteSpec {
+                name: "home".to_string(),
+                feeder: 0,
+                bus: Some(1),
+                houses: 1,
+                limits: NodeLimits::new(5.0, 4.0),
+                solar_kw_peak_per_house: 0.0,
+            }],
+            feeders: vec![FeederSpec {
+                name: "F1".to_string(),
+                limits: NodeLimits::new(50.0, 40.0),
+            }],
+            substation: NodeLimits::unlimited(),
+            network: Some(network),
+            voltage_min_pu: 0.95,
+            voltage_max_pu: 1.05,
+        };
+        let scenario = ScenarioConfig::default();
+        let result = run_topology(&scenario, &topology);
+
+        let mut out = Vec::new();
+        write_topology_csv(&mut out, &topology, &result, 24, 12)
+            .expect("csv export should succeed");
+
+        let csv = String::from_utf8(out).expect("csv output should be valid UTF-8");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], TOPOLOGY_SCHEMA_V1_HEADER);
+        // Substation, feeder, site, bus and line at steps 0 and 12.
+        assert_eq!(lines.len(), 1 + 2 * 5);
+        assert!(lines[1].starts_with("0,0.000000,Substation,substation,"));
+        assert!(lines[1].contains(",inf,inf,true,1.000000,,"));
+        assert!(lines[2].ends_with(",true,,,"));
+        assert!(lines[4].starts_with("0,0.000000,b1,bus,"));
+        assert!(lines[5].starts_with("0,0.000000,L1,line,,,,true,,"));
+        assert!(lines[6].starts_with("12,12.000000,Substation,"));
+    }
 }
@@ -101,10 +101,10 @@ fn spawn_api_process(bind_addr: &str) -> ChildGuard {
 fn wait_for_server(bind_addr: &str, timeout: Duration) {
     let start = Instant::now();
     loop {
-        if let Ok((status, _)) = http_get(bind_addr, "/state") {
-            if status == 200 {
-                return;
-            }
+        if let Ok((status, _)) = http_get(bind_addr, "/state")
+            && status == 200
+        {
+            return;
         }
 
         if start.elapsed() >= timeout {